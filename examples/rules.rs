@@ -0,0 +1,37 @@
+//! Using slpy as an embedded rule language.
+//!
+//! Run with `cargo run --example rules`.
+use slpy_rust::{Interpreter, Kind};
+
+/// Scoring rules a user might keep in a config file.
+const RULES: &str = "
+bonus = streak * 5
+penalty = misses * misses
+score = max(base + bonus - penalty, 0)
+";
+
+fn main() {
+    let mut interp = Interpreter::default();
+    interp.register_fn("max", |a: i32, b: i32| a.max(b));
+
+    for (base, streak, misses) in [(100, 3, 1), (80, 0, 4), (50, 10, 0), (10, 0, 5)] {
+        interp.set("base", base);
+        interp.set("streak", streak);
+        interp.set("misses", misses);
+        interp.eval_str(RULES).expect("rules are valid");
+        println!(
+            "base {base}, streak {streak}, misses {misses} => score {}",
+            interp.get("score").expect("rules set score")
+        );
+    }
+
+    // globals from earlier calls are still around for ad-hoc queries
+    let doubled = interp.eval_expr("score * 2").expect("score is defined");
+    println!("last score doubled: {doubled}");
+
+    // errors come back as values with a kind and a span, not as panics
+    match interp.eval_expr("score // (misses - misses)") {
+        Err(e) if e.kind == Kind::DivisionByZero => println!("caught: {e}"),
+        other => unreachable!("expected a division by zero, got {other:?}"),
+    }
+}
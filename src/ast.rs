@@ -2,28 +2,991 @@
 //!
 //! TODO: error handling
 
-use std::collections::{HashMap, VecDeque};
-use std::io::Write;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
 
+use crate::builtin::Builtin;
+use crate::cancel::CancelHandle;
+use crate::class::{Class, Instance, Method, Super};
+use crate::env::Env;
 use crate::error::{Error, Kind, Result};
-use crate::tokenizer::{Op, Token, TokenKind, TokenStream};
+use crate::exception::{ExcType, Exception};
+use crate::iter::{GenState, Generator, SeqIter};
+use crate::limits::{Limits, Usage};
+use crate::module::{Module, Modules};
+use crate::native::{Native, NativeError, NativeFn};
+use crate::object::SlpyObject;
+use crate::signature::Signature;
+use crate::tokenizer::{Op, TokenKind, TokenStream, Tokenizer};
 use crate::{Loc, Span};
 
-type SlpyObject = i32;
+mod bindings;
+mod cfg;
+mod dot;
+mod export;
+mod unparse;
 
-#[derive(Default)]
-pub struct Context(HashMap<String, SlpyObject>);
+pub(crate) use bindings::Bindings;
+pub(crate) use export::write_str;
+pub use export::AST_SCHEMA_VERSION;
 
-impl Context {
-    fn get(&self, name: &str) -> Option<SlpyObject> {
-        self.0.get(name).copied()
+/// The state of a running program: its variables, the native functions it can call, the
+/// handles `print` and `input` use, and its execution budget.
+pub struct Context<'io> {
+    /// The scope code is running in; the globals at the top level, or a function's locals.
+    scope: Env,
+    natives: HashMap<String, Native<'io>>,
+    input: Box<dyn BufRead + 'io>,
+    output: Box<dyn Write + 'io>,
+    limits: Limits,
+    usage: Usage,
+    cancel: CancelHandle,
+    /// The errors being handled by the enclosing `except` blocks, innermost last.
+    handling: Vec<Error>,
+    /// The functions being run, innermost last, so `super()` can find its receiver.
+    calls: Vec<Rc<Function>>,
+    /// Where the generator being resumed left off, outermost statement last; each statement on
+    /// the way down to the `yield` takes its own entry.
+    suspended: Vec<Resume>,
+    /// The file the running code was read from, if any.
+    file: Option<Arc<String>>,
+    modules: Modules,
+    /// The program's command-line arguments, as the list `argv`.
+    argv: SlpyObject,
+}
+
+impl Default for Context<'_> {
+    /// A context reading from stdin and writing to stdout.
+    fn default() -> Self {
+        // not a `StdinLock`, which would hang the next context made on this thread
+        Self::new(std::io::BufReader::new(std::io::stdin()), std::io::stdout())
+    }
+}
+
+impl<'io> Context<'io> {
+    /// Create a context that reads `input` from `input` and `print`s to `output`.
+    pub fn new(input: impl BufRead + 'io, output: impl Write + 'io) -> Self {
+        Self {
+            scope: Env::default(),
+            natives: HashMap::new(),
+            input: Box::new(input),
+            output: Box::new(output),
+            limits: Limits::default(),
+            usage: Usage::default(),
+            cancel: CancelHandle::default(),
+            handling: Vec::new(),
+            calls: Vec::new(),
+            suspended: Vec::new(),
+            file: None,
+            modules: Modules::default(),
+            argv: SlpyObject::list_of(Vec::new()),
+        }
+    }
+
+    /// Bound the work programs run in this context may do.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Look for imported modules in `dir` when they aren't next to the file importing them.
+    pub fn add_module_path(&mut self, dir: impl Into<PathBuf>) {
+        self.modules.path.push(dir.into());
+    }
+
+    /// Give programs `args` as their `argv`, starting with the program's own name.
+    pub fn set_argv(&mut self, args: impl IntoIterator<Item = String>) {
+        self.argv = SlpyObject::list_of(args.into_iter().map(SlpyObject::Str).collect());
+    }
+
+    /// Leave `assert` statements out of the modules programs import.
+    pub(crate) fn set_strip_asserts(&mut self, strip: bool) {
+        self.modules.strip_asserts = strip;
+    }
+
+    /// Run `prgm`, which was read from `file`: errors report that path, and imports are found
+    /// next to it.
+    pub(crate) fn eval_file(&mut self, prgm: &Prgm, file: Arc<String>) -> Result<()> {
+        let outer = self.file.replace(Arc::clone(&file));
+        let result = prgm.eval(self);
+        self.file = outer;
+        result.map_err(|e| e.in_file(Some(&file)))
+    }
+
+    /// Start a fresh budget, e.g. before running another program in the same context.
+    pub fn reset_usage(&mut self) {
+        self.usage = Usage::default();
+    }
+
+    /// A handle that stops whatever this context is running, e.g. from another thread.
+    #[must_use]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Clear the request to stop that ended `result`, now that the evaluation it stopped has
+    /// returned.
+    pub(crate) fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        if result.as_ref().is_err_and(|e| e.kind == Kind::Cancelled) {
+            self.cancel.reset();
+        }
+        result
+    }
+
+    /// Count one evaluation step.
+    fn step(&mut self, span: Span) -> Result<()> {
+        self.usage.steps += 1;
+        if self.cancel.is_requested() {
+            return Err(Error::new(Kind::Cancelled, span));
+        }
+        if self
+            .limits
+            .max_steps
+            .is_some_and(|max| self.usage.steps > max)
+        {
+            return Err(Error::new(Kind::StepLimit, span));
+        }
+        // reading the clock is slow compared to a step, so only check it every so often
+        if self.usage.steps.is_multiple_of(1024)
+            && self
+                .limits
+                .max_time
+                .is_some_and(|max| self.usage.started.elapsed() > max)
+        {
+            return Err(Error::new(Kind::Timeout, span));
+        }
+        Ok(())
+    }
+
+    /// Enter a function call.
+    fn enter_call(&mut self, span: Span) -> Result<()> {
+        if self
+            .limits
+            .max_depth
+            .is_some_and(|max| self.usage.depth >= max)
+        {
+            return Err(Error::new(Kind::DepthLimit, span));
+        }
+        self.usage.depth += 1;
+        Ok(())
+    }
+
+    /// Run the body of a call with `f`, moving to a fresh stretch of stack first if this one is
+    /// running low, so that however deep calls are allowed to nest, they can't overflow it.
+    fn on_stack<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        // enough for what one call evaluates before the next is entered, even unoptimized
+        const RED_ZONE: usize = 1024 * 1024;
+        const SEGMENT: usize = 16 * 1024 * 1024;
+        stacker::maybe_grow(RED_ZONE, SEGMENT, || f(self))
+    }
+
+    /// Leave a function call.
+    fn exit_call(&mut self) {
+        self.usage.depth -= 1;
+    }
+
+    /// Check that a value of length `len` may be created.
+    fn check_size(&self, len: usize, span: Span) -> Result<()> {
+        if self.limits.max_value_size.is_some_and(|max| len > max) {
+            return Err(Error::new(Kind::ValueSizeLimit, span));
+        }
+        Ok(())
+    }
+
+    /// Look up a variable, falling back to the natives, the constants, `argv`, the built-in
+    /// functions and the built-in exception classes unless it's a local of the running function.
+    pub(crate) fn get(&self, name: &str) -> Option<SlpyObject> {
+        if self.scope.is_local(name) {
+            return self.scope.get(name);
+        }
+        self.scope.get(name).or_else(|| match name {
+            _ if self.natives.contains_key(name) => Some(SlpyObject::Native(name.to_string())),
+            "argv" => Some(self.argv.clone()),
+            "None" => Some(SlpyObject::None),
+            "True" => Some(SlpyObject::Bool(true)),
+            "False" => Some(SlpyObject::Bool(false)),
+            _ => Builtin::from_name(name)
+                .map(SlpyObject::Builtin)
+                .or_else(|| ExcType::from_name(name).map(SlpyObject::ExcType)),
+        })
+    }
+
+    /// The error for reading `name` at `span` when `get` doesn't find it.
+    fn undefined(&self, name: &str, span: Span) -> Error {
+        let kind = if self.scope.is_local(name) {
+            Kind::UnboundLocal(name.to_string())
+        } else {
+            Kind::UndefinedName(name.to_string())
+        };
+        Error::new(kind, span)
+    }
+
+    pub(crate) fn set(&mut self, name: String, val: SlpyObject) {
+        self.scope.set(name, val);
+    }
+
+    /// Every name `get` finds, sorted, e.g. to complete them in the REPL.
+    pub(crate) fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .scope
+            .vars()
+            .into_iter()
+            .map(|(name, _)| name)
+            .chain(self.natives.keys().cloned())
+            .chain(["argv", "None", "True", "False"].map(String::from))
+            .chain(Builtin::ALL.map(|b| b.name().to_string()))
+            .chain(ExcType::ALL.map(|t| t.name().to_string()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Register a Rust closure as a function callable from slpy as `name`.
+    ///
+    /// The number of arguments is checked at each call, and arguments and the return value are
+    /// converted with `FromSlpy` and `IntoSlpy`.
+    pub fn register_fn<Args>(&mut self, name: impl Into<String>, f: impl NativeFn<Args> + 'io) {
+        self.natives.insert(name.into(), Native::new(f));
+    }
+
+    /// Register a Rust closure taking the parameters in `sig`, which it receives in the order
+    /// `Signature` describes.
+    ///
+    /// # Panics
+    /// If `f` doesn't take one argument for each of the signature's slots.
+    pub fn register_fn_with_signature<Args>(
+        &mut self,
+        name: impl Into<String>,
+        sig: Signature,
+        f: impl NativeFn<Args> + 'io,
+    ) {
+        self.natives
+            .insert(name.into(), Native::with_signature(sig, f));
+    }
+
+    /// Call `func`, reporting errors at `span`.
+    fn call(
+        &mut self,
+        func: SlpyObject,
+        args: Vec<SlpyObject>,
+        mut kwargs: Vec<(String, SlpyObject)>,
+        span: Span,
+    ) -> Result<SlpyObject> {
+        match func {
+            SlpyObject::Function(func) => self.call_function(&func, args, kwargs, span),
+            SlpyObject::Method(method) => {
+                let args = std::iter::once(method.receiver.clone())
+                    .chain(args)
+                    .collect();
+                self.call_function(&method.func, args, kwargs, span)
+            }
+            SlpyObject::Class(class) => self.instantiate(&class, args, kwargs, span),
+            SlpyObject::Native(name) => self.call_native(&name, args, kwargs, span),
+            SlpyObject::Builtin(builtin) if !kwargs.is_empty() => Err(Error::new(
+                Kind::UnexpectedKeyword {
+                    name: builtin.name().to_string(),
+                    param: kwargs.swap_remove(0).0,
+                },
+                span,
+            )),
+            SlpyObject::Builtin(Builtin::Super) => self.new_super(args, span),
+            SlpyObject::Builtin(Builtin::Iter) => match <[SlpyObject; 1]>::try_from(args) {
+                Ok([val]) => self.iter(val, span),
+                Err(args) => Err(Error::new(
+                    Kind::Arity {
+                        name: "iter".to_string(),
+                        expected: 1,
+                        got: args.len(),
+                    },
+                    span,
+                )),
+            },
+            SlpyObject::Builtin(Builtin::Next) => self.call_next(args, span),
+            SlpyObject::Builtin(Builtin::Exit) => Self::call_exit(args, span),
+            SlpyObject::Builtin(Builtin::Getenv) => Self::call_getenv(args, span),
+            SlpyObject::ExcType(ty) if !kwargs.is_empty() => Err(Error::new(
+                Kind::UnexpectedKeyword {
+                    name: ty.name().to_string(),
+                    param: kwargs.swap_remove(0).0,
+                },
+                span,
+            )),
+            SlpyObject::ExcType(ty) => match <[SlpyObject; 1]>::try_from(args) {
+                Ok([message]) => Ok(SlpyObject::Exception(Exception {
+                    ty,
+                    message: message.to_string(),
+                })),
+                Err(args) if args.is_empty() => Ok(SlpyObject::Exception(Exception {
+                    ty,
+                    message: String::new(),
+                })),
+                Err(args) => Err(Error::new(
+                    Kind::Arity {
+                        name: ty.name().to_string(),
+                        expected: 1,
+                        got: args.len(),
+                    },
+                    span,
+                )),
+            },
+            val => Err(Error::new(
+                Kind::Type(format!("{} object is not callable", val.type_name())),
+                span,
+            )),
+        }
+    }
+
+    /// Call a function defined in slpy, in a new scope inside the one it was defined in.
+    ///
+    /// Calling a generator function only binds its arguments; its body runs as values are asked
+    /// for.
+    fn call_function(
+        &mut self,
+        func: &Rc<Function>,
+        args: Vec<SlpyObject>,
+        kwargs: Vec<(String, SlpyObject)>,
+        span: Span,
+    ) -> Result<SlpyObject> {
+        let args = func.sig.bind(&func.name, args, kwargs, span)?;
+        let scope = func.closure.call(Rc::clone(&func.locals));
+        for (param, arg) in func.sig.names().zip(args) {
+            scope.set(param.to_string(), arg);
+        }
+        if func.generator {
+            let gen = Generator::new(Rc::clone(func), scope);
+            return Ok(SlpyObject::Generator(Rc::new(gen)));
+        }
+        self.enter_call(span)?;
+        let caller = std::mem::replace(&mut self.scope, scope);
+        let caller_file = std::mem::replace(&mut self.file, func.file.clone());
+        self.calls.push(Rc::clone(func));
+        let val = self.on_stack(|ctx| match &func.body {
+            Body::Blck(body) => body.eval(ctx).map(|flow| match flow {
+                Flow::Return(val) => val,
+                Flow::Normal | Flow::Break | Flow::Continue => SlpyObject::None,
+                Flow::Yield(_) => unreachable!("only generators yield"),
+            }),
+            Body::Expn(body) => body.eval(ctx),
+        });
+        self.calls.pop();
+        let file = std::mem::replace(&mut self.file, caller_file);
+        self.scope = caller;
+        self.exit_call();
+        val.map_err(|e| e.in_call_from(&func.name, span, file.as_ref(), self.file.as_ref()))
+    }
+
+    /// Run `gen` until it yields its next value, or return `None` once it's finished.
+    fn resume(&mut self, gen: &Generator, span: Span) -> Result<Option<SlpyObject>> {
+        self.enter_call(span)?;
+        let (scope, at) = match gen.state.replace(GenState::Running) {
+            GenState::Suspended { scope, at } => (scope, at),
+            state => {
+                self.exit_call();
+                let running = matches!(state, GenState::Running);
+                gen.state.replace(state);
+                if running {
+                    return Err(Error::new(
+                        Kind::Exception(Exception {
+                            ty: ExcType::ValueError,
+                            message: "generator already executing".to_string(),
+                        }),
+                        span,
+                    ));
+                }
+                return Ok(None);
+            }
+        };
+        let Body::Blck(body) = &gen.func.body else {
+            unreachable!("lambdas can't yield")
+        };
+
+        let caller = std::mem::replace(&mut self.scope, scope);
+        let caller_file = std::mem::replace(&mut self.file, gen.func.file.clone());
+        let outer = std::mem::replace(&mut self.suspended, at);
+        self.calls.push(Rc::clone(&gen.func));
+        let result = self.on_stack(|ctx| body.eval(ctx));
+        self.calls.pop();
+        let at = std::mem::replace(&mut self.suspended, outer);
+        let file = std::mem::replace(&mut self.file, caller_file);
+        let scope = std::mem::replace(&mut self.scope, caller);
+        self.exit_call();
+        let in_call =
+            |e: Error| e.in_call_from(&gen.func.name, span, file.as_ref(), self.file.as_ref());
+
+        if let Ok(Flow::Yield(val)) = result {
+            gen.state.replace(GenState::Suspended { scope, at });
+            return Ok(Some(val));
+        }
+        gen.state.replace(GenState::Finished);
+        match result {
+            Ok(_) => Ok(None),
+            // as in Python, a `StopIteration` escaping the body would otherwise look like the
+            // generator finishing
+            Err(err)
+                if err
+                    .kind
+                    .as_exception()
+                    .is_some_and(|e| e.ty == ExcType::StopIteration) =>
+            {
+                let mut runtime = Error::new(
+                    Kind::Exception(Exception {
+                        ty: ExcType::RuntimeError,
+                        message: "generator raised StopIteration".to_string(),
+                    }),
+                    err.span,
+                );
+                runtime.file = err.file;
+                Err(in_call(runtime))
+            }
+            Err(err) => Err(in_call(err)),
+        }
+    }
+
+    /// An iterator over `val`: itself if it's already one, otherwise over a snapshot of its items.
+    fn iter(&self, val: SlpyObject, span: Span) -> Result<SlpyObject> {
+        let items = match val {
+            SlpyObject::Iter(_) | SlpyObject::Generator(_) => return Ok(val),
+            SlpyObject::Tuple(items) => items.to_vec(),
+            SlpyObject::List(items) | SlpyObject::Set(items) => items.borrow().clone(),
+            SlpyObject::Str(s) => s.chars().map(|c| SlpyObject::Str(c.to_string())).collect(),
+            SlpyObject::Dict(entries) => entries.borrow().iter().map(|(k, _)| k.clone()).collect(),
+            val => {
+                return Err(Error::new(
+                    Kind::Type(format!("{} object is not iterable", val.type_name())),
+                    span,
+                ))
+            }
+        };
+        Ok(SlpyObject::Iter(Rc::new(SeqIter::new(items))))
+    }
+
+    /// Import the module `name`, running its file the first time it's imported.
+    fn import(&mut self, name: &str, span: Span) -> Result<Rc<Module>> {
+        let dir = self
+            .file
+            .as_deref()
+            .and_then(|file| Path::new(file).parent())
+            .unwrap_or_else(|| Path::new(""));
+        let path = self
+            .modules
+            .find(name, dir)
+            .ok_or_else(|| Error::new(Kind::ModuleNotFound(name.to_string()), span))?;
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if let Some(module) = self.modules.get(&key) {
+            return Ok(module);
+        }
+        if let Some(cycle) = self.modules.cycle(&key, name) {
+            return Err(Error::new(
+                Kind::Import(format!("circular import of `{}`: {}", name, cycle)),
+                span,
+            ));
+        }
+
+        let file = Arc::new(path.display().to_string());
+        let source = std::fs::read_to_string(&path).map_err(|e| {
+            Error::new(Kind::Import(format!("cannot read `{}`: {}", file, e)), span)
+        })?;
+        let in_module = |e: Error| e.in_file(Some(&file));
+        let mut tokens = Tokenizer::lex(&source).map_err(in_module)?;
+        tokens.set_strip_asserts(self.modules.strip_asserts);
+        let prgm = Prgm::parse(&mut tokens).map_err(in_module)?;
+
+        self.enter_call(span)?;
+        let module = Rc::new(Module::new(name.to_string()));
+        self.modules.start(key, name.to_string());
+        let importer = std::mem::replace(&mut self.scope, module.globals.clone());
+        let importer_file = self.file.replace(Arc::clone(&file));
+        let result = self.on_stack(|ctx| prgm.eval(ctx));
+        self.file = importer_file;
+        self.scope = importer;
+        self.modules
+            .finish(result.is_ok().then(|| Rc::clone(&module)));
+        self.exit_call();
+        result.map_err(|e| e.in_call_from("<module>", span, Some(&file), self.file.as_ref()))?;
+        Ok(module)
+    }
+
+    /// The next value from the iterator `iter`, or `None` once it's exhausted.
+    fn next(&mut self, iter: &SlpyObject, span: Span) -> Result<Option<SlpyObject>> {
+        match iter {
+            SlpyObject::Iter(iter) => Ok(iter.next()),
+            SlpyObject::Generator(gen) => self.resume(gen, span),
+            val => Err(Error::new(
+                Kind::Type(format!("{} object is not an iterator", val.type_name())),
+                span,
+            )),
+        }
+    }
+
+    /// Call `next(it)`, which raises `StopIteration` once `it` is exhausted, or
+    /// `next(it, default)`, which returns `default` instead.
+    fn call_next(&mut self, args: Vec<SlpyObject>, span: Span) -> Result<SlpyObject> {
+        let (iter, default) = match <[SlpyObject; 2]>::try_from(args) {
+            Ok([iter, default]) => (iter, Some(default)),
+            Err(args) => match <[SlpyObject; 1]>::try_from(args) {
+                Ok([iter]) => (iter, None),
+                Err(args) => {
+                    return Err(Error::new(
+                        Kind::Arity {
+                            name: "next".to_string(),
+                            expected: if args.is_empty() { 1 } else { 2 },
+                            got: args.len(),
+                        },
+                        span,
+                    ))
+                }
+            },
+        };
+        match self.next(&iter, span)? {
+            Some(val) => Ok(val),
+            None => default.ok_or_else(|| {
+                Error::new(
+                    Kind::Exception(Exception {
+                        ty: ExcType::StopIteration,
+                        message: String::new(),
+                    }),
+                    span,
+                )
+            }),
+        }
+    }
+
+    /// Call `exit()` or `exit(status)`, which unwinds the whole program, running `finally` blocks
+    /// on the way out.
+    fn call_exit(args: Vec<SlpyObject>, span: Span) -> Result<SlpyObject> {
+        let status = match <[SlpyObject; 1]>::try_from(args) {
+            Ok([SlpyObject::Int(status)]) => status,
+            Ok([SlpyObject::None]) => 0,
+            Ok([val]) => {
+                return Err(Error::new(
+                    Kind::Type(format!(
+                        "exit status must be an int, not {}",
+                        val.type_name()
+                    )),
+                    span,
+                ))
+            }
+            Err(args) if args.is_empty() => 0,
+            Err(args) => {
+                return Err(Error::new(
+                    Kind::Arity {
+                        name: "exit".to_string(),
+                        expected: 1,
+                        got: args.len(),
+                    },
+                    span,
+                ))
+            }
+        };
+        Err(Error::new(Kind::Exit(status), span))
+    }
+
+    /// Call `getenv(name)`, which returns `None` if the variable isn't set, or
+    /// `getenv(name, default)`, which returns `default` instead.
+    fn call_getenv(args: Vec<SlpyObject>, span: Span) -> Result<SlpyObject> {
+        let (name, default) = match <[SlpyObject; 2]>::try_from(args) {
+            Ok([name, default]) => (name, default),
+            Err(args) => match <[SlpyObject; 1]>::try_from(args) {
+                Ok([name]) => (name, SlpyObject::None),
+                Err(args) => {
+                    return Err(Error::new(
+                        Kind::Arity {
+                            name: "getenv".to_string(),
+                            expected: if args.is_empty() { 1 } else { 2 },
+                            got: args.len(),
+                        },
+                        span,
+                    ))
+                }
+            },
+        };
+        let SlpyObject::Str(name) = name else {
+            return Err(Error::new(
+                Kind::Type(format!(
+                    "environment variable names must be str, not {}",
+                    name.type_name()
+                )),
+                span,
+            ));
+        };
+        Ok(std::env::var(name).map_or(default, SlpyObject::Str))
+    }
+
+    /// Create an instance of `class`, passing the arguments to its `__init__`.
+    fn instantiate(
+        &mut self,
+        class: &Rc<Class>,
+        args: Vec<SlpyObject>,
+        kwargs: Vec<(String, SlpyObject)>,
+        span: Span,
+    ) -> Result<SlpyObject> {
+        let obj = SlpyObject::Instance(Rc::new(Instance::new(Rc::clone(class))));
+        match obj.get_attr("__init__") {
+            Some(init) => match self.call(init, args, kwargs, span)? {
+                SlpyObject::None => {}
+                val => {
+                    return Err(Error::new(
+                        Kind::Type(format!(
+                            "`__init__` should return None, not {}",
+                            val.type_name()
+                        )),
+                        span,
+                    ))
+                }
+            },
+            None if !args.is_empty() || !kwargs.is_empty() => {
+                return Err(Error::new(
+                    Kind::Type(format!("`{}` takes no arguments", class.name)),
+                    span,
+                ))
+            }
+            None => {}
+        }
+        Ok(obj)
+    }
+
+    /// Call `super(class, obj)`, or `super()` in a method, which uses the class the method was
+    /// defined in and its first argument.
+    fn new_super(&self, args: Vec<SlpyObject>, span: Span) -> Result<SlpyObject> {
+        let runtime = |message: &str| {
+            Error::new(
+                Kind::Exception(Exception {
+                    ty: ExcType::RuntimeError,
+                    message: message.to_string(),
+                }),
+                span,
+            )
+        };
+        let (class, receiver) = match <[SlpyObject; 2]>::try_from(args) {
+            Ok([SlpyObject::Class(class), receiver]) => (class, receiver),
+            Ok([val, _]) => {
+                return Err(Error::new(
+                    Kind::Type(format!(
+                        "`super` argument 1 must be a class, not {}",
+                        val.type_name()
+                    )),
+                    span,
+                ))
+            }
+            Err(args) if args.is_empty() => {
+                let Some(SlpyObject::Class(class)) = self.scope.get("__class__") else {
+                    return Err(runtime("super(): no class to look methods up from"));
+                };
+                let receiver = self
+                    .calls
+                    .last()
+                    .and_then(|func| func.sig.first_param())
+                    .and_then(|param| self.scope.get(param))
+                    .ok_or_else(|| runtime("super(): no arguments"))?;
+                (class, receiver)
+            }
+            Err(args) => {
+                return Err(Error::new(
+                    Kind::Arity {
+                        name: "super".to_string(),
+                        expected: 2,
+                        got: args.len(),
+                    },
+                    span,
+                ))
+            }
+        };
+        Ok(SlpyObject::Super(Rc::new(Super { class, receiver })))
+    }
+
+    /// Call the method `name` of `obj`'s class, if it's an instance of a class that has one.
+    fn call_dunder(
+        &mut self,
+        obj: &SlpyObject,
+        name: &str,
+        args: Vec<SlpyObject>,
+        span: Span,
+    ) -> Result<Option<SlpyObject>> {
+        let SlpyObject::Instance(inst) = obj else {
+            return Ok(None);
+        };
+        // like Python, look special methods up on the class, skipping the instance's attributes
+        let Some(method) = inst.class.lookup(name) else {
+            return Ok(None);
+        };
+        let method = Method::bind(obj, method);
+        self.call(method, args, Vec::new(), span).map(Some)
+    }
+
+    /// `obj[key]`, with its class's `__getitem__` if it has one.
+    fn get_item(&mut self, obj: &SlpyObject, key: &SlpyObject, span: Span) -> Result<SlpyObject> {
+        if let Some(val) = self.call_dunder(obj, "__getitem__", vec![key.clone()], span)? {
+            return Ok(val);
+        }
+        match obj {
+            SlpyObject::List(items) => {
+                let items = items.borrow();
+                Ok(items[seq_index(items.len(), key, obj, span)?].clone())
+            }
+            SlpyObject::Tuple(items) => Ok(items[seq_index(items.len(), key, obj, span)?].clone()),
+            SlpyObject::Str(s) => {
+                let i = seq_index(s.chars().count(), key, obj, span)?;
+                Ok(SlpyObject::Str(
+                    s.chars().nth(i).map(String::from).unwrap_or_default(),
+                ))
+            }
+            SlpyObject::Dict(entries) => {
+                check_hashable(key, span)?;
+                entries
+                    .borrow()
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v.clone())
+                    .ok_or_else(|| Error::new(Kind::Key(key.repr()), span))
+            }
+            _ => Err(Error::new(
+                Kind::Type(format!("{} object is not subscriptable", obj.type_name())),
+                span,
+            )),
+        }
+    }
+
+    /// `obj[key] = val`, with its class's `__setitem__` if it has one.
+    fn set_item(
+        &mut self,
+        obj: &SlpyObject,
+        key: SlpyObject,
+        val: SlpyObject,
+        span: Span,
+    ) -> Result<()> {
+        if self
+            .call_dunder(obj, "__setitem__", vec![key.clone(), val.clone()], span)?
+            .is_some()
+        {
+            return Ok(());
+        }
+        match obj {
+            SlpyObject::List(items) => {
+                let i = seq_index(items.borrow().len(), &key, obj, span)?;
+                items.borrow_mut()[i] = val;
+            }
+            SlpyObject::Dict(entries) => {
+                check_hashable(&key, span)?;
+                // compare keys before borrowing the entries mutably, not while borrowed
+                let found = entries.borrow().iter().position(|(k, _)| *k == key);
+                match found {
+                    Some(i) => entries.borrow_mut()[i].1 = val,
+                    None => {
+                        self.check_size(entries.borrow().len() + 1, span)?;
+                        entries.borrow_mut().push((key, val));
+                    }
+                }
+            }
+            _ => {
+                return Err(Error::new(
+                    Kind::Type(format!(
+                        "{} object does not support item assignment",
+                        obj.type_name()
+                    )),
+                    span,
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert `val` to a string the way `print` does, with its class's `__str__` if it has one.
+    fn str(&mut self, val: &SlpyObject, span: Span) -> Result<String> {
+        match self.call_dunder(val, "__str__", Vec::new(), span)? {
+            Some(SlpyObject::Str(s)) => Ok(s),
+            Some(s) => Err(Error::new(
+                Kind::Type(format!(
+                    "`__str__` returned non-string (type {})",
+                    s.type_name()
+                )),
+                span,
+            )),
+            None => Ok(val.to_string()),
+        }
+    }
+
+    /// Call the native function `name`, reporting errors at `span`.
+    fn call_native(
+        &mut self,
+        name: &str,
+        args: Vec<SlpyObject>,
+        kwargs: Vec<(String, SlpyObject)>,
+        span: Span,
+    ) -> Result<SlpyObject> {
+        self.enter_call(span)?;
+        let val = self.call_native_impl(name, args, kwargs, span);
+        self.exit_call();
+        let val = val?;
+        if let SlpyObject::Str(s) = &val {
+            self.check_size(s.len(), span)?;
+        }
+        Ok(val)
+    }
+
+    fn call_native_impl(
+        &mut self,
+        name: &str,
+        args: Vec<SlpyObject>,
+        mut kwargs: Vec<(String, SlpyObject)>,
+        span: Span,
+    ) -> Result<SlpyObject> {
+        let native = self
+            .natives
+            .get_mut(name)
+            .expect("only called for registered natives");
+        let args = match &native.sig {
+            Some(sig) => sig.bind(name, args, kwargs, span)?,
+            None if !kwargs.is_empty() => {
+                return Err(Error::new(
+                    Kind::UnexpectedKeyword {
+                        name: name.to_string(),
+                        param: kwargs.swap_remove(0).0,
+                    },
+                    span,
+                ))
+            }
+            None => args,
+        };
+        if args.len() != native.arity {
+            return Err(Error::new(
+                Kind::Arity {
+                    name: name.to_string(),
+                    expected: native.arity,
+                    got: args.len(),
+                },
+                span,
+            ));
+        }
+        (native.func)(args).map_err(|e| {
+            Error::new(
+                match e {
+                    NativeError::Argument(i, message) => {
+                        Kind::Type(format!("argument {} to `{}`: {}", i + 1, name, message))
+                    }
+                    NativeError::Raised(message) => Kind::Native {
+                        name: name.to_string(),
+                        message,
+                    },
+                },
+                span,
+            )
+        })
+    }
+
+    /// Read a line from the input into `buf`, returning the number of bytes read.
+    pub(crate) fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        self.input.read_line(buf)
+    }
+
+    /// Write `s` to the output and flush it.
+    ///
+    /// If that would exceed the output limit, only the part that fits is written.
+    fn write(&mut self, s: &str, span: Span) -> Result<()> {
+        let room = self
+            .limits
+            .max_output
+            .map_or(usize::MAX, |max| max - self.usage.output);
+        let bytes = &s.as_bytes()[..s.len().min(room)];
+        self.usage.output += bytes.len();
+        self.output
+            .write_all(bytes)
+            .and_then(|()| self.output.flush())
+            .map_err(|e| Error::new(Kind::Io(e.to_string()), span))?;
+        if bytes.len() < s.len() {
+            return Err(Error::new(Kind::OutputLimit, span));
+        }
+        Ok(())
+    }
+}
+
+/// A function defined with `def` or `lambda`, along with the scope it was defined in.
+pub struct Function {
+    pub(crate) name: String,
+    sig: Signature,
+    body: Body,
+    closure: Env,
+    /// The names local to its body.
+    locals: Rc<HashSet<String>>,
+    /// Whether its body contains a `yield`, so calling it makes a generator.
+    generator: bool,
+    /// The file it was defined in, if any.
+    file: Option<Arc<String>>,
+}
+
+impl Function {
+    /// The same function, but running in `closure`.
+    fn with_closure(&self, closure: Env) -> Self {
+        Self {
+            name: self.name.clone(),
+            sig: self.sig.clone(),
+            body: self.body.clone(),
+            closure,
+            locals: Rc::clone(&self.locals),
+            generator: self.generator,
+            file: self.file.clone(),
+        }
     }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
 
-    fn set(&mut self, name: String, val: i32) {
-        self.0.insert(name, val);
+impl Eq for Function {}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<function {}>", self.name)
     }
 }
 
+#[derive(Clone)]
+enum Body {
+    Blck(Rc<Blck>),
+    Expn(Rc<Expn>),
+}
+
+/// How a statement finished: normally, or by jumping out of the enclosing loop or function.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(SlpyObject),
+    /// A generator yielded the value and is suspended until the next one is asked for.
+    Yield(SlpyObject),
+}
+
+/// Where a statement a generator is suspended in left off, so it can pick up from there.
+pub(crate) enum Resume {
+    /// At the statement with this index.
+    Blck(usize),
+    /// At the `yield` itself, which finishes when resumed.
+    Yield,
+    /// In the `if` branch if true, otherwise the `else` branch.
+    Cond(bool),
+    /// In the body of a `while` loop.
+    Whle,
+    /// In the body of a `for` loop over this iterator.
+    For(SlpyObject),
+    Try(TryPart),
+}
+
+/// A part of a `try` statement.
+pub(crate) enum TryPart {
+    Body,
+    /// The handler with this index, and the error it's handling.
+    Handler(usize, Error),
+    Else,
+    /// The `finally` block, and what to do after it unless it jumps or raises itself.
+    Finally(Result<Flow>),
+}
+
 pub trait Ast: Sized {
     type Output;
 
@@ -31,7 +994,7 @@ pub trait Ast: Sized {
 
     fn parse(tokens: &mut TokenStream) -> Result<Self>;
 
-    fn eval(self, ctx: &mut Context) -> Result<Self::Output>;
+    fn eval(&self, ctx: &mut Context) -> Result<Self::Output>;
 
     fn parse_and_eval(mut tokens: TokenStream, ctx: &mut Context) -> Result<Self::Output> {
         Self::parse(&mut tokens)?.eval(ctx)
@@ -40,12 +1003,13 @@ pub trait Ast: Sized {
     fn dump(&self, indent: usize) -> String;
 }
 
-struct Blck {
+#[derive(PartialEq, Eq, Debug)]
+pub struct Blck {
     stmts: VecDeque<Stmt>,
 }
 
 impl Ast for Blck {
-    type Output = ();
+    type Output = Flow;
 
     fn span(&self) -> Span {
         Span {
@@ -62,19 +1026,33 @@ impl Ast for Blck {
 
     fn parse(tokens: &mut TokenStream) -> Result<Self> {
         let mut stmts = VecDeque::new();
-        while tokens.current().is_some() {
-            stmts.push_back(Stmt::parse(tokens)?);
-            tokens.eat(&TokenKind::NewLine)?;
+        while let Some(tkn) = tokens.current() {
+            // skip blank lines
+            if tkn.kind == TokenKind::NewLine {
+                tokens.advance();
+                continue;
+            }
+            Self::parse_stmt(tokens, &mut stmts)?;
         }
 
         Ok(Self { stmts })
     }
 
-    fn eval(self, ctx: &mut Context) -> Result<Self::Output> {
-        for stmt in self.stmts {
-            stmt.eval(ctx)?;
+    fn eval(&self, ctx: &mut Context) -> Result<Self::Output> {
+        let start = match ctx.suspended.pop() {
+            Some(Resume::Blck(i)) => i,
+            _ => 0,
+        };
+        for (i, stmt) in self.stmts.iter().enumerate().skip(start) {
+            let flow = stmt.eval(ctx)?;
+            if let Flow::Yield(_) = flow {
+                ctx.suspended.push(Resume::Blck(i));
+            }
+            if flow != Flow::Normal {
+                return Ok(flow);
+            }
         }
-        Ok(())
+        Ok(Flow::Normal)
     }
 
     fn dump(&self, indent: usize) -> String {
@@ -88,167 +1066,1580 @@ impl Ast for Blck {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
-pub struct Stmt {
-    data: StmtData,
-    span: Span,
-}
+impl Blck {
+    /// Parse a statement and the newline after it, adding it to `stmts` unless it's stripped.
+    fn parse_stmt(tokens: &mut TokenStream, stmts: &mut VecDeque<Stmt>) -> Result<()> {
+        let stmt = Stmt::parse(tokens)?;
+        if !stmt.is_compound() {
+            tokens.eat(&TokenKind::NewLine)?;
+        }
+        if !(tokens.strips_asserts() && matches!(stmt.data, StmtData::Asrt { .. })) {
+            stmts.push_back(stmt);
+        }
+        Ok(())
+    }
 
-impl Stmt {
-    fn parse_asgn(tokens: &mut TokenStream) -> Result<Self> {
-        let start = tokens.current_or()?.span.start;
-        let name = match tokens.take().kind {
-            TokenKind::Ident(ident) => ident,
-            _ => {
-                return Err(Error {
-                    kind: Kind::Parser,
-                    span: tokens.current_or()?.span,
-                });
+    /// Parse the block after a compound statement's header, from the colon through the dedent
+    /// that closes it, or a single simple statement on the same line as the header.
+    fn parse_suite(tokens: &mut TokenStream) -> Result<Self> {
+        tokens.eat(&TokenKind::Colon)?;
+        if tokens.current_or()?.kind != TokenKind::NewLine {
+            let mut stmts = VecDeque::new();
+            let tkn = tokens.current_or()?.span;
+            Self::parse_stmt(tokens, &mut stmts)?;
+            if stmts.back().is_some_and(Stmt::is_compound) {
+                return Err(Error::new(Kind::Parser, tkn));
             }
-        };
+            return Ok(Self { stmts });
+        }
+        tokens.eat(&TokenKind::NewLine)?;
+        while tokens
+            .current()
+            .is_some_and(|t| t.kind == TokenKind::NewLine)
+        {
+            tokens.advance();
+        }
+        tokens.eat(&TokenKind::Indent)?;
+
+        let mut stmts = VecDeque::new();
+        while tokens.current_or()?.kind != TokenKind::Dedent {
+            // skip blank lines
+            if tokens.current_or()?.kind == TokenKind::NewLine {
+                tokens.advance();
+                continue;
+            }
+            Self::parse_stmt(tokens, &mut stmts)?;
+        }
+        tokens.eat(&TokenKind::Dedent)?;
+
+        Ok(Self { stmts })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Stmt {
+    data: StmtData,
+    span: Span,
+}
+
+impl Stmt {
+    /// Evaluate the statement, returning its value if it's an expression statement.
+    pub(crate) fn eval_interactive(&self, ctx: &mut Context) -> Result<Option<SlpyObject>> {
+        if let StmtData::Expn(expn) = &self.data {
+            ctx.step(self.span)?;
+            return expn.eval(ctx).map(Some);
+        }
+        self.eval(ctx).map(|_| None)
+    }
+
+    /// Whether the statement ends with a block, and so isn't followed by a newline of its own.
+    const fn is_compound(&self) -> bool {
+        matches!(
+            self.data,
+            StmtData::Cond { .. }
+                | StmtData::Whle { .. }
+                | StmtData::For { .. }
+                | StmtData::Defn { .. }
+                | StmtData::Clss { .. }
+                | StmtData::Try { .. }
+        )
+    }
+
+    /// Parse `pass`, `break` or `continue`.
+    fn parse_jump(tokens: &mut TokenStream) -> Result<Self> {
+        let (name, span) = parse_name(tokens)?;
+        let data = match name.as_str() {
+            "pass" => StmtData::Pass,
+            _ if !tokens.in_loop => {
+                return Err(Error::new(
+                    Kind::Syntax(format!("`{}` outside loop", name)),
+                    span,
+                ))
+            }
+            "break" => StmtData::Brek,
+            _ => StmtData::Cont,
+        };
+        Ok(Self { data, span })
+    }
+
+    /// Parse an `if` or `elif` and the branches after it.
+    fn parse_cond(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.advance();
+        let cond = Expn::parse_named(tokens)?;
+        let body = Blck::parse_suite(tokens)?;
+        let orelse = if at_keyword(tokens, "elif") {
+            let mut stmts = VecDeque::new();
+            stmts.push_back(Self::parse_cond(tokens)?);
+            Some(Blck { stmts })
+        } else if at_keyword(tokens, "else") {
+            tokens.advance();
+            Some(Blck::parse_suite(tokens)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            span: Span {
+                start,
+                end: orelse.as_ref().unwrap_or(&body).span().end,
+            },
+            data: StmtData::Cond { cond, body, orelse },
+        })
+    }
+
+    fn parse_whle(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("while".to_string()))?;
+        let cond = Expn::parse_named(tokens)?;
+        let in_loop = std::mem::replace(&mut tokens.in_loop, true);
+        let body = Blck::parse_suite(tokens);
+        tokens.in_loop = in_loop;
+        let body = body?;
+        Ok(Self {
+            span: Span {
+                start,
+                end: body.span().end,
+            },
+            data: StmtData::Whle { cond, body },
+        })
+    }
+
+    fn parse_for(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("for".to_string()))?;
+        let (name, _) = parse_name(tokens)?;
+        tokens.bind(&name);
+        tokens.eat(&TokenKind::Ident("in".to_string()))?;
+        let iter = Expn::parse(tokens)?;
+        let in_loop = std::mem::replace(&mut tokens.in_loop, true);
+        let body = Blck::parse_suite(tokens);
+        tokens.in_loop = in_loop;
+        let body = body?;
+        Ok(Self {
+            span: Span {
+                start,
+                end: body.span().end,
+            },
+            data: StmtData::For { name, iter, body },
+        })
+    }
+
+    fn parse_defn(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("def".to_string()))?;
+        let (name, _) = parse_name(tokens)?;
+        tokens.eat(&TokenKind::LParen)?;
+        let params = parse_params(tokens, &TokenKind::RParen)?;
+        tokens.eat(&TokenKind::RParen)?;
+        tokens.bind(&name);
+
+        let in_function = std::mem::replace(&mut tokens.in_function, true);
+        let in_loop = std::mem::replace(&mut tokens.in_loop, false);
+        let yields = std::mem::replace(&mut tokens.yields, false);
+        enter_function(tokens, &params);
+        let body = Blck::parse_suite(tokens);
+        let locals = tokens.leave_body();
+        tokens.in_function = in_function;
+        tokens.in_loop = in_loop;
+        let generator = std::mem::replace(&mut tokens.yields, yields);
+        let body = body?;
+        Ok(Self {
+            span: Span {
+                start,
+                end: body.span().end,
+            },
+            data: StmtData::Defn {
+                name,
+                params,
+                body: Rc::new(body),
+                generator,
+                locals: locals?,
+            },
+        })
+    }
+
+    fn parse_clss(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("class".to_string()))?;
+        let (name, _) = parse_name(tokens)?;
+        let mut base = None;
+        if tokens.current_or()?.kind == TokenKind::LParen {
+            tokens.advance();
+            if tokens.current_or()?.kind != TokenKind::RParen {
+                base = Some(Expn::parse(tokens)?);
+            }
+            tokens.eat(&TokenKind::RParen)?;
+        }
+        tokens.bind(&name);
+
+        let in_function = std::mem::replace(&mut tokens.in_function, false);
+        let in_loop = std::mem::replace(&mut tokens.in_loop, false);
+        tokens.enter_body(false);
+        let body = Blck::parse_suite(tokens);
+        let locals = tokens.leave_body();
+        tokens.in_function = in_function;
+        tokens.in_loop = in_loop;
+        let body = body?;
+        locals?;
+        Ok(Self {
+            span: Span {
+                start,
+                end: body.span().end,
+            },
+            data: StmtData::Clss { name, base, body },
+        })
+    }
+
+    /// Run a class body and make a class of what it defines.
+    fn eval_clss(
+        name: &str,
+        base: Option<&Expn>,
+        body: &Blck,
+        ctx: &mut Context,
+    ) -> Result<SlpyObject> {
+        let base = match base {
+            None => None,
+            Some(expn) => match expn.eval(ctx)? {
+                SlpyObject::Class(class) => Some(class),
+                val => {
+                    let message = match val {
+                        SlpyObject::ExcType(ty) => {
+                            format!("cannot inherit from built-in class `{}`", ty.name())
+                        }
+                        val => format!("base class must be a class, not {}", val.type_name()),
+                    };
+                    return Err(Error::new(Kind::Type(message), expn.span()));
+                }
+            },
+        };
+        let class = Rc::new(Class::new(name.to_string(), base));
+
+        let namespace = ctx.scope.child();
+        let outer = std::mem::replace(&mut ctx.scope, namespace.clone());
+        let flow = body.eval(ctx);
+        ctx.scope = outer;
+        flow?;
+
+        // as in Python, methods don't see the class body's variables, only `__class__`, which
+        // `super()` uses
+        let cell = ctx.scope.child();
+        cell.set(
+            "__class__".to_string(),
+            SlpyObject::Class(Rc::clone(&class)),
+        );
+        for (attr, val) in namespace.vars() {
+            let val = match val {
+                SlpyObject::Function(func) if func.closure.is(&namespace) => {
+                    SlpyObject::Function(Rc::new(func.with_closure(cell.clone())))
+                }
+                val => val,
+            };
+            class.set(attr, val);
+        }
+        Ok(SlpyObject::Class(class))
+    }
+
+    fn parse_retn(tokens: &mut TokenStream) -> Result<Self> {
+        let span = tokens.current_or()?.span;
+        if !tokens.in_function {
+            return Err(Error::new(
+                Kind::Syntax("`return` outside function".to_string()),
+                span,
+            ));
+        }
+        tokens.advance();
+        if tokens
+            .current()
+            .is_none_or(|t| t.kind == TokenKind::NewLine)
+        {
+            return Ok(Self {
+                span,
+                data: StmtData::Retn(None),
+            });
+        }
+        let expn = Expn::parse(tokens)?;
+        Ok(Self {
+            span: Span {
+                start: span.start,
+                end: expn.span().end,
+            },
+            data: StmtData::Retn(Some(expn)),
+        })
+    }
+
+    fn parse_yild(tokens: &mut TokenStream) -> Result<Self> {
+        let span = tokens.current_or()?.span;
+        if !tokens.in_function {
+            return Err(Error::new(
+                Kind::Syntax("`yield` outside function".to_string()),
+                span,
+            ));
+        }
+        tokens.advance();
+        tokens.yields = true;
+        if tokens
+            .current()
+            .is_none_or(|t| t.kind == TokenKind::NewLine)
+        {
+            return Ok(Self {
+                span,
+                data: StmtData::Yild(None),
+            });
+        }
+        let expn = Expn::parse(tokens)?;
+        Ok(Self {
+            span: Span {
+                start: span.start,
+                end: expn.span().end,
+            },
+            data: StmtData::Yild(Some(expn)),
+        })
+    }
+
+    /// Parse a `global` or `nonlocal` declaration.
+    fn parse_decl(tokens: &mut TokenStream) -> Result<Self> {
+        let (keyword, span) = parse_name(tokens)?;
+        if keyword == "nonlocal" && !tokens.in_function {
+            return Err(Error::new(
+                Kind::Syntax("nonlocal declaration not allowed at module level".to_string()),
+                span,
+            ));
+        }
+        let mut names = Vec::new();
+        let end = loop {
+            let (name, span) = parse_name(tokens)?;
+            tokens.declare(&name, keyword == "nonlocal", span);
+            names.push(name);
+            if tokens.current().is_none_or(|t| t.kind != TokenKind::Comma) {
+                break span.end;
+            }
+            tokens.advance();
+        };
+        Ok(Self {
+            span: Span {
+                start: span.start,
+                end,
+            },
+            data: if keyword == "global" {
+                StmtData::Glbl(names)
+            } else {
+                StmtData::Nloc(names)
+            },
+        })
+    }
+
+    /// Parse `import` or `from ... import`.
+    fn parse_impt(tokens: &mut TokenStream) -> Result<Self> {
+        let (keyword, span) = parse_name(tokens)?;
+        let module = if keyword == "from" {
+            let (module, _) = parse_name(tokens)?;
+            tokens.eat(&TokenKind::Ident("import".to_string()))?;
+            Some(module)
+        } else {
+            None
+        };
+        let mut names = Vec::new();
+        let end = loop {
+            let (name, mut end) = parse_name(tokens)?;
+            let alias = if at_keyword(tokens, "as") {
+                tokens.advance();
+                let (alias, span) = parse_name(tokens)?;
+                end = span;
+                Some(alias)
+            } else {
+                None
+            };
+            tokens.bind(alias.as_ref().unwrap_or(&name));
+            names.push((name, alias));
+            if tokens.current().is_none_or(|t| t.kind != TokenKind::Comma) {
+                break end.end;
+            }
+            tokens.advance();
+        };
+        Ok(Self {
+            span: Span {
+                start: span.start,
+                end,
+            },
+            data: match module {
+                Some(module) => StmtData::From { module, names },
+                None => StmtData::Impt(names),
+            },
+        })
+    }
+
+    /// Run a `for` loop, or pick up in its body where a generator left off.
+    fn eval_for(&self, name: &str, iter: &Expn, body: &Blck, ctx: &mut Context) -> Result<Flow> {
+        let (iter, mut resuming) = match ctx.suspended.pop() {
+            Some(Resume::For(iter)) => (iter, true),
+            _ => {
+                let val = iter.eval(ctx)?;
+                (ctx.iter(val, iter.span())?, false)
+            }
+        };
+        loop {
+            if !std::mem::take(&mut resuming) {
+                let Some(val) = ctx.next(&iter, self.span)? else {
+                    break;
+                };
+                ctx.set(name.to_string(), val);
+            }
+            match body.eval(ctx)? {
+                Flow::Break => break,
+                Flow::Normal | Flow::Continue => {}
+                flow @ Flow::Return(_) => return Ok(flow),
+                flow @ Flow::Yield(_) => {
+                    ctx.suspended.push(Resume::For(iter));
+                    return Ok(flow);
+                }
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// The error a `raise` raises: the exception `expn` evaluates to, or the one being handled.
+    fn eval_rais(&self, expn: Option<&Expn>, ctx: &mut Context) -> Error {
+        let Some(expn) = expn else {
+            return ctx.handling.last().cloned().unwrap_or_else(|| {
+                Error::new(
+                    Kind::Exception(Exception {
+                        ty: ExcType::RuntimeError,
+                        message: "no active exception to reraise".to_string(),
+                    }),
+                    self.span,
+                )
+            });
+        };
+        let exc = match expn.eval(ctx) {
+            Ok(SlpyObject::Exception(exc)) => exc,
+            Ok(SlpyObject::ExcType(ty)) => Exception {
+                ty,
+                message: String::new(),
+            },
+            Ok(_) => {
+                return Error::new(
+                    Kind::Type("exceptions must derive from BaseException".to_string()),
+                    self.span,
+                )
+            }
+            Err(err) => return err,
+        };
+        Error::new(Kind::Exception(exc), self.span)
+    }
+
+    /// Run an `import` or `from ... import`, binding what it names.
+    fn eval_impt(&self, ctx: &mut Context) -> Result<()> {
+        match &self.data {
+            StmtData::Impt(modules) => {
+                for (name, alias) in modules {
+                    let module = ctx.import(name, self.span)?;
+                    ctx.set(
+                        alias.as_ref().unwrap_or(name).clone(),
+                        SlpyObject::Module(module),
+                    );
+                }
+            }
+            StmtData::From { module, names } => {
+                let module = ctx.import(module, self.span)?;
+                for (name, alias) in names {
+                    let val = module.get(name).ok_or_else(|| {
+                        Error::new(
+                            Kind::Import(format!(
+                                "cannot import name `{}` from `{}`",
+                                name, module.name
+                            )),
+                            self.span,
+                        )
+                    })?;
+                    ctx.set(alias.as_ref().unwrap_or(name).clone(), val);
+                }
+            }
+            _ => unreachable!("only called for imports"),
+        }
+        Ok(())
+    }
+
+    fn parse_try(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("try".to_string()))?;
+        let body = Blck::parse_suite(tokens)?;
+
+        let mut handlers = Vec::new();
+        while at_keyword(tokens, "except") {
+            let span = tokens.current_or()?.span;
+            tokens.advance();
+            let class = if tokens.current_or()?.kind == TokenKind::Colon {
+                None
+            } else {
+                Some(parse_name(tokens)?)
+            };
+            let name = if class.is_some() && at_keyword(tokens, "as") {
+                tokens.advance();
+                let (name, _) = parse_name(tokens)?;
+                tokens.bind(&name);
+                Some(name)
+            } else {
+                None
+            };
+            handlers.push(Handler {
+                class,
+                name,
+                body: Blck::parse_suite(tokens)?,
+                span,
+            });
+        }
+
+        let orelse = if at_keyword(tokens, "else") {
+            if handlers.is_empty() {
+                return Err(Error::new(Kind::Parser, tokens.current_or()?.span));
+            }
+            tokens.advance();
+            Some(Blck::parse_suite(tokens)?)
+        } else {
+            None
+        };
+        let finally = if at_keyword(tokens, "finally") {
+            tokens.advance();
+            Some(Blck::parse_suite(tokens)?)
+        } else {
+            None
+        };
+        if handlers.is_empty() && finally.is_none() {
+            return Err(Error::new(
+                Kind::Parser,
+                tokens.current().map_or(body.span(), |t| t.span),
+            ));
+        }
+
+        let end = finally
+            .as_ref()
+            .or(orelse.as_ref())
+            .or(handlers.last().map(|h| &h.body))
+            .unwrap_or(&body)
+            .span()
+            .end;
+        Ok(Self {
+            span: Span { start, end },
+            data: StmtData::Try {
+                body,
+                handlers,
+                orelse,
+                finally,
+            },
+        })
+    }
+
+    fn parse_rais(tokens: &mut TokenStream) -> Result<Self> {
+        let span = tokens.current_or()?.span;
+        tokens.eat(&TokenKind::Ident("raise".to_string()))?;
+        if tokens
+            .current()
+            .is_none_or(|t| t.kind == TokenKind::NewLine)
+        {
+            return Ok(Self {
+                span,
+                data: StmtData::Rais(None),
+            });
+        }
+        let expn = Expn::parse(tokens)?;
+        Ok(Self {
+            span: Span {
+                start: span.start,
+                end: expn.span().end,
+            },
+            data: StmtData::Rais(Some(expn)),
+        })
+    }
+
+    fn parse_asrt(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("assert".to_string()))?;
+        let cond = Expn::parse(tokens)?;
+        let text = tokens.text(cond.span());
+        let message = if tokens.current().is_some_and(|t| t.kind == TokenKind::Comma) {
+            tokens.advance();
+            Some(Expn::parse(tokens)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            span: Span {
+                start,
+                end: message.as_ref().unwrap_or(&cond).span().end,
+            },
+            data: StmtData::Asrt {
+                cond,
+                message,
+                text,
+            },
+        })
+    }
+
+    /// Run a `try` statement, picking up in whichever part of it a generator was suspended in.
+    fn eval_try(
+        body: &Blck,
+        handlers: &[Handler],
+        orelse: Option<&Blck>,
+        finally: Option<&Blck>,
+        ctx: &mut Context,
+    ) -> Result<Flow> {
+        let mut part = match ctx.suspended.pop() {
+            Some(Resume::Try(part)) => part,
+            _ => TryPart::Body,
+        };
+        loop {
+            let result = match &part {
+                TryPart::Body => body.eval(ctx),
+                TryPart::Handler(i, err) => {
+                    ctx.handling.push(err.clone());
+                    let result = handlers[*i].body.eval(ctx);
+                    ctx.handling.pop();
+                    result
+                }
+                TryPart::Else => orelse.expect("only run if there is one").eval(ctx),
+                TryPart::Finally(_) => finally.expect("only run if there is one").eval(ctx),
+            };
+            if let Ok(Flow::Yield(_)) = result {
+                ctx.suspended.push(Resume::Try(part));
+                return result;
+            }
+
+            let result = match part {
+                TryPart::Body => match result {
+                    Ok(Flow::Normal) if orelse.is_some() => {
+                        part = TryPart::Else;
+                        continue;
+                    }
+                    Err(err) => match Self::find_handler(&err, handlers, ctx) {
+                        Ok(Some(i)) => {
+                            if let (Some(name), Some(exc)) =
+                                (&handlers[i].name, err.kind.as_exception())
+                            {
+                                ctx.set(name.clone(), SlpyObject::Exception(exc));
+                            }
+                            part = TryPart::Handler(i, err);
+                            continue;
+                        }
+                        Ok(None) => Err(err),
+                        Err(err) => Err(err),
+                    },
+                    result => result,
+                },
+                TryPart::Handler(i, _) => {
+                    if let Some(name) = &handlers[i].name {
+                        ctx.scope.remove(name);
+                    }
+                    result
+                }
+                TryPart::Else => result,
+                // an error or jump in `finally` replaces whatever happened before it
+                TryPart::Finally(pending) => {
+                    let flow = result?;
+                    return if flow == Flow::Normal {
+                        pending
+                    } else {
+                        Ok(flow)
+                    };
+                }
+            };
+            match finally {
+                Some(_) => part = TryPart::Finally(result),
+                None => return result,
+            }
+        }
+    }
+
+    /// Find the first handler that matches `err`.
+    fn find_handler(err: &Error, handlers: &[Handler], ctx: &Context) -> Result<Option<usize>> {
+        let Some(exc) = err.kind.as_exception() else {
+            return Ok(None);
+        };
+        for (i, handler) in handlers.iter().enumerate() {
+            if let Some((class, span)) = &handler.class {
+                match ctx.get(class) {
+                    Some(SlpyObject::ExcType(ty)) if exc.ty.is_subclass(ty) => {}
+                    Some(SlpyObject::ExcType(_)) => continue,
+                    Some(_) => {
+                        return Err(Error::new(
+                            Kind::Type(
+                                "catching classes that do not inherit from BaseException is not \
+                                 allowed"
+                                    .to_string(),
+                            ),
+                            *span,
+                        ))
+                    }
+                    None => return Err(ctx.undefined(class, *span)),
+                }
+            }
+            return Ok(Some(i));
+        }
+        Ok(None)
+    }
+
+    /// Parse the rest of an assignment to `target`, from the `=` or augmented assignment
+    /// operator like `+=`.
+    fn parse_asgn(tokens: &mut TokenStream, target: Expn) -> Result<Self> {
+        let span = target.span();
+        let place = match &target {
+            Expn::Leaf(Leaf {
+                data: LeafData::Name(name),
+                ..
+            }) => {
+                tokens.bind(name);
+                Target::Name(name.clone(), span)
+            }
+            Expn::Attr { obj, name, .. } => Target::Attr(obj.as_ref().clone(), name.clone(), span),
+            Expn::Index { obj, key, .. } => {
+                Target::Index(obj.as_ref().clone(), key.as_ref().clone(), span)
+            }
+            _ => {
+                return Err(Error::new(
+                    Kind::Syntax("cannot assign to expression".to_string()),
+                    span,
+                ))
+            }
+        };
+
+        let aug = match tokens.current_or()?.kind {
+            TokenKind::Op(op) => BinOp::from_augmented(op),
+            _ => None,
+        };
+        if aug.is_some() {
+            tokens.advance();
+        } else {
+            tokens.eat(&TokenKind::Op(Op::Asgn))?;
+        }
+        let expn = Expn::parse(tokens)?;
+
+        Ok(Self {
+            span: Span {
+                start: span.start,
+                end: expn.span().end,
+            },
+            data: match aug {
+                Some(op) => StmtData::AugAsgn(place, op, expn),
+                None => StmtData::Asgn(place, expn),
+            },
+        })
+    }
+
+    /// Apply `op` to what's in `target` and `expn`, evaluating any object `target` is an
+    /// attribute or item of, and any key, only once.
+    fn eval_aug_asgn(
+        &self,
+        target: &Target,
+        op: BinOp,
+        expn: &Expn,
+        ctx: &mut Context,
+    ) -> Result<()> {
+        match target {
+            Target::Name(name, _) => {
+                let lhs = ctx
+                    .get(name)
+                    .ok_or_else(|| ctx.undefined(name, self.span))?;
+                let rhs = expn.eval(ctx)?;
+                let val = op.eval(lhs, rhs, self.span, ctx)?;
+                ctx.set(name.clone(), val);
+            }
+            Target::Attr(obj, name, _) => {
+                let obj = obj.eval(ctx)?;
+                let attr_error = || {
+                    Error::new(
+                        Kind::Attribute {
+                            ty: obj.type_name(),
+                            name: name.clone(),
+                        },
+                        self.span,
+                    )
+                };
+                let lhs = obj.get_attr(name).ok_or_else(attr_error)?;
+                let rhs = expn.eval(ctx)?;
+                let val = op.eval(lhs, rhs, self.span, ctx)?;
+                if !obj.set_attr(name.clone(), val) {
+                    return Err(attr_error());
+                }
+            }
+            Target::Index(obj, key, _) => {
+                let (obj, key) = (obj.eval(ctx)?, key.eval(ctx)?);
+                let lhs = ctx.get_item(&obj, &key, self.span)?;
+                let rhs = expn.eval(ctx)?;
+                let val = op.eval(lhs, rhs, self.span, ctx)?;
+                ctx.set_item(&obj, key, val, self.span)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_prnt(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("print".to_string()))?;
+        tokens.eat(&TokenKind::LParen)?;
+        let mut expns = vec![Expn::parse_named(tokens)?];
+        while tokens.current().is_some_and(|t| t.kind == TokenKind::Comma) {
+            tokens.eat(&TokenKind::Comma)?;
+            expns.push(Expn::parse_named(tokens)?);
+        }
+        let end = tokens.current_or()?.span.end;
+        tokens.eat(&TokenKind::RParen)?;
+        Ok(Self {
+            span: Span { start, end },
+            data: StmtData::Prnt(expns),
+        })
+    }
+}
+
+impl Ast for Stmt {
+    type Output = Flow;
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn parse(tokens: &mut TokenStream) -> Result<Self> {
+        let keyword = match &tokens
+            .current()
+            .expect("parsing a statement; have already checked not none")
+            .kind
+        {
+            TokenKind::Ident(ident) => ident.clone(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "pass" | "break" | "continue" => Self::parse_jump(tokens),
+            "print" => Self::parse_prnt(tokens),
+            "if" => Self::parse_cond(tokens),
+            "while" => Self::parse_whle(tokens),
+            "for" => Self::parse_for(tokens),
+            "def" => Self::parse_defn(tokens),
+            "class" => Self::parse_clss(tokens),
+            "return" => Self::parse_retn(tokens),
+            "yield" => Self::parse_yild(tokens),
+            "global" | "nonlocal" => Self::parse_decl(tokens),
+            "import" | "from" => Self::parse_impt(tokens),
+            "try" => Self::parse_try(tokens),
+            "raise" => Self::parse_rais(tokens),
+            "assert" => Self::parse_asrt(tokens),
+            _ => {
+                let expn = Expn::parse(tokens)?;
+                if tokens.current().is_some_and(|t| {
+                    matches!(t.kind, TokenKind::Op(op)
+                        if op == Op::Asgn || BinOp::from_augmented(op).is_some())
+                }) {
+                    return Self::parse_asgn(tokens, expn);
+                }
+                Ok(Self {
+                    span: expn.span(),
+                    data: StmtData::Expn(expn),
+                })
+            }
+        }
+    }
+
+    fn eval(&self, ctx: &mut Context) -> Result<Flow> {
+        ctx.step(self.span)?;
+        match &self.data {
+            StmtData::Asgn(Target::Name(name, _), expn) => {
+                let val = expn.eval(ctx)?;
+                ctx.set(name.clone(), val);
+            }
+            StmtData::Asgn(Target::Attr(obj, name, _), expn) => {
+                let val = expn.eval(ctx)?;
+                let obj = obj.eval(ctx)?;
+                if !obj.set_attr(name.clone(), val) {
+                    return Err(Error::new(
+                        Kind::Attribute {
+                            ty: obj.type_name(),
+                            name: name.clone(),
+                        },
+                        self.span,
+                    ));
+                }
+            }
+            StmtData::Asgn(Target::Index(obj, key, _), expn) => {
+                let val = expn.eval(ctx)?;
+                let (obj, key) = (obj.eval(ctx)?, key.eval(ctx)?);
+                ctx.set_item(&obj, key, val, self.span)?;
+            }
+            StmtData::AugAsgn(target, op, expn) => self.eval_aug_asgn(target, *op, expn, ctx)?,
+            StmtData::Prnt(expns) => {
+                let mut strs = Vec::new();
+                for expn in expns {
+                    let val = expn.eval(ctx)?;
+                    strs.push(ctx.str(&val, expn.span())?);
+                }
+                ctx.write(&(strs.join(" ") + "\n"), self.span)?;
+            }
+            StmtData::Expn(expn) => {
+                expn.eval(ctx)?;
+            }
+            StmtData::Cond { cond, body, orelse } => {
+                let taken = match ctx.suspended.pop() {
+                    Some(Resume::Cond(taken)) => taken,
+                    _ => cond.eval(ctx)?.is_truthy(),
+                };
+                let flow = match orelse {
+                    _ if taken => body.eval(ctx)?,
+                    Some(orelse) => orelse.eval(ctx)?,
+                    None => Flow::Normal,
+                };
+                if let Flow::Yield(_) = flow {
+                    ctx.suspended.push(Resume::Cond(taken));
+                }
+                return Ok(flow);
+            }
+            StmtData::Whle { cond, body } => {
+                // a suspended loop picks up in its body, without checking the condition first
+                let mut resuming = ctx.suspended.pop().is_some();
+                while std::mem::take(&mut resuming) || cond.eval(ctx)?.is_truthy() {
+                    match body.eval(ctx)? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                        flow @ Flow::Return(_) => return Ok(flow),
+                        flow @ Flow::Yield(_) => {
+                            ctx.suspended.push(Resume::Whle);
+                            return Ok(flow);
+                        }
+                    }
+                }
+            }
+            StmtData::For { name, iter, body } => return self.eval_for(name, iter, body, ctx),
+            StmtData::Brek => return Ok(Flow::Break),
+            StmtData::Cont => return Ok(Flow::Continue),
+            StmtData::Defn {
+                name,
+                params,
+                body,
+                generator,
+                locals,
+            } => {
+                let func = Function {
+                    name: name.clone(),
+                    sig: eval_params(params, ctx)?,
+                    body: Body::Blck(Rc::clone(body)),
+                    closure: ctx.scope.clone(),
+                    locals: Rc::clone(locals),
+                    generator: *generator,
+                    file: ctx.file.clone(),
+                };
+                ctx.set(name.clone(), SlpyObject::Function(Rc::new(func)));
+            }
+            StmtData::Clss { name, base, body } => {
+                let class = Self::eval_clss(name, base.as_ref(), body, ctx)?;
+                ctx.set(name.clone(), class);
+            }
+            StmtData::Retn(expn) => {
+                let val = match expn {
+                    Some(expn) => expn.eval(ctx)?,
+                    None => SlpyObject::None,
+                };
+                return Ok(Flow::Return(val));
+            }
+            StmtData::Yild(expn) => {
+                if let Some(Resume::Yield) = ctx.suspended.pop() {
+                    return Ok(Flow::Normal);
+                }
+                let val = match expn {
+                    Some(expn) => expn.eval(ctx)?,
+                    None => SlpyObject::None,
+                };
+                ctx.suspended.push(Resume::Yield);
+                return Ok(Flow::Yield(val));
+            }
+            StmtData::Glbl(names) => {
+                for name in names {
+                    ctx.scope.declare_global(name.clone());
+                }
+            }
+            StmtData::Impt(_) | StmtData::From { .. } => self.eval_impt(ctx)?,
+            StmtData::Nloc(names) => {
+                for name in names {
+                    ctx.scope.declare_nonlocal(name.clone());
+                }
+            }
+            StmtData::Try {
+                body,
+                handlers,
+                orelse,
+                finally,
+            } => {
+                return Self::eval_try(body, handlers, orelse.as_ref(), finally.as_ref(), ctx);
+            }
+            StmtData::Asrt {
+                cond,
+                message,
+                text,
+            } => {
+                let span = cond.span();
+                if !cond.eval(ctx)?.is_truthy() {
+                    // formatted like `print` does, so a class's `__str__` is used
+                    let message = message
+                        .as_ref()
+                        .map(|expn| {
+                            let val = expn.eval(ctx)?;
+                            ctx.str(&val, expn.span())
+                        })
+                        .transpose()?;
+                    return Err(Error::new(
+                        Kind::Assertion {
+                            expr: text.clone(),
+                            message,
+                        },
+                        span,
+                    ));
+                }
+            }
+            StmtData::Rais(expn) => return Err(self.eval_rais(expn.as_ref(), ctx)),
+            StmtData::Pass => {}
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn dump(&self, indent: usize) -> String {
+        let line = |indent: usize, s: &str| "\n".to_string() + &" ".repeat(indent) + s;
+        match &self.data {
+            StmtData::Asgn(target, expn) => {
+                " ".repeat(indent)
+                    + "Asgn\n"
+                    + &target.dump(indent + 1)
+                    + "\n"
+                    + &expn.dump(indent + 1)
+            }
+            StmtData::AugAsgn(target, op, expn) => {
+                " ".repeat(indent)
+                    + "AugAsgn\n"
+                    + &target.dump(indent + 1)
+                    + &line(indent + 1, &(op.symbol().to_string() + "="))
+                    + "\n"
+                    + &expn.dump(indent + 1)
+            }
+            StmtData::Prnt(expn) => {
+                " ".repeat(indent)
+                    + "Prnt\n"
+                    + expn
+                        .iter()
+                        .map(|e| e.dump(indent + 1))
+                        .fold(String::new(), |s, n| s + &n + "\n")
+                        .trim_end()
+            }
+            StmtData::Expn(expn) => " ".repeat(indent) + "Expn\n" + &expn.dump(indent + 1),
+            StmtData::Cond { cond, body, orelse } => {
+                let mut out = " ".repeat(indent)
+                    + "Cond\n"
+                    + &cond.dump(indent + 1)
+                    + "\n"
+                    + body.dump(indent + 1).trim_end();
+                if let Some(orelse) = orelse {
+                    out += &(line(indent + 1, "Else\n") + orelse.dump(indent + 2).trim_end());
+                }
+                out
+            }
+            StmtData::Whle { cond, body } => {
+                " ".repeat(indent)
+                    + "Whle\n"
+                    + &cond.dump(indent + 1)
+                    + "\n"
+                    + body.dump(indent + 1).trim_end()
+            }
+            StmtData::For { name, iter, body } => {
+                " ".repeat(indent)
+                    + "For"
+                    + &line(indent + 1, name)
+                    + "\n"
+                    + &iter.dump(indent + 1)
+                    + "\n"
+                    + body.dump(indent + 1).trim_end()
+            }
+            StmtData::Brek => " ".repeat(indent) + "Brek",
+            StmtData::Cont => " ".repeat(indent) + "Cont",
+            StmtData::Defn {
+                name, params, body, ..
+            } => {
+                " ".repeat(indent)
+                    + "Defn"
+                    + &line(indent + 1, name)
+                    + &dump_params(params, indent + 1)
+                    + "\n"
+                    + body.dump(indent + 1).trim_end()
+            }
+            StmtData::Clss { name, base, body } => {
+                " ".repeat(indent)
+                    + "Clss"
+                    + &line(indent + 1, name)
+                    + &base
+                        .as_ref()
+                        .map_or(String::new(), |e| "\n".to_string() + &e.dump(indent + 1))
+                    + "\n"
+                    + body.dump(indent + 1).trim_end()
+            }
+            StmtData::Retn(expn) => {
+                " ".repeat(indent)
+                    + "Retn"
+                    + &expn
+                        .as_ref()
+                        .map_or(String::new(), |e| "\n".to_string() + &e.dump(indent + 1))
+            }
+            StmtData::Yild(expn) => {
+                " ".repeat(indent)
+                    + "Yild"
+                    + &expn
+                        .as_ref()
+                        .map_or(String::new(), |e| "\n".to_string() + &e.dump(indent + 1))
+            }
+            StmtData::Glbl(names) | StmtData::Nloc(names) => {
+                let node = if matches!(self.data, StmtData::Glbl(_)) {
+                    "Glbl"
+                } else {
+                    "Nloc"
+                };
+                " ".repeat(indent)
+                    + node
+                    + &names
+                        .iter()
+                        .map(|n| line(indent + 1, n))
+                        .collect::<String>()
+            }
+            StmtData::Impt(names) => {
+                " ".repeat(indent) + "Impt" + &dump_imported(names, indent + 1)
+            }
+            StmtData::From { module, names } => {
+                " ".repeat(indent)
+                    + "From"
+                    + &line(indent + 1, module)
+                    + &dump_imported(names, indent + 2)
+            }
+            StmtData::Try {
+                body,
+                handlers,
+                orelse,
+                finally,
+            } => {
+                let mut out = " ".repeat(indent) + "Try\n" + body.dump(indent + 1).trim_end();
+                for handler in handlers {
+                    out += &line(indent + 1, "Xcpt");
+                    if let Some((class, _)) = &handler.class {
+                        out += &line(indent + 2, class);
+                    }
+                    if let Some(name) = &handler.name {
+                        out += &line(indent + 2, name);
+                    }
+                    out += &("\n".to_string() + handler.body.dump(indent + 2).trim_end());
+                }
+                if let Some(orelse) = orelse {
+                    out += &(line(indent + 1, "Else\n") + orelse.dump(indent + 2).trim_end());
+                }
+                if let Some(finally) = finally {
+                    out += &(line(indent + 1, "Fnly\n") + finally.dump(indent + 2).trim_end());
+                }
+                out
+            }
+            StmtData::Asrt { cond, message, .. } => {
+                " ".repeat(indent)
+                    + "Asrt\n"
+                    + &cond.dump(indent + 1)
+                    + &message
+                        .as_ref()
+                        .map_or(String::new(), |e| "\n".to_string() + &e.dump(indent + 1))
+            }
+            StmtData::Rais(expn) => {
+                " ".repeat(indent)
+                    + "Rais"
+                    + &expn
+                        .as_ref()
+                        .map_or(String::new(), |e| "\n".to_string() + &e.dump(indent + 1))
+            }
+            StmtData::Pass => " ".repeat(indent) + "Pass",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum StmtData {
+    Asgn(Target, Expn),
+    /// `target op= expn`
+    AugAsgn(Target, BinOp, Expn),
+    Expn(Expn),
+    Asrt {
+        cond: Expn,
+        message: Option<Expn>,
+        /// The source text of `cond`, for reporting a failure.
+        text: String,
+    },
+    Brek,
+    Cond {
+        cond: Expn,
+        body: Blck,
+        /// The `else` branch; an `elif` is an `if` inside it.
+        orelse: Option<Blck>,
+    },
+    Clss {
+        name: String,
+        base: Option<Expn>,
+        body: Blck,
+    },
+    Cont,
+    Defn {
+        name: String,
+        params: Vec<Param>,
+        body: Rc<Blck>,
+        /// Whether the body contains a `yield`.
+        generator: bool,
+        /// The names local to the body.
+        locals: Rc<HashSet<String>>,
+    },
+    For {
+        name: String,
+        iter: Expn,
+        body: Blck,
+    },
+    /// `from module import name as alias, ...`
+    From {
+        module: String,
+        names: Vec<(String, Option<String>)>,
+    },
+    Glbl(Vec<String>),
+    /// `import module as alias, ...`
+    Impt(Vec<(String, Option<String>)>),
+    Nloc(Vec<String>),
+    Pass,
+    Prnt(Vec<Expn>),
+    Rais(Option<Expn>),
+    Retn(Option<Expn>),
+    Yild(Option<Expn>),
+    Whle {
+        cond: Expn,
+        body: Blck,
+    },
+    Try {
+        body: Blck,
+        handlers: Vec<Handler>,
+        orelse: Option<Blck>,
+        finally: Option<Blck>,
+    },
+}
+
+/// Something that can be assigned to.
+#[derive(PartialEq, Eq, Debug)]
+enum Target {
+    Name(String, Span),
+    /// `obj.name`
+    Attr(Expn, String, Span),
+    /// `obj[key]`
+    Index(Expn, Expn, Span),
+}
+
+impl Target {
+    const fn span(&self) -> Span {
+        match self {
+            Self::Name(_, span) | Self::Attr(_, _, span) | Self::Index(_, _, span) => *span,
+        }
+    }
+
+    fn dump(&self, indent: usize) -> String {
+        match self {
+            Self::Name(name, _) => " ".repeat(indent) + name,
+            Self::Attr(obj, name, _) => dump_attr(obj, name, indent),
+            Self::Index(obj, key, _) => dump_index(obj, key, indent),
+        }
+    }
+}
+
+/// An `except` clause.
+#[derive(PartialEq, Eq, Debug)]
+struct Handler {
+    /// The class it catches and where it's named; a bare `except` catches everything.
+    class: Option<(String, Span)>,
+    /// The name the exception is bound to with `as`.
+    name: Option<String>,
+    body: Blck,
+    span: Span,
+}
 
-        let tkn = tokens.current_or()?;
-        let span = tkn.span;
+/// A parameter in a `def` or `lambda`, with its default still to be evaluated.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Param {
+    Named(String, Option<Expn>),
+    /// `*name`, or a bare `*` if the name is empty.
+    Star(String),
+    DStar(String),
+}
 
-        let expn = if tkn.kind == TokenKind::Op(Op::AddEq) {
-            tokens.advance();
-            let constant = Expn::parse(tokens)?;
-            Expn::BinOp {
-                left: Box::new(Expn::Leaf(Leaf {
-                    data: LeafData::Name(name.clone()),
-                    span,
-                })),
-                right: Box::new(constant),
-                op: BinOp::Plus,
+/// Parse a function's parameters, up to (but not including) `end`.
+fn parse_params(tokens: &mut TokenStream, end: &TokenKind) -> Result<Vec<Param>> {
+    let syntax = |message: &str, span| Err(Error::new(Kind::Syntax(message.to_string()), span));
+    let mut params = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    let (mut star, mut dstar, mut default) = (false, false, false);
+    while &tokens.current_or()?.kind != end {
+        let span = tokens.current_or()?.span;
+        if dstar {
+            return syntax("parameters cannot follow the `**` parameter", span);
+        }
+        let param = match tokens.current_or()?.kind {
+            TokenKind::Op(Op::Times) => {
+                tokens.advance();
+                if star {
+                    return syntax("`*` parameter may appear only once", span);
+                }
+                star = true;
+                match tokens.current_or()?.kind {
+                    TokenKind::Ident(_) => Param::Star(parse_name(tokens)?.0),
+                    _ => Param::Star(String::new()),
+                }
+            }
+            TokenKind::Op(Op::Expt) => {
+                tokens.advance();
+                dstar = true;
+                Param::DStar(parse_name(tokens)?.0)
+            }
+            _ => {
+                let (name, _) = parse_name(tokens)?;
+                let val = if tokens.current_or()?.kind == TokenKind::Op(Op::Asgn) {
+                    tokens.advance();
+                    Some(Expn::parse(tokens)?)
+                } else {
+                    None
+                };
+                if !star {
+                    if val.is_none() && default {
+                        return syntax("non-default parameter follows default parameter", span);
+                    }
+                    default |= val.is_some();
+                }
+                Param::Named(name, val)
             }
-        } else {
-            tokens.eat(&TokenKind::Op(Op::Asgn))?;
-
-            Expn::parse(tokens)?
         };
 
-        Ok(Self {
-            span: Span {
-                start,
-                end: expn.span().end,
-            },
-            data: StmtData::Asgn(name, expn),
-        })
+        let (Param::Named(name, _) | Param::Star(name) | Param::DStar(name)) = &param;
+        if names.contains(name) {
+            return syntax(
+                &format!("duplicate argument `{}` in function definition", name),
+                span,
+            );
+        }
+        if !name.is_empty() {
+            names.push(name.clone());
+        }
+        params.push(param);
+        if &tokens.current_or()?.kind != end {
+            tokens.eat(&TokenKind::Comma)?;
+        }
     }
 
-    fn parse_prnt(tokens: &mut TokenStream) -> Result<Self> {
-        let start = tokens.current_or()?.span.start;
-        tokens.eat(&TokenKind::Ident("print".to_string()))?;
-        tokens.eat(&TokenKind::LParen)?;
-        let mut expns = vec![Expn::parse(tokens)?];
-        while tokens
-            .current()
-            .map_or(false, |t| t.kind == TokenKind::Comma)
-        {
-            tokens.eat(&TokenKind::Comma)?;
-            expns.push(Expn::parse(tokens)?);
+    let bare_star = params.iter().position(|p| p == &Param::Star(String::new()));
+    if bare_star.is_some_and(|i| !matches!(params.get(i + 1), Some(Param::Named(..)))) {
+        return syntax(
+            "named parameters must follow bare `*`",
+            tokens.current_or()?.span,
+        );
+    }
+    Ok(params)
+}
+
+/// Start parsing the body of a function taking `params`, which are among its locals.
+fn enter_function(tokens: &mut TokenStream, params: &[Param]) {
+    tokens.enter_body(true);
+    for param in params {
+        let (Param::Named(name, _) | Param::Star(name) | Param::DStar(name)) = param;
+        if !name.is_empty() {
+            tokens.bind(name);
         }
-        let end = tokens.current_or()?.span.end;
-        tokens.eat(&TokenKind::RParen)?;
-        Ok(Self {
-            span: Span { start, end },
-            data: StmtData::Prnt(expns),
-        })
     }
 }
 
-impl Ast for Stmt {
-    type Output = ();
+/// Evaluate the defaults in `params`.
+fn eval_params(params: &[Param], ctx: &mut Context) -> Result<Signature> {
+    params.iter().try_fold(Signature::new(), |sig, param| {
+        Ok(match param {
+            Param::Named(name, None) => sig.param(name),
+            Param::Named(name, Some(default)) => sig.push(name.clone(), Some(default.eval(ctx)?)),
+            Param::Star(name) => sig.var_args(name),
+            Param::DStar(name) => sig.kw_args(name),
+        })
+    })
+}
 
-    fn span(&self) -> Span {
-        self.span
-    }
+fn dump_params(params: &[Param], indent: usize) -> String {
+    let line = |indent: usize, s: &str| "\n".to_string() + &" ".repeat(indent) + s;
+    line(indent, "Prms")
+        + &params
+            .iter()
+            .map(|p| match p {
+                Param::Named(name, None) => line(indent + 1, name),
+                Param::Named(name, Some(default)) => {
+                    line(indent + 1, name) + "\n" + &default.dump(indent + 2)
+                }
+                Param::Star(name) => line(indent + 1, &format!("*{}", name)),
+                Param::DStar(name) => line(indent + 1, &format!("**{}", name)),
+            })
+            .collect::<String>()
+}
 
-    fn parse(tokens: &mut TokenStream) -> Result<Self> {
-        Ok(
-            match tokens
-                .current()
-                .expect("parsing a statement; have already checked not none")
-            {
-                Token {
-                    span,
-                    kind: TokenKind::Ident(ident),
-                } if ident.as_str() == "pass" => Self {
-                    span: *span,
-                    data: StmtData::Pass,
-                },
-                Token {
-                    kind: TokenKind::Ident(ident),
-                    ..
-                } if ident.as_str() == "print" => Self::parse_prnt(tokens)?,
-                _ => Self::parse_asgn(tokens)?,
-            },
-        )
-    }
+/// Whether the current token is the keyword `kw`.
+fn at_keyword(tokens: &TokenStream, kw: &str) -> bool {
+    tokens
+        .current()
+        .is_some_and(|t| matches!(&t.kind, TokenKind::Ident(i) if i == kw))
+}
 
-    fn eval(self, ctx: &mut Context) -> Result<()> {
-        match self.data {
-            StmtData::Asgn(name, expn) => {
-                let val = expn.eval(ctx)?;
-                ctx.set(name, val);
-            }
-            StmtData::Prnt(expns) => {
-                println!(
-                    "{}",
-                    expns
-                        .into_iter()
-                        .flat_map(|e| e.eval(ctx).map(|n| n.to_string()))
-                        .fold(String::new(), |s, n| s + &n + " ")
-                        .trim_end()
-                );
-            }
-            StmtData::Pass => {}
-        }
+/// Dump what an import binds, one line each, as `name` or `name as alias`.
+fn dump_imported(names: &[(String, Option<String>)], indent: usize) -> String {
+    names
+        .iter()
+        .map(|(name, alias)| {
+            "\n".to_string()
+                + &" ".repeat(indent)
+                + name
+                + &alias
+                    .as_ref()
+                    .map_or(String::new(), |alias| " as ".to_string() + alias)
+        })
+        .collect()
+}
 
-        Ok(())
+/// Take a name, along with where it appears.
+fn parse_name(tokens: &mut TokenStream) -> Result<(String, Span)> {
+    let tkn = tokens.current_or()?;
+    match &tkn.kind {
+        TokenKind::Ident(name) => {
+            let name = (name.clone(), tkn.span);
+            tokens.advance();
+            Ok(name)
+        }
+        _ => Err(Error::new(Kind::Parser, tkn.span)),
     }
+}
+
+/// An argument at a call site.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Arg {
+    Pos(Expn),
+    /// `*args`, spreading a tuple into positional arguments.
+    Star(Expn),
+    Kw(String, Expn),
+    /// `**kwargs`, spreading a dict into keyword arguments.
+    DStar(Expn),
+}
 
+impl Arg {
     fn dump(&self, indent: usize) -> String {
-        match &self.data {
-            StmtData::Asgn(name, expn) => {
+        match self {
+            Self::Pos(expn) => expn.dump(indent),
+            Self::Star(expn) => " ".repeat(indent) + "Star\n" + &expn.dump(indent + 1),
+            Self::Kw(name, expn) => {
                 " ".repeat(indent)
-                    + "Asgn\n"
+                    + "Kwrd\n"
                     + &" ".repeat(indent + 1)
                     + name
                     + "\n"
                     + &expn.dump(indent + 1)
             }
-            StmtData::Prnt(expn) => {
-                " ".repeat(indent)
-                    + "Prnt\n"
-                    + expn
-                        .iter()
-                        .map(|e| e.dump(indent + 1))
-                        .fold(String::new(), |s, n| s + &n + "\n")
-                        .trim_end()
-            }
-            StmtData::Pass => " ".repeat(indent) + "Pass",
+            Self::DStar(expn) => " ".repeat(indent) + "DStr\n" + &expn.dump(indent + 1),
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
-enum StmtData {
-    Asgn(String, Expn),
-    Pass,
-    Prnt(Vec<Expn>),
-}
-
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Expn {
+    Attr {
+        obj: Box<Self>,
+        name: String,
+        span: Span,
+    },
+    /// `obj[key]`
+    Index {
+        obj: Box<Self>,
+        key: Box<Self>,
+        span: Span,
+    },
     BinOp {
         left: Box<Self>,
         right: Box<Self>,
         op: BinOp,
     },
+    Call {
+        func: Box<Self>,
+        args: Vec<Arg>,
+        span: Span,
+    },
+    Lmbd {
+        params: Vec<Param>,
+        body: Rc<Self>,
+        /// The names local to the body: its parameters, and any it assigns with `:=`.
+        locals: Rc<HashSet<String>>,
+        span: Span,
+    },
+    /// `body if cond else orelse`
+    Tern {
+        cond: Box<Self>,
+        body: Box<Self>,
+        orelse: Box<Self>,
+    },
+    /// `name := value`
+    Wlrs {
+        name: String,
+        value: Box<Self>,
+        span: Span,
+    },
+    List {
+        items: Vec<Self>,
+        span: Span,
+    },
+    Set {
+        items: Vec<Self>,
+        span: Span,
+    },
+    Dict {
+        entries: Vec<(Self, Self)>,
+        span: Span,
+    },
+    /// A comprehension or generator expression, making `elt` for each time through `clauses`.
+    Comp {
+        kind: CompKind,
+        elt: Box<Self>,
+        clauses: Vec<Clause>,
+        span: Span,
+    },
     Leaf(Leaf),
 }
 
+/// What a comprehension makes.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum CompKind {
+    List,
+    Set,
+    /// A dict, with `elt` as the keys and this as the values.
+    Dict(Box<Expn>),
+    /// A generator, running this body, which is the clauses as `for` and `if` statements around a
+    /// `yield`. It's called with an iterator over the first clause's iterable as `.0`, which is
+    /// evaluated where the generator expression is, as in Python.
+    Gen(Rc<Blck>),
+}
+
+/// A `for` or `if` clause in a comprehension.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Clause {
+    For(String, Expn),
+    If(Expn),
+}
+
+impl Clause {
+    fn dump(&self, indent: usize) -> String {
+        match self {
+            Self::For(name, iter) => {
+                " ".repeat(indent)
+                    + "For\n"
+                    + &" ".repeat(indent + 1)
+                    + name
+                    + "\n"
+                    + &iter.dump(indent + 1)
+            }
+            Self::If(cond) => " ".repeat(indent) + "If\n" + &cond.dump(indent + 1),
+        }
+    }
+}
+
 impl Expn {
     fn parse_impl(tokens: &mut TokenStream, min_bp: u8) -> Result<Self> {
         let tkn = tokens.current_or()?;
@@ -256,31 +2647,71 @@ impl Expn {
         let mut lhs = match tkn.kind {
             TokenKind::LParen => {
                 tokens.advance();
-                let lhs = Self::parse_impl(tokens, 0)?;
+                let lhs = Self::parse_named(tokens)?;
+                let lhs = if at_keyword(tokens, "for") {
+                    let clauses = Self::parse_clauses(tokens)?;
+                    let end = tokens.current_or()?.span.end;
+                    Self::gen(
+                        lhs,
+                        clauses,
+                        Span {
+                            start: span.start,
+                            end,
+                        },
+                    )
+                } else {
+                    lhs
+                };
                 tokens.eat(&TokenKind::RParen)?;
                 lhs
             }
-            TokenKind::Ident(_) | TokenKind::Number(_) => Self::Leaf(Leaf::parse(tokens)?),
+            TokenKind::LBracket => Self::parse_list(tokens)?,
+            TokenKind::LBrace => Self::parse_braces(tokens)?,
+            TokenKind::Ident(ref name) if name == "lambda" => {
+                return Self::parse_lmbd(tokens);
+            }
+            TokenKind::Ident(_) | TokenKind::Number(_) | TokenKind::Str(_) => {
+                Self::Leaf(Leaf::parse(tokens)?)
+            }
             _ => {
-                return Err(Error {
-                    span,
-                    kind: Kind::Parser,
-                });
+                return Err(Error::new(Kind::Parser, span));
             }
         };
+        loop {
+            match tokens.current().map(|t| &t.kind) {
+                Some(TokenKind::LParen) => lhs = Self::parse_call(tokens, lhs)?,
+                Some(TokenKind::Dot) => lhs = Self::parse_attr(tokens, lhs)?,
+                Some(TokenKind::LBracket) => lhs = Self::parse_index(tokens, lhs)?,
+                _ => break,
+            }
+        }
 
         while let Some(tkn) = tokens.current() {
             let span = tkn.span;
             let op = match tkn.kind {
-                TokenKind::NewLine | TokenKind::RParen | TokenKind::Comma => {
+                TokenKind::NewLine
+                | TokenKind::RParen
+                | TokenKind::RBracket
+                | TokenKind::RBrace
+                | TokenKind::Comma
+                | TokenKind::Colon
+                | TokenKind::Op(
+                    Op::Asgn
+                    | Op::AddEq
+                    | Op::SubEq
+                    | Op::MulEq
+                    | Op::DivEq
+                    | Op::ModEq
+                    | Op::ExptEq
+                    | Op::ColonEq,
+                ) => {
                     break;
                 }
+                // comprehension clauses and conditional expressions
+                TokenKind::Ident(ref kw) if kw == "for" || kw == "if" || kw == "else" => break,
                 TokenKind::Op(op) => BinOp::from_token(op, span)?,
                 _ => {
-                    return Err(Error {
-                        span,
-                        kind: Kind::Parser,
-                    });
+                    return Err(Error::new(Kind::Parser, span));
                 }
             };
 
@@ -299,7 +2730,470 @@ impl Expn {
             }
         }
 
-        Ok(lhs)
+        // conditional expressions bind more loosely than any operator
+        if min_bp > 0 {
+            return Ok(lhs);
+        }
+        if at_keyword(tokens, "if") {
+            tokens.advance();
+            let cond = Self::parse_impl(tokens, 1)?;
+            if !at_keyword(tokens, "else") {
+                return Err(Error::new(
+                    Kind::Syntax("expected `else` after `if` expression".to_string()),
+                    tokens.current().map_or(cond.span(), |t| t.span),
+                ));
+            }
+            tokens.advance();
+            let orelse = Self::parse_impl(tokens, 0)?;
+            lhs = Self::Tern {
+                cond: Box::new(cond),
+                body: Box::new(lhs),
+                orelse: Box::new(orelse),
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    /// Parse an expression where an unparenthesized `name := value` may stand, as in Python: an
+    /// `if` or `while` condition, a positional argument, a subscript, or an item of a list or set.
+    fn parse_named(tokens: &mut TokenStream) -> Result<Self> {
+        let expn = Self::parse_impl(tokens, 0)?;
+        if tokens
+            .current()
+            .is_some_and(|t| t.kind == TokenKind::Op(Op::ColonEq))
+        {
+            return Self::parse_wlrs(tokens, expn);
+        }
+        Ok(expn)
+    }
+
+    /// Parse the rest of `target := value`, from the `:=`.
+    fn parse_wlrs(tokens: &mut TokenStream, target: Self) -> Result<Self> {
+        let start = target.span().start;
+        let name = Self::walrus_target(target)?;
+        tokens.eat(&TokenKind::Op(Op::ColonEq))?;
+        tokens.bind(&name);
+        let value = Self::parse_impl(tokens, 0)?;
+        Ok(Self::Wlrs {
+            name,
+            span: Span {
+                start,
+                end: value.span().end,
+            },
+            value: Box::new(value),
+        })
+    }
+
+    /// The name `target` is if it can be assigned with `:=`.
+    fn walrus_target(target: Self) -> Result<String> {
+        match target {
+            Self::Leaf(Leaf {
+                data: LeafData::Name(name),
+                ..
+            }) => Ok(name),
+            target => {
+                let what = match target {
+                    Self::Attr { .. } => "attribute",
+                    Self::Index { .. } => "subscript",
+                    Self::Call { .. } => "function call",
+                    Self::Leaf(_) | Self::List { .. } | Self::Set { .. } | Self::Dict { .. } => {
+                        "literal"
+                    }
+                    _ => "expression",
+                };
+                Err(Error::new(
+                    Kind::Syntax(format!("cannot use assignment expressions with {}", what)),
+                    target.span(),
+                ))
+            }
+        }
+    }
+
+    /// Parse a list, or a list comprehension.
+    fn parse_list(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::LBracket)?;
+        let mut items = Vec::new();
+        let mut clauses = Vec::new();
+        while tokens.current_or()?.kind != TokenKind::RBracket {
+            items.push(Self::parse_named(tokens)?);
+            if items.len() == 1 && at_keyword(tokens, "for") {
+                clauses = Self::parse_clauses(tokens)?;
+                break;
+            }
+            if tokens.current_or()?.kind != TokenKind::RBracket {
+                tokens.eat(&TokenKind::Comma)?;
+            }
+        }
+        let end = tokens.current_or()?.span.end;
+        tokens.eat(&TokenKind::RBracket)?;
+        let span = Span { start, end };
+        Ok(match items.pop() {
+            Some(elt) if !clauses.is_empty() => Self::Comp {
+                kind: CompKind::List,
+                elt: Box::new(elt),
+                clauses,
+                span,
+            },
+            last => {
+                items.extend(last);
+                Self::List { items, span }
+            }
+        })
+    }
+
+    /// Parse what's in braces: a dict, a set, or a comprehension making one.
+    fn parse_braces(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::LBrace)?;
+        let mut entries = Vec::new();
+        let mut items = Vec::new();
+        let mut comp = None;
+        while tokens.current_or()?.kind != TokenKind::RBrace {
+            let first = entries.is_empty() && items.is_empty();
+            let mut item = Self::parse_impl(tokens, 0)?;
+            // only a set's items can be unparenthesized assignment expressions
+            let named = tokens
+                .current()
+                .is_some_and(|t| t.kind == TokenKind::Op(Op::ColonEq));
+            if named {
+                item = Self::parse_wlrs(tokens, item)?;
+            }
+            // the first item decides whether it's a dict or a set
+            if (first || !entries.is_empty())
+                && !named
+                && tokens.current_or()?.kind == TokenKind::Colon
+            {
+                tokens.advance();
+                entries.push((item, Self::parse(tokens)?));
+            } else if entries.is_empty() {
+                items.push(item);
+            } else {
+                tokens.eat(&TokenKind::Colon)?;
+            }
+            if first && at_keyword(tokens, "for") {
+                comp = Some(Self::parse_clauses(tokens)?);
+                break;
+            }
+            if tokens.current_or()?.kind != TokenKind::RBrace {
+                tokens.eat(&TokenKind::Comma)?;
+            }
+        }
+        let end = tokens.current_or()?.span.end;
+        tokens.eat(&TokenKind::RBrace)?;
+        let span = Span { start, end };
+        Ok(match (comp, entries.pop(), items.pop()) {
+            (Some(clauses), Some((key, value)), _) => Self::Comp {
+                kind: CompKind::Dict(Box::new(value)),
+                elt: Box::new(key),
+                clauses,
+                span,
+            },
+            (Some(clauses), _, Some(elt)) => Self::Comp {
+                kind: CompKind::Set,
+                elt: Box::new(elt),
+                clauses,
+                span,
+            },
+            (_, None, Some(last)) => {
+                items.push(last);
+                Self::Set { items, span }
+            }
+            (_, last, _) => {
+                entries.extend(last);
+                Self::Dict { entries, span }
+            }
+        })
+    }
+
+    /// Parse the `for` and `if` clauses of a comprehension, which start with a `for`.
+    fn parse_clauses(tokens: &mut TokenStream) -> Result<Vec<Clause>> {
+        let mut clauses = Vec::new();
+        loop {
+            if at_keyword(tokens, "for") {
+                tokens.advance();
+                let (name, _) = parse_name(tokens)?;
+                tokens.eat(&TokenKind::Ident("in".to_string()))?;
+                // as in Python, a conditional expression here needs parentheses, so that the
+                // clauses after it aren't read as one
+                clauses.push(Clause::For(name, Self::parse_impl(tokens, 1)?));
+            } else if at_keyword(tokens, "if") && !clauses.is_empty() {
+                tokens.advance();
+                clauses.push(Clause::If(Self::parse_impl(tokens, 1)?));
+            } else if clauses.is_empty() {
+                return Err(Error::new(Kind::Parser, tokens.current_or()?.span));
+            } else {
+                return Ok(clauses);
+            }
+        }
+    }
+
+    /// A generator expression, with the body its generator runs.
+    fn gen(elt: Self, clauses: Vec<Clause>, span: Span) -> Self {
+        let mut stmts = VecDeque::from([Stmt {
+            span: elt.span(),
+            data: StmtData::Yild(Some(elt.clone())),
+        }]);
+        for (i, clause) in clauses.iter().enumerate().rev() {
+            let stmt = match clause {
+                Clause::For(name, iter) => Stmt {
+                    span: iter.span(),
+                    data: StmtData::For {
+                        name: name.clone(),
+                        iter: if i == 0 {
+                            Self::Leaf(Leaf {
+                                data: LeafData::Name(".0".to_string()),
+                                span: iter.span(),
+                            })
+                        } else {
+                            iter.clone()
+                        },
+                        body: Blck { stmts },
+                    },
+                },
+                Clause::If(cond) => Stmt {
+                    span: cond.span(),
+                    data: StmtData::Cond {
+                        cond: cond.clone(),
+                        body: Blck { stmts },
+                        orelse: None,
+                    },
+                },
+            };
+            stmts = VecDeque::from([stmt]);
+        }
+        Self::Comp {
+            kind: CompKind::Gen(Rc::new(Blck { stmts })),
+            elt: Box::new(elt),
+            clauses,
+            span,
+        }
+    }
+
+    /// Evaluate a comprehension other than a generator expression.
+    fn eval_comp(
+        kind: &CompKind,
+        elt: &Self,
+        clauses: &[Clause],
+        span: Span,
+        ctx: &mut Context,
+    ) -> Result<SlpyObject> {
+        let Some(Clause::For(_, first)) = clauses.first() else {
+            unreachable!("comprehensions start with `for`")
+        };
+        // the first iterable is evaluated outside the comprehension's scope
+        let first = {
+            let val = first.eval(ctx)?;
+            ctx.iter(val, first.span())?
+        };
+        if let CompKind::Gen(body) = kind {
+            let func = Function {
+                name: "<genexpr>".to_string(),
+                sig: Signature::new().param(".0"),
+                body: Body::Blck(Rc::clone(body)),
+                closure: ctx.scope.clone(),
+                locals: Rc::default(),
+                generator: true,
+                file: ctx.file.clone(),
+            };
+            return ctx.call_function(&Rc::new(func), vec![first], Vec::new(), span);
+        }
+
+        let scope = ctx.scope.child();
+        let outer = std::mem::replace(&mut ctx.scope, scope);
+        let mut out = Vec::new();
+        let result = Self::eval_clauses(clauses, Some(first), ctx, &mut |ctx| {
+            let key = elt.eval(ctx)?;
+            if !matches!(kind, CompKind::List) {
+                check_hashable(&key, elt.span())?;
+            }
+            let val = match kind {
+                CompKind::Dict(value) => value.eval(ctx)?,
+                _ => SlpyObject::None,
+            };
+            out.push((key, val));
+            ctx.check_size(out.len(), span)
+        });
+        ctx.scope = outer;
+        result?;
+        Ok(match kind {
+            CompKind::List => SlpyObject::list_of(out.into_iter().map(|(k, _)| k).collect()),
+            CompKind::Set => SlpyObject::set_of(out.into_iter().map(|(k, _)| k).collect()),
+            CompKind::Dict(_) => SlpyObject::dict_of(out),
+            CompKind::Gen(_) => unreachable!("handled above"),
+        })
+    }
+
+    /// Run `clauses`, calling `emit` each time through all of them. `iter` is the first `for`
+    /// clause's iterator, if it's already been evaluated.
+    fn eval_clauses(
+        clauses: &[Clause],
+        iter: Option<SlpyObject>,
+        ctx: &mut Context,
+        emit: &mut dyn FnMut(&mut Context) -> Result<()>,
+    ) -> Result<()> {
+        let Some((clause, rest)) = clauses.split_first() else {
+            return emit(ctx);
+        };
+        match clause {
+            Clause::For(name, expn) => {
+                let iter = match iter {
+                    Some(iter) => iter,
+                    None => {
+                        let val = expn.eval(ctx)?;
+                        ctx.iter(val, expn.span())?
+                    }
+                };
+                while let Some(val) = ctx.next(&iter, expn.span())? {
+                    ctx.step(expn.span())?;
+                    ctx.set(name.clone(), val);
+                    Self::eval_clauses(rest, None, ctx, emit)?;
+                }
+            }
+            Clause::If(cond) => {
+                if cond.eval(ctx)?.is_truthy() {
+                    Self::eval_clauses(rest, None, ctx, emit)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse `.name` after `obj`.
+    fn parse_attr(tokens: &mut TokenStream, obj: Self) -> Result<Self> {
+        tokens.eat(&TokenKind::Dot)?;
+        let (name, span) = parse_name(tokens)?;
+        Ok(Self::Attr {
+            span: Span {
+                start: obj.span().start,
+                end: span.end,
+            },
+            obj: Box::new(obj),
+            name,
+        })
+    }
+
+    /// Parse `[key]` after `obj`.
+    fn parse_index(tokens: &mut TokenStream, obj: Self) -> Result<Self> {
+        tokens.eat(&TokenKind::LBracket)?;
+        let key = Self::parse_named(tokens)?;
+        let end = tokens.current_or()?.span.end;
+        tokens.eat(&TokenKind::RBracket)?;
+        Ok(Self::Index {
+            span: Span {
+                start: obj.span().start,
+                end,
+            },
+            obj: Box::new(obj),
+            key: Box::new(key),
+        })
+    }
+
+    /// Parse the arguments of a call to `func`.
+    fn parse_call(tokens: &mut TokenStream, func: Self) -> Result<Self> {
+        let start = func.span().start;
+        tokens.eat(&TokenKind::LParen)?;
+        let mut args: Vec<Arg> = Vec::new();
+        while tokens.current_or()?.kind != TokenKind::RParen {
+            let span = tokens.current_or()?.span;
+            let arg = match &tokens.current_or()?.kind {
+                TokenKind::Op(Op::Times) => {
+                    tokens.advance();
+                    Arg::Star(Self::parse(tokens)?)
+                }
+                TokenKind::Op(Op::Expt) => {
+                    tokens.advance();
+                    Arg::DStar(Self::parse(tokens)?)
+                }
+                TokenKind::Ident(_)
+                    if tokens
+                        .peek()
+                        .is_some_and(|t| t.kind == TokenKind::Op(Op::Asgn)) =>
+                {
+                    let (name, _) = parse_name(tokens)?;
+                    tokens.advance();
+                    Arg::Kw(name, Self::parse(tokens)?)
+                }
+                _ => {
+                    let expn = Self::parse_named(tokens)?;
+                    if at_keyword(tokens, "for") {
+                        // a generator expression needs no parentheses of its own as the only
+                        // argument
+                        let clauses = Self::parse_clauses(tokens)?;
+                        let end = tokens.current_or()?.span.end;
+                        if !args.is_empty() || tokens.current_or()?.kind != TokenKind::RParen {
+                            return Err(Error::new(
+                                Kind::Syntax(
+                                    "generator expression must be parenthesized".to_string(),
+                                ),
+                                span,
+                            ));
+                        }
+                        Arg::Pos(Self::gen(
+                            expn,
+                            clauses,
+                            Span {
+                                start: span.start,
+                                end,
+                            },
+                        ))
+                    } else {
+                        Arg::Pos(expn)
+                    }
+                }
+            };
+            let after_kw = args.iter().any(|a| matches!(a, Arg::Kw(..)));
+            let after_dstar = args.iter().any(|a| matches!(a, Arg::DStar(_)));
+            let message = match &arg {
+                Arg::Pos(_) | Arg::Star(_) if after_dstar => {
+                    Some("positional argument follows keyword argument unpacking".to_string())
+                }
+                Arg::Pos(_) if after_kw => {
+                    Some("positional argument follows keyword argument".to_string())
+                }
+                Arg::Kw(name, _)
+                    if args.iter().any(|a| matches!(a, Arg::Kw(n, _) if n == name)) =>
+                {
+                    Some(format!("keyword argument repeated: `{}`", name))
+                }
+                _ => None,
+            };
+            if let Some(message) = message {
+                return Err(Error::new(Kind::Syntax(message), span));
+            }
+            args.push(arg);
+            if tokens.current_or()?.kind != TokenKind::RParen {
+                tokens.eat(&TokenKind::Comma)?;
+            }
+        }
+        let end = tokens.current_or()?.span.end;
+        tokens.eat(&TokenKind::RParen)?;
+        Ok(Self::Call {
+            func: Box::new(func),
+            args,
+            span: Span { start, end },
+        })
+    }
+
+    fn parse_lmbd(tokens: &mut TokenStream) -> Result<Self> {
+        let start = tokens.current_or()?.span.start;
+        tokens.eat(&TokenKind::Ident("lambda".to_string()))?;
+        let params = parse_params(tokens, &TokenKind::Colon)?;
+        tokens.eat(&TokenKind::Colon)?;
+        enter_function(tokens, &params);
+        let body = Self::parse_impl(tokens, 0);
+        let locals = tokens.leave_body();
+        let body = body?;
+        Ok(Self::Lmbd {
+            params,
+            locals: locals?,
+            span: Span {
+                start,
+                end: body.span().end,
+            },
+            body: Rc::new(body),
+        })
     }
 }
 
@@ -309,26 +3203,176 @@ impl Ast for Expn {
     fn span(&self) -> Span {
         match self {
             Self::Leaf(leaf) => leaf.span(),
+            Self::Attr { span, .. }
+            | Self::Index { span, .. }
+            | Self::Call { span, .. }
+            | Self::Lmbd { span, .. }
+            | Self::Wlrs { span, .. }
+            | Self::List { span, .. }
+            | Self::Set { span, .. }
+            | Self::Dict { span, .. }
+            | Self::Comp { span, .. } => *span,
             Self::BinOp { left, right, .. } => Span {
                 start: left.span().start,
                 end: right.span().end,
             },
+            Self::Tern { body, orelse, .. } => Span {
+                start: body.span().start,
+                end: orelse.span().end,
+            },
         }
     }
 
+    /// Parse an expression, which can only be an assignment expression if it's parenthesized.
     fn parse(tokens: &mut TokenStream) -> Result<Self> {
-        Self::parse_impl(tokens, 0)
+        let expn = Self::parse_impl(tokens, 0)?;
+        match tokens.current() {
+            Some(tkn) if tkn.kind == TokenKind::Op(Op::ColonEq) => {
+                let span = tkn.span;
+                Self::walrus_target(expn)?;
+                Err(Error::new(
+                    Kind::Syntax("assignment expressions must be parenthesized here".to_string()),
+                    span,
+                ))
+            }
+            _ => Ok(expn),
+        }
     }
 
-    fn eval(self, ctx: &mut Context) -> Result<Self::Output> {
+    fn eval(&self, ctx: &mut Context) -> Result<Self::Output> {
+        ctx.step(self.span())?;
         match self {
-            Self::BinOp { left, right, op } => Ok(op.eval(left.eval(ctx)?, right.eval(ctx)?)),
+            Self::Attr { obj, name, span } => {
+                let obj = obj.eval(ctx)?;
+                obj.get_attr(name).ok_or_else(|| {
+                    Error::new(
+                        Kind::Attribute {
+                            ty: obj.type_name(),
+                            name: name.clone(),
+                        },
+                        *span,
+                    )
+                })
+            }
+            Self::Index { obj, key, span } => {
+                let (obj, key) = (obj.eval(ctx)?, key.eval(ctx)?);
+                ctx.get_item(&obj, &key, *span)
+            }
+            Self::BinOp { left, right, op } => {
+                let (lhs, rhs) = (left.eval(ctx)?, right.eval(ctx)?);
+                op.eval(lhs, rhs, self.span(), ctx)
+            }
+            Self::Call { func, args, span } => {
+                let func = func.eval(ctx)?;
+                let (mut pos, mut kw) = (Vec::new(), Vec::new());
+                for arg in args {
+                    match arg {
+                        Arg::Pos(expn) => pos.push(expn.eval(ctx)?),
+                        Arg::Star(expn) => match expn.eval(ctx)? {
+                            SlpyObject::Tuple(items) => pos.extend(items.iter().cloned()),
+                            val => {
+                                return Err(Error::new(
+                                    Kind::Type(format!(
+                                        "argument after * must be a tuple, not {}",
+                                        val.type_name()
+                                    )),
+                                    expn.span(),
+                                ))
+                            }
+                        },
+                        Arg::Kw(name, expn) => kw.push((name.clone(), expn.eval(ctx)?)),
+                        Arg::DStar(expn) => match expn.eval(ctx)? {
+                            SlpyObject::Dict(entries) => {
+                                for (key, val) in entries.borrow().iter() {
+                                    let SlpyObject::Str(key) = key else {
+                                        return Err(Error::new(
+                                            Kind::Type("keywords must be strings".to_string()),
+                                            expn.span(),
+                                        ));
+                                    };
+                                    kw.push((key.clone(), val.clone()));
+                                }
+                            }
+                            val => {
+                                return Err(Error::new(
+                                    Kind::Type(format!(
+                                        "argument after ** must be a dict, not {}",
+                                        val.type_name()
+                                    )),
+                                    expn.span(),
+                                ))
+                            }
+                        },
+                    }
+                }
+                ctx.call(func, pos, kw, *span)
+            }
+            Self::Lmbd {
+                params,
+                body,
+                locals,
+                ..
+            } => Ok(SlpyObject::Function(Rc::new(Function {
+                name: "<lambda>".to_string(),
+                sig: eval_params(params, ctx)?,
+                body: Body::Expn(Rc::clone(body)),
+                closure: ctx.scope.clone(),
+                locals: Rc::clone(locals),
+                generator: false,
+                file: ctx.file.clone(),
+            }))),
+            Self::Tern { cond, body, orelse } => {
+                if cond.eval(ctx)?.is_truthy() {
+                    body.eval(ctx)
+                } else {
+                    orelse.eval(ctx)
+                }
+            }
+            Self::Wlrs { name, value, .. } => {
+                let val = value.eval(ctx)?;
+                ctx.set(name.clone(), val.clone());
+                Ok(val)
+            }
+            Self::List { items, span } | Self::Set { items, span } => {
+                ctx.check_size(items.len(), *span)?;
+                let mut vals = Vec::new();
+                for item in items {
+                    let val = item.eval(ctx)?;
+                    if let Self::Set { .. } = self {
+                        check_hashable(&val, item.span())?;
+                    }
+                    vals.push(val);
+                }
+                Ok(if let Self::List { .. } = self {
+                    SlpyObject::list_of(vals)
+                } else {
+                    SlpyObject::set_of(vals)
+                })
+            }
+            Self::Dict { entries, span } => {
+                ctx.check_size(entries.len(), *span)?;
+                let mut dict = Vec::new();
+                for (key, val) in entries {
+                    let k = key.eval(ctx)?;
+                    check_hashable(&k, key.span())?;
+                    dict.push((k, val.eval(ctx)?));
+                }
+                Ok(SlpyObject::dict_of(dict))
+            }
+            Self::Comp {
+                kind,
+                elt,
+                clauses,
+                span,
+            } => Self::eval_comp(kind, elt, clauses, *span, ctx),
             Self::Leaf(l) => l.eval(ctx),
         }
     }
 
     fn dump(&self, indent: usize) -> String {
         match &self {
+            Self::Attr { obj, name, .. } => dump_attr(obj, name, indent),
+            Self::Index { obj, key, .. } => dump_index(obj, key, indent),
             Self::BinOp { left, right, op } => {
                 " ".repeat(indent)
                     + op.as_str()
@@ -337,11 +3381,139 @@ impl Ast for Expn {
                     + "\n"
                     + &right.dump(indent + 1)
             }
+            Self::Call { func, args, .. } => {
+                // calls of a plain name show just the name
+                let func = match func.as_ref() {
+                    Self::Leaf(Leaf {
+                        data: LeafData::Name(name),
+                        ..
+                    }) => " ".repeat(indent + 1) + name,
+                    func => func.dump(indent + 1),
+                };
+                " ".repeat(indent)
+                    + "Call\n"
+                    + &func
+                    + &args
+                        .iter()
+                        .map(|a| a.dump(indent + 1))
+                        .fold(String::new(), |s, n| s + "\n" + &n)
+            }
+            Self::Lmbd { params, body, .. } => {
+                " ".repeat(indent)
+                    + "Lmbd"
+                    + &dump_params(params, indent + 1)
+                    + "\n"
+                    + &body.dump(indent + 1)
+            }
+            Self::Tern { cond, body, orelse } => {
+                " ".repeat(indent)
+                    + "Tern\n"
+                    + &cond.dump(indent + 1)
+                    + "\n"
+                    + &body.dump(indent + 1)
+                    + "\n"
+                    + &orelse.dump(indent + 1)
+            }
+            Self::Wlrs { name, value, .. } => {
+                " ".repeat(indent)
+                    + "Wlrs\n"
+                    + &" ".repeat(indent + 1)
+                    + name
+                    + "\n"
+                    + &value.dump(indent + 1)
+            }
+            Self::List { items, .. } | Self::Set { items, .. } => {
+                let node = if let Self::List { .. } = self {
+                    "List"
+                } else {
+                    "Set"
+                };
+                " ".repeat(indent)
+                    + node
+                    + &items
+                        .iter()
+                        .map(|e| "\n".to_string() + &e.dump(indent + 1))
+                        .collect::<String>()
+            }
+            Self::Dict { entries, .. } => {
+                " ".repeat(indent)
+                    + "Dict"
+                    + &entries
+                        .iter()
+                        .map(|(k, v)| {
+                            "\n".to_string() + &k.dump(indent + 1) + "\n" + &v.dump(indent + 1)
+                        })
+                        .collect::<String>()
+            }
+            Self::Comp {
+                kind, elt, clauses, ..
+            } => {
+                let (node, value) = match kind {
+                    CompKind::List => ("LsCm", None),
+                    CompKind::Set => ("StCm", None),
+                    CompKind::Dict(value) => ("DcCm", Some(value)),
+                    CompKind::Gen(_) => ("GnXp", None),
+                };
+                " ".repeat(indent)
+                    + node
+                    + "\n"
+                    + &elt.dump(indent + 1)
+                    + &value.map_or(String::new(), |v| "\n".to_string() + &v.dump(indent + 1))
+                    + &clauses
+                        .iter()
+                        .map(|c| "\n".to_string() + &c.dump(indent + 1))
+                        .collect::<String>()
+            }
             Self::Leaf(l) => l.dump(indent),
         }
     }
 }
 
+fn dump_attr(obj: &Expn, name: &str, indent: usize) -> String {
+    " ".repeat(indent) + "Attr\n" + &obj.dump(indent + 1) + "\n" + &" ".repeat(indent + 1) + name
+}
+
+fn dump_index(obj: &Expn, key: &Expn, indent: usize) -> String {
+    " ".repeat(indent) + "Index\n" + &obj.dump(indent + 1) + "\n" + &key.dump(indent + 1)
+}
+
+/// Check that `key` can be a dict key or set item.
+fn check_hashable(key: &SlpyObject, span: Span) -> Result<()> {
+    match key.unhashable() {
+        Some(val) => Err(Error::new(
+            Kind::Type(format!("unhashable type: '{}'", val.type_name())),
+            span,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Where `key` indexes `seq`, which has `len` items, counting negative keys back from the end.
+fn seq_index(len: usize, key: &SlpyObject, seq: &SlpyObject, span: Span) -> Result<usize> {
+    let SlpyObject::Int(i) = *key else {
+        return Err(Error::new(
+            Kind::Type(format!(
+                "{} indices must be integers, not {}",
+                seq.type_name(),
+                key.type_name()
+            )),
+            span,
+        ));
+    };
+    let len_i64 = i64::try_from(len).unwrap_or(i64::MAX);
+    let i = if i < 0 {
+        i64::from(i) + len_i64
+    } else {
+        i64::from(i)
+    };
+    usize::try_from(i).ok().filter(|&i| i < len).ok_or_else(|| {
+        Error::new(
+            Kind::Index(format!("{} index out of range", seq.type_name())),
+            span,
+        )
+    })
+}
+
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum BinOp {
     Plus,
@@ -350,6 +3522,12 @@ pub enum BinOp {
     Div,
     Mod,
     Expt,
+    Eq,
+    NotEq,
+    Lt,
+    LtE,
+    Gt,
+    GtE,
 }
 
 impl BinOp {
@@ -361,33 +3539,195 @@ impl BinOp {
             Op::Div => Self::Div,
             Op::Mod => Self::Mod,
             Op::Expt => Self::Expt,
-            Op::Asgn | Op::AddEq => {
-                return Err(Error {
-                    kind: Kind::Parser,
-                    span,
-                });
+            Op::Eq => Self::Eq,
+            Op::NotEq => Self::NotEq,
+            Op::Lt => Self::Lt,
+            Op::LtE => Self::LtE,
+            Op::Gt => Self::Gt,
+            Op::GtE => Self::GtE,
+            Op::Asgn
+            | Op::AddEq
+            | Op::SubEq
+            | Op::MulEq
+            | Op::DivEq
+            | Op::ModEq
+            | Op::ExptEq
+            | Op::ColonEq => {
+                return Err(Error::new(Kind::Parser, span));
             }
         })
     }
 
+    /// The operator an augmented assignment like `+=` applies.
+    const fn from_augmented(op: Op) -> Option<Self> {
+        Some(match op {
+            Op::AddEq => Self::Plus,
+            Op::SubEq => Self::Minus,
+            Op::MulEq => Self::Times,
+            Op::DivEq => Self::Div,
+            Op::ModEq => Self::Mod,
+            Op::ExptEq => Self::Expt,
+            _ => return None,
+        })
+    }
+
     const fn bp(self) -> (u8, u8) {
         match self {
-            Self::Plus | Self::Minus => (1, 2),
-            Self::Times | Self::Div | Self::Mod => (3, 4),
-            Self::Expt => (6, 5),
+            Self::Eq | Self::NotEq | Self::Lt | Self::LtE | Self::Gt | Self::GtE => (1, 2),
+            Self::Plus | Self::Minus => (3, 4),
+            Self::Times | Self::Div | Self::Mod => (5, 6),
+            Self::Expt => (8, 7),
+        }
+    }
+
+    fn eval(
+        self,
+        lhs: SlpyObject,
+        rhs: SlpyObject,
+        span: Span,
+        ctx: &mut Context,
+    ) -> Result<SlpyObject> {
+        if let Some(val) = self.eval_dunder(&lhs, &rhs, span, ctx)? {
+            return Ok(val);
+        }
+        match self {
+            Self::Eq => return Ok(SlpyObject::Bool(lhs == rhs)),
+            Self::NotEq => return Ok(SlpyObject::Bool(lhs != rhs)),
+            Self::Lt | Self::LtE | Self::Gt | Self::GtE => return self.compare(&lhs, &rhs, span),
+            _ => {}
+        }
+        match (lhs, rhs) {
+            (SlpyObject::Int(lhs), SlpyObject::Int(rhs)) => self.eval_int(lhs, rhs, span),
+            (SlpyObject::Str(lhs), SlpyObject::Str(rhs)) if self == Self::Plus => {
+                ctx.check_size(lhs.len().saturating_add(rhs.len()), span)?;
+                Ok(SlpyObject::Str(lhs + &rhs))
+            }
+            (SlpyObject::Str(s), SlpyObject::Int(n)) | (SlpyObject::Int(n), SlpyObject::Str(s))
+                if self == Self::Times =>
+            {
+                let n = usize::try_from(n).unwrap_or(0);
+                ctx.check_size(s.len().saturating_mul(n), span)?;
+                Ok(SlpyObject::Str(s.repeat(n)))
+            }
+            (lhs, rhs) => Err(Error::new(
+                Kind::Type(format!(
+                    "unsupported operand types for {}: {} and {}",
+                    self.symbol(),
+                    lhs.type_name(),
+                    rhs.type_name()
+                )),
+                span,
+            )),
+        }
+    }
+
+    /// Apply the operator with the left operand's special method, like `__add__`, if it's an
+    /// instance of a class that defines one. `==` and `!=` also try the right operand's, and `!=`
+    /// falls back to negating `__eq__`.
+    fn eval_dunder(
+        self,
+        lhs: &SlpyObject,
+        rhs: &SlpyObject,
+        span: Span,
+        ctx: &mut Context,
+    ) -> Result<Option<SlpyObject>> {
+        let operands: &[(&SlpyObject, &SlpyObject)] = match self {
+            Self::Eq | Self::NotEq => &[(lhs, rhs), (rhs, lhs)],
+            _ => &[(lhs, rhs)],
+        };
+        for &(a, b) in operands {
+            if let Some(val) = ctx.call_dunder(a, self.dunder(), vec![b.clone()], span)? {
+                return Ok(Some(val));
+            }
+            if self == Self::NotEq {
+                if let Some(val) = ctx.call_dunder(a, "__eq__", vec![b.clone()], span)? {
+                    return Ok(Some(SlpyObject::Bool(!val.is_truthy())));
+                }
+            }
         }
+        Ok(None)
+    }
+
+    /// Order two values of the same type.
+    fn compare(self, lhs: &SlpyObject, rhs: &SlpyObject, span: Span) -> Result<SlpyObject> {
+        let ordering = match (lhs, rhs) {
+            (SlpyObject::Int(lhs), SlpyObject::Int(rhs)) => lhs.cmp(rhs),
+            (SlpyObject::Str(lhs), SlpyObject::Str(rhs)) => lhs.cmp(rhs),
+            (SlpyObject::Bool(lhs), SlpyObject::Bool(rhs)) => lhs.cmp(rhs),
+            _ => {
+                return Err(Error::new(
+                    Kind::Type(format!(
+                        "`{}` not supported between {} and {}",
+                        self.symbol(),
+                        lhs.type_name(),
+                        rhs.type_name()
+                    )),
+                    span,
+                ))
+            }
+        };
+        Ok(SlpyObject::Bool(match self {
+            Self::Lt => ordering.is_lt(),
+            Self::LtE => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            _ => ordering.is_ge(),
+        }))
     }
 
-    fn eval(self, lhs: SlpyObject, rhs: SlpyObject) -> SlpyObject {
+    fn eval_int(self, lhs: i32, rhs: i32, span: Span) -> Result<SlpyObject> {
+        if rhs == 0 && matches!(self, Self::Div | Self::Mod) {
+            return Err(Error::new(Kind::DivisionByZero, span));
+        }
+
         match self {
-            Self::Plus => lhs + rhs,
-            Self::Minus => lhs - rhs,
-            Self::Times => lhs * rhs,
-            Self::Div => lhs / rhs,
-            Self::Mod => lhs % rhs,
+            Self::Plus => lhs.checked_add(rhs),
+            Self::Minus => lhs.checked_sub(rhs),
+            Self::Times => lhs.checked_mul(rhs),
+            Self::Div => lhs.checked_div(rhs),
+            Self::Mod => lhs.checked_rem(rhs),
             // if rhs is negative, then we have a fractionl result as the output, which we round to
             // zero.
-            Self::Expt => u32::try_from(rhs).map_or_else(|_| 0, |n| lhs.pow(n)),
+            Self::Expt => u32::try_from(rhs).map_or(Some(0), |n| lhs.checked_pow(n)),
+            Self::Eq | Self::NotEq | Self::Lt | Self::LtE | Self::Gt | Self::GtE => {
+                unreachable!("comparisons are handled in `eval`")
+            }
+        }
+        .map(SlpyObject::Int)
+        .ok_or(Error::new(Kind::Overflow, span))
+    }
+
+    const fn symbol(self) -> &'static str {
+        match self {
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Times => "*",
+            Self::Div => "//",
+            Self::Mod => "%",
+            Self::Expt => "**",
+            Self::Eq => "==",
+            Self::NotEq => "!=",
+            Self::Lt => "<",
+            Self::LtE => "<=",
+            Self::Gt => ">",
+            Self::GtE => ">=",
+        }
+    }
+
+    /// The special method that implements the operator for instances of a class.
+    const fn dunder(self) -> &'static str {
+        match self {
+            Self::Plus => "__add__",
+            Self::Minus => "__sub__",
+            Self::Times => "__mul__",
+            Self::Div => "__floordiv__",
+            Self::Mod => "__mod__",
+            Self::Expt => "__pow__",
+            Self::Eq => "__eq__",
+            Self::NotEq => "__ne__",
+            Self::Lt => "__lt__",
+            Self::LtE => "__le__",
+            Self::Gt => "__gt__",
+            Self::GtE => "__ge__",
         }
     }
 
@@ -399,11 +3739,17 @@ impl BinOp {
             Self::Div => "IDiv",
             Self::Mod => "Modu",
             Self::Expt => "Expt",
+            Self::Eq => "Equl",
+            Self::NotEq => "NtEq",
+            Self::Lt => "Less",
+            Self::LtE => "LsEq",
+            Self::Gt => "Grtr",
+            Self::GtE => "GrEq",
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Leaf {
     data: LeafData,
     span: Span,
@@ -417,10 +3763,7 @@ impl Leaf {
         let prompt = if let TokenKind::Str(s) = &tkn.kind {
             s.to_string()
         } else {
-            return Err(Error {
-                span: tkn.span,
-                kind: Kind::Parser,
-            });
+            return Err(Error::new(Kind::Parser, tkn.span));
         };
         tokens.advance();
         let end = tokens.current_or()?.span.end;
@@ -452,30 +3795,39 @@ impl Ast for Leaf {
                 data: LeafData::Nmbr(n),
                 span,
             },
+            TokenKind::Str(s) => Self {
+                data: LeafData::Strg(s),
+                span,
+            },
             _ => panic!(),
         })
     }
 
-    fn eval(self, ctx: &mut Context) -> Result<Self::Output> {
-        let err = Error {
-            kind: Kind::Interpretation,
-            span: self.span,
-        };
-        Ok(match self.data {
-            LeafData::Name(s) => ctx.get(s.as_str()).ok_or(err)?,
-            // TODO: handle too-large numbers gracefully
-            #[allow(clippy::cast_possible_wrap)]
-            LeafData::Nmbr(n) => n as SlpyObject,
+    fn eval(&self, ctx: &mut Context) -> Result<Self::Output> {
+        Ok(match &self.data {
+            LeafData::Name(s) => match ctx.get(s.as_str()) {
+                Some(val) => val,
+                None => return Err(ctx.undefined(s, self.span)),
+            },
+            LeafData::Nmbr(n) => SlpyObject::Int(
+                i32::try_from(*n).map_err(|_| Error::new(Kind::Overflow, self.span))?,
+            ),
+            LeafData::Strg(s) => SlpyObject::Str(s.clone()),
             LeafData::Inpt(s) => {
-                print!("{}", s);
-                std::io::stdout().flush().expect("can flush stdout");
+                ctx.write(s, self.span)?;
                 let mut buffer = String::new();
-                if std::io::stdin().read_line(&mut buffer).is_ok() {
+                if ctx.read_line(&mut buffer).is_ok() {
                     if let Ok(n) = buffer.trim_end().parse() {
-                        return Ok(n);
+                        return Ok(SlpyObject::Int(n));
                     }
                 }
-                return Err(err);
+                return Err(Error::new(
+                    Kind::Value(format!(
+                        "invalid literal for int(): `{}`",
+                        buffer.trim_end()
+                    )),
+                    self.span,
+                ));
             }
         })
     }
@@ -496,17 +3848,22 @@ impl Ast for Leaf {
             LeafData::Nmbr(num) => {
                 " ".repeat(indent) + "Nmbr\n" + &" ".repeat(indent + 1) + &num.to_string()
             }
+            LeafData::Strg(s) => {
+                " ".repeat(indent) + "Strg\n" + &" ".repeat(indent + 1) + "\"" + s.as_str() + "\""
+            }
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 enum LeafData {
     Name(String),
     Nmbr(u32),
+    Strg(String),
     Inpt(String),
 }
 
+#[derive(Debug)]
 pub struct Prgm {
     main: Blck,
 }
@@ -524,8 +3881,9 @@ impl Ast for Prgm {
         })
     }
 
-    fn eval(self, ctx: &mut Context) -> Result<()> {
-        self.main.eval(ctx)
+    fn eval(&self, ctx: &mut Context) -> Result<()> {
+        // `return`, `break` and `continue` outside a function or loop don't parse
+        self.main.eval(ctx).map(|_| ())
     }
 
     fn dump(&self, indent: usize) -> String {
@@ -584,6 +3942,9 @@ mod tests {
             }
 
             dump_test!(plus: "3 + 2" => "Plus\n Nmbr\n  3\n Nmbr\n  2");
+            dump_test!(call: "f(x, 1)" => "Call\n f\n Lkup\n  x\n Nmbr\n  1");
+            dump_test!(call_no_args: "f()" => "Call\n f");
+            dump_test!(string: "\"a\" + b" => "Plus\n Strg\n  \"a\"\n Lkup\n  b");
         }
 
         mod eval {
@@ -595,7 +3956,10 @@ mod tests {
                     fn $name() {
                         let mut tokens = Tokenizer::lex($in).unwrap();
                         let expn = Expn::parse(&mut tokens).unwrap();
-                        assert_eq!(expn.eval(&mut Context::default()).unwrap(), $out);
+                        assert_eq!(
+                            expn.eval(&mut Context::default()).unwrap(),
+                            SlpyObject::Int($out)
+                        );
                     }
                 };
             }
@@ -739,7 +4103,13 @@ mod tests {
                             end: Loc { row: 1, col: 5 },
                         },
                         data: StmtData::Asgn(
-                            "x".to_string(),
+                            Target::Name(
+                                "x".to_string(),
+                                Span {
+                                    start: Loc { row: 1, col: 1 },
+                                    end: Loc { row: 1, col: 1 },
+                                }
+                            ),
                             Expn::BinOp {
                                 left: num!(1,3 => 2),
                                 op: BinOp::Plus,
@@ -751,4 +4121,638 @@ mod tests {
             }
         }
     }
+
+    mod prgm {
+        use super::*;
+        use crate::tokenizer::Tokenizer;
+
+        mod dump {
+            use super::*;
+
+            macro_rules! dump_test {
+                ($name:ident: $in:expr => $out:expr) => {
+                    #[test]
+                    fn $name() {
+                        let mut tokens = Tokenizer::lex($in).unwrap();
+                        let prgm = Prgm::parse(&mut tokens).unwrap();
+                        assert_eq!(prgm.dump(0).as_str(), $out)
+                    }
+                };
+            }
+
+            dump_test!(try_except:
+                "try:\n    raise ValueError(\"a\")\nexcept ValueError as e:\n    pass\nfinally:\n    print(1)\n"
+                => "Prgm\n Blck\n  Try\n   Blck\n    Rais\n     Call\n      ValueError\n      Strg\n       \"a\"\n   Xcpt\n    ValueError\n    e\n    Blck\n     Pass\n   Fnly\n    Blck\n     Prnt\n      Nmbr\n       1\n");
+            dump_test!(bare_raise: "try:\n    pass\nexcept:\n    raise\n"
+                => "Prgm\n Blck\n  Try\n   Blck\n    Pass\n   Xcpt\n    Blck\n     Rais\n");
+            dump_test!(def: "def f(a, b):\n    return a\n"
+                => "Prgm\n Blck\n  Defn\n   f\n   Prms\n    a\n    b\n   Blck\n    Retn\n     Lkup\n      a\n");
+            dump_test!(lambda_call: "(lambda: 1)()"
+                => "Prgm\n Blck\n  Expn\n   Call\n    Lmbd\n     Prms\n     Nmbr\n      1\n");
+            dump_test!(class: "class B(A):\n    def f(self):\n        self.x = 1\n"
+                => "Prgm\n Blck\n  Clss\n   B\n   Lkup\n    A\n   Blck\n    Defn\n     f\n     Prms\n      self\n     Blck\n      Asgn\n       Attr\n        Lkup\n         self\n        x\n       Nmbr\n        1\n");
+            dump_test!(method_call: "a.b.c(1)"
+                => "Prgm\n Blck\n  Expn\n   Call\n    Attr\n     Attr\n      Lkup\n       a\n      b\n     c\n    Nmbr\n     1\n");
+            dump_test!(generator: "def g(xs):\n    for x in xs:\n        yield x\n    yield\n"
+                => "Prgm\n Blck\n  Defn\n   g\n   Prms\n    xs\n   Blck\n    For\n     x\n     Lkup\n      xs\n     Blck\n      Yild\n       Lkup\n        x\n    Yild\n");
+            dump_test!(list_comp: "[x * 2 for x in xs if x for y in x]"
+                => "Prgm\n Blck\n  Expn\n   LsCm\n    Tmes\n     Lkup\n      x\n     Nmbr\n      2\n    For\n     x\n     Lkup\n      xs\n    If\n     Lkup\n      x\n    For\n     y\n     Lkup\n      x\n");
+            dump_test!(dict_and_set_comps: "{k: 1 for k in a}\n{k for k in a}"
+                => "Prgm\n Blck\n  Expn\n   DcCm\n    Lkup\n     k\n    Nmbr\n     1\n    For\n     k\n     Lkup\n      a\n  Expn\n   StCm\n    Lkup\n     k\n    For\n     k\n     Lkup\n      a\n");
+            dump_test!(generator_expression: "f(x for x in a)"
+                => "Prgm\n Blck\n  Expn\n   Call\n    f\n    GnXp\n     Lkup\n      x\n     For\n      x\n      Lkup\n       a\n");
+            dump_test!(displays: "[1, 2]\n{1: 2}\n{1,}"
+                => "Prgm\n Blck\n  Expn\n   List\n    Nmbr\n     1\n    Nmbr\n     2\n  Expn\n   Dict\n    Nmbr\n     1\n    Nmbr\n     2\n  Expn\n   Set\n    Nmbr\n     1\n");
+            dump_test!(conditional_expression: "x = a if b else c"
+                => "Prgm\n Blck\n  Asgn\n   x\n   Tern\n    Lkup\n     b\n    Lkup\n     a\n    Lkup\n     c\n");
+            dump_test!(walrus: "while (n := n - 1) > 0: pass"
+                => "Prgm\n Blck\n  Whle\n   Grtr\n    Wlrs\n     n\n     Mnus\n      Lkup\n       n\n      Nmbr\n       1\n    Nmbr\n     0\n   Blck\n    Pass\n");
+            dump_test!(augmented_assignment: "x -= 1\na.b **= 2"
+                => "Prgm\n Blck\n  AugAsgn\n   x\n   -=\n   Nmbr\n    1\n  AugAsgn\n   Attr\n    Lkup\n     a\n    b\n   **=\n   Nmbr\n    2\n");
+            dump_test!(imports: "import a, b as c\nfrom d import e as f, g"
+                => "Prgm\n Blck\n  Impt\n   a\n   b as c\n  From\n   d\n    e as f\n    g\n");
+            dump_test!(if_elif: "if x < 1: pass\nelif x == 1: pass\n"
+                => "Prgm\n Blck\n  Cond\n   Less\n    Lkup\n     x\n    Nmbr\n     1\n   Blck\n    Pass\n   Else\n    Blck\n     Cond\n      Equl\n       Lkup\n        x\n       Nmbr\n        1\n      Blck\n       Pass\n");
+            dump_test!(assert: "assert x, \"m\"\nassert 1"
+                => "Prgm\n Blck\n  Asrt\n   Lkup\n    x\n   Strg\n    \"m\"\n  Asrt\n   Nmbr\n    1\n");
+
+            #[test]
+            fn stripped_assert() {
+                let mut tokens = Tokenizer::lex("try:\n    assert 0\nfinally:\n    pass").unwrap();
+                tokens.set_strip_asserts(true);
+                let prgm = Prgm::parse(&mut tokens).unwrap();
+                assert_eq!(
+                    prgm.dump(0),
+                    "Prgm\n Blck\n  Try\n   Blck\n   Fnly\n    Blck\n     Pass\n"
+                );
+            }
+        }
+
+        mod eval {
+            use super::*;
+
+            /// Run a program against scripted input and check everything it printed.
+            macro_rules! run_test {
+                ($name:ident: $in:expr, $stdin:expr => $out:expr) => {
+                    #[test]
+                    fn $name() {
+                        let mut tokens = Tokenizer::lex($in).unwrap();
+                        let prgm = Prgm::parse(&mut tokens).unwrap();
+                        let mut output = Vec::new();
+                        prgm.eval(&mut Context::new($stdin.as_bytes(), &mut output))
+                            .unwrap();
+                        assert_eq!(String::from_utf8(output).unwrap(), $out);
+                    }
+                };
+                ($name:ident: $in:expr => $out:expr) => { run_test!($name: $in, "" => $out); }
+            }
+
+            run_test!(print: "print(1)" => "1\n");
+            run_test!(print_many: "print(1, 2 + 3, 4 * 5)" => "1 5 20\n");
+            run_test!(add_eq: "x = 1\nx += 2\nprint(x)" => "3\n");
+            run_test!(augmented_operators: "x = 20\nx -= 3\nx *= 2\nprint(x)\nx //= 5\nx **= 3\nx %= 100\nprint(x)"
+                => "34\n16\n");
+            run_test!(augmented_attribute_evaluates_object_once: "class Box:\n    pass\nb = Box()\nb.n = 1\ndef get():\n    print(\"get\")\n    return b\nget().n *= 5\nprint(b.n)"
+                => "get\n5\n");
+            run_test!(strings: "x = \"ab\"\nprint(x + \"c\", x * 2)" => "abc abab\n");
+            run_test!(blank_lines: "\nx = 1\n\n\nprint(x)\n\n" => "1\n");
+            run_test!(input: "x = input(\"n? \")\nprint(x * 2)", "21\n" => "n? 42\n");
+            run_test!(inputs: "x = input(\"a\")\ny = input(\"b\")\nprint(x - y)", "5\n3\n" => "ab2\n");
+
+            run_test!(except_subclass: "try:\n    print(1 // 0)\nexcept ArithmeticError as e:\n    print(\"caught\", e)\nprint(\"after\")"
+                => "caught division by zero\nafter\n");
+            run_test!(except_in_order: "try:\n    x = y\nexcept ValueError:\n    print(1)\nexcept NameError:\n    print(2)\nexcept:\n    print(3)"
+                => "2\n");
+            run_test!(else_and_finally: "try:\n    x = 1\nexcept Exception:\n    print(\"no\")\nelse:\n    print(\"else\")\nfinally:\n    print(\"finally\")"
+                => "else\nfinally\n");
+            run_test!(raise_and_catch: "try:\n    raise ValueError(\"bad\")\n    print(\"unreachable\")\nexcept ValueError as e:\n    print(e)"
+                => "bad\n");
+            run_test!(nested_reraise: "try:\n    try:\n        raise TypeError\n    except TypeError:\n        print(\"inner\")\n        raise\n    finally:\n        print(\"cleanup\")\nexcept Exception:\n    print(\"outer\")"
+                => "inner\ncleanup\nouter\n");
+            run_test!(counter_closure: "def counter():\n    n = 0\n    def inc():\n        nonlocal n\n        n += 1\n        return n\n    return inc\n\na = counter()\nb = counter()\na()\na()\nprint(a(), b())"
+                => "3 1\n");
+            run_test!(late_binding_in_loop: "def make():\n    i = 0\n    while i < 3:\n        if i == 0:\n            first = lambda: i * 10\n        i += 1\n    return first\n\nprint(make()())"
+                => "30\n");
+            run_test!(functions_as_arguments: "def twice(f, x):\n    return f(f(x))\n\nprint(twice(lambda n: n * 3, 2), twice(lambda s: s + \"!\", \"a\"))"
+                => "18 a!!\n");
+            run_test!(global: "count = 0\ndef bump():\n    global count\n    count += 1\nbump()\nbump()\nprint(count)"
+                => "2\n");
+            run_test!(locals_stay_local: "x = 1\ndef f():\n    x = 2\n    return x\nprint(f(), x)"
+                => "2 1\n");
+            run_test!(forgotten_nonlocal: "def counter():\n    n = 0\n    def inc():\n        n += 1\n        return n\n    return inc\ntry:\n    counter()()\nexcept UnboundLocalError as e:\n    print(e)"
+                => "local variable `n` referenced before assignment\n");
+            run_test!(locals_hide_builtins_and_globals: "x = 1\ndef f():\n    for name in [\"x\", \"iter\"]:\n        try:\n            x if name == \"x\" else iter\n        except UnboundLocalError as e:\n            print(e)\n    x = 2\n    iter = 2\nf()\nprint(x)"
+                => "local variable `x` referenced before assignment\nlocal variable `iter` referenced before assignment\n1\n");
+            run_test!(nonlocal_bound_later: "def f():\n    def g():\n        nonlocal x\n        x = 2\n    g()\n    print(x)\n    x = 3\n    return lambda: x\nprint(f()())"
+                => "2\n3\n");
+            run_test!(recursion: "def fact(n):\n    if n <= 1:\n        return 1\n    return n * fact(n - 1)\nprint(fact(10))"
+                => "3628800\n");
+            run_test!(while_break_continue: "i = 0\nwhile True:\n    i += 1\n    if i == 2: continue\n    if i > 4: break\n    print(i)"
+                => "1\n3\n4\n");
+            run_test!(return_from_try: "def f():\n    try:\n        return 1\n    finally:\n        print(\"cleanup\")\nprint(f())"
+                => "cleanup\n1\n");
+            run_test!(comparisons: "print(1 < 2, 2 <= 1, \"a\" == \"a\", 1 != 1, 3 > 2 + 2, None == None)"
+                => "True False True False False True\n");
+            run_test!(assert_passes: "assert 1, \"unused\"\nassert \"s\"\nprint(2)" => "2\n");
+            run_test!(catch_assert: "try:\n    assert 3 - 3\nexcept AssertionError as e:\n    print(e)"
+                => "`3 - 3` is false\n");
+            run_test!(assert_message_uses_str: "class Why:\n    def __str__(self):\n        return \"because\"\ntry:\n    assert 0, Why()\nexcept AssertionError as e:\n    print(e)"
+                => "`0` is false: because\n");
+            run_test!(defaults_and_keywords: "def f(a, b=2, c=3):\n    print(a, b, c)\nf(1)\nf(1, c=5)\nf(c=0, a=7, b=8)"
+                => "1 2 3\n1 2 5\n7 8 0\n");
+            run_test!(var_args: "def f(a, *args, **kwargs):\n    print(a, args, kwargs)\nf(1, 2, 3, k=1)\nf(1)"
+                => "1 (2, 3) {'k': 1}\n1 () {}\n");
+            run_test!(forwarding: "def g(a, b, c=0):\n    return a + b + c\ndef f(*args, **kwargs):\n    return g(*args, **kwargs)\nprint(f(1, 2), f(1, b=2, c=3))"
+                => "3 6\n");
+            run_test!(keyword_only: "def f(a, *, key=\"k\", reverse):\n    print(a, key, reverse)\nf(1, reverse=True)"
+                => "1 k True\n");
+            run_test!(default_binds_early: "def make():\n    i = 0\n    while i < 3:\n        if i == 0:\n            first = lambda i=i: i * 10\n        i += 1\n    return first\n\nprint(make()())"
+                => "0\n");
+            run_test!(defaults_evaluated_once: "n = 1\ndef f(x=n * 10):\n    return x\nn = 2\nprint(f(), f())"
+                => "10 10\n");
+            run_test!(class_with_init: "class Point:\n    def __init__(self, x, y):\n        self.x = x\n        self.y = y\n    def norm(self):\n        return self.x * self.x + self.y * self.y\n\np = Point(3, 4)\np.y = 0\nprint(p.x, p.y, p.norm())"
+                => "3 0 9\n");
+            run_test!(inheritance_and_super: "class A:\n    def __init__(self, name):\n        self.name = name\n    def greet(self):\n        return \"hi \" + self.name\n    def who(self):\n        return \"A\"\nclass B(A):\n    def __init__(self):\n        super().__init__(\"b\")\n    def greet(self):\n        return super().greet() + \" from \" + self.who()\n\nprint(B().greet())"
+                => "hi b from A\n");
+            run_test!(super_with_arguments: "class A:\n    def f(self):\n        return 1\nclass B(A):\n    def f(self):\n        return 2\nclass C(B):\n    def f(self):\n        return super(B, self).f()\nprint(C().f())"
+                => "1\n");
+            run_test!(class_attributes: "class Counter:\n    count = 0\n    def __init__(self):\n        Counter.count += 1\n\nCounter()\nc = Counter()\nprint(Counter.count, c.count)\nc.count = 10\nprint(Counter.count, c.count)"
+                => "2 2\n2 10\n");
+            run_test!(dunder_hooks: "class Money:\n    def __init__(self, cents):\n        self.cents = cents\n    def __add__(self, other):\n        return Money(self.cents + other.cents)\n    def __eq__(self, other):\n        return self.cents == other.cents\n    def __str__(self):\n        return \"$\" + self.cents\n\nprint(Money(\"1\") + Money(\"5\"), Money(2) == Money(2), Money(2) != Money(2), Money(1) == Money(2))"
+                => "$15 True False False\n");
+            run_test!(identity_without_eq: "class A:\n    pass\na = A()\nb = a\nprint(a == b, a == A(), a)"
+                => "True False <A object>\n");
+            run_test!(methods_skip_class_scope: "x = \"global\"\nclass A:\n    x = \"class\"\n    def f(self):\n        return x\nprint(A().f(), A.x)"
+                => "global class\n");
+            run_test!(catch_attribute_error: "class A:\n    pass\ntry:\n    A().missing\nexcept AttributeError as e:\n    print(e)"
+                => "A object has no attribute `missing`\n");
+            run_test!(generator_in_for: "def count(n):\n    i = 0\n    while i < n:\n        yield i\n        i += 1\n\nfor x in count(3):\n    print(x)"
+                => "0\n1\n2\n");
+            run_test!(next_and_default: "def g():\n    yield 1\n    if True:\n        yield 2\n    return 3\n    yield 4\nit = g()\nprint(next(it), next(it), next(it, \"done\"), next(it, None))"
+                => "1 2 done None\n");
+            run_test!(infinite_generator: "def naturals():\n    n = 0\n    while True:\n        yield n\n        n += 1\n\nfor n in naturals():\n    if n > 2:\n        break\n    print(n)\nit = naturals()\nnext(it)\nprint(next(it))"
+                => "0\n1\n2\n1\n");
+            run_test!(yield_in_try: "def g():\n    try:\n        yield 1\n        raise ValueError(\"v\")\n    except ValueError as e:\n        yield e\n        raise\n    finally:\n        print(\"cleanup\")\n\nit = g()\nprint(next(it), next(it))\ntry:\n    next(it)\nexcept ValueError as e:\n    print(\"reraised\", e)"
+                => "1 v\ncleanup\nreraised v\n");
+            run_test!(generators_are_independent: "def g(x):\n    yield x\n    yield x * 2\na = g(1)\nb = g(10)\nprint(next(a), next(b), next(a), next(b))"
+                => "1 10 2 20\n");
+            run_test!(nested_for: "def pairs(*xs):\n    for x in xs:\n        for y in xs:\n            yield x + y\n\nfor s in pairs(\"a\", \"b\"):\n    print(s)"
+                => "aa\nab\nba\nbb\n");
+            run_test!(catch_stop_iteration: "it = iter(\"ab\")\nfor c in it:\n    print(c)\ntry:\n    next(it)\nexcept StopIteration:\n    print(\"exhausted\")"
+                => "a\nb\nexhausted\n");
+            run_test!(stop_iteration_does_not_end_loop: "def g():\n    yield 1\n    next(iter(\"\"))\n    yield 2\ntry:\n    for x in g():\n        print(x)\nexcept RuntimeError as e:\n    print(e)"
+                => "1\ngenerator raised StopIteration\n");
+            run_test!(for_over_containers: "def f(*args, **kwargs):\n    for a in args:\n        print(a)\n    for k in kwargs:\n        print(k)\nf(1, 2, x=3)\nfor c in \"hi\":\n    print(c)"
+                => "1\n2\nx\nh\ni\n");
+            run_test!(list_comprehension: "xs = [1, 2, 3, 4, 5, 6]\nprint([x * x for x in xs if x % 2 == 0])"
+                => "[4, 16, 36]\n");
+            run_test!(multiple_clauses: "print([a + b for a in \"ab\" if a != \"c\" for b in \"xy\" if b != a])"
+                => "['ax', 'ay', 'bx', 'by']\n");
+            run_test!(dict_and_set_comprehensions: "xs = [1, 2, 3, 4]\nprint({x % 2 for x in xs}, {x: x * x for x in xs if x < 3})"
+                => "{1, 0} {1: 1, 2: 4}\n");
+            run_test!(subscripts: "xs = [1, [2, 3]]\nd = {\"a\": 1}\nxs[0] = 5\nxs[1][0 - 1] += 1\nd[\"b\"] = xs[0]\nd[\"a\"] *= 10\ndef args(*a):\n    return a\nprint(xs, d, args(7, 8)[1], \"hey\"[0 - 1])"
+                => "[5, [2, 4]] {'a': 10, 'b': 5} 8 y\n");
+            run_test!(augmented_index_evaluates_object_and_key_once: "xs = [1, 2]\ndef get():\n    print(\"get\")\n    return xs\ndef key():\n    print(\"key\")\n    return 1\nget()[key()] += 5\nprint(xs)"
+                => "get\nkey\n[1, 7]\n");
+            run_test!(lookup_errors: "try:\n    [1][1]\nexcept IndexError as e:\n    print(e)\ntry:\n    {}[\"k\"]\nexcept LookupError as e:\n    print(e)\ndef args(*a):\n    return a\ntry:\n    args(1)[0] = 2\nexcept TypeError as e:\n    print(e)"
+                => "list index out of range\n'k'\ntuple object does not support item assignment\n");
+            run_test!(dict_keys_are_checked: "d = {}\ntry:\n    d[[d]] = 1\nexcept TypeError as e:\n    print(e)\ntry:\n    d[{}]\nexcept TypeError as e:\n    print(e)\nd[1] = d\nd[1] = 2\nprint(d)"
+                => "unhashable type: 'list'\nunhashable type: 'dict'\n{1: 2}\n");
+            run_test!(item_dunders: "class Grid:\n    def __getitem__(self, k):\n        return k * 2\n    def __setitem__(self, k, v):\n        print(\"set\", k, v)\ng = Grid()\ng[3] += 1\nprint(g[\"a\"])"
+                => "set 3 7\naa\n");
+            run_test!(unhashable_keys: "def args(*a):\n    return a\nfor make in [lambda: {[1]: 2}, lambda: {1, {}}, lambda: {args(1, [2]): 3}, lambda: {x: 1 for x in [[]]}]:\n    try:\n        make()\n    except TypeError as e:\n        print(e)"
+                => "unhashable type: 'list'\nunhashable type: 'dict'\nunhashable type: 'list'\nunhashable type: 'list'\n");
+            run_test!(display_containing_itself: "xs = [1]\nxs[0] = xs\nprint(xs, [xs])" => "[[...]] [[[...]]]\n");
+            run_test!(displays: "print([], {}, [1, [2, 3],], {1, 2, 1}, {\"a\": 1, \"b\": 2, \"a\": 3})"
+                => "[] {} [1, [2, 3]] {1, 2} {'a': 3, 'b': 2}\n");
+            run_test!(loop_variable_does_not_leak: "x = \"outer\"\nys = [x for x in \"ab\"]\nzs = {x: 1 for x in \"cd\"}\ng = (x for x in \"ef\")\nfor v in g:\n    pass\nprint(ys, zs, x)\nprint([z for z in \"a\"])\ntry:\n    z\nexcept NameError:\n    print(\"no z\")"
+                => "['a', 'b'] {'c': 1, 'd': 1} outer\n['a']\nno z\n");
+            run_test!(first_iterable_in_enclosing_scope: "x = \"ab\"\nprint([x for x in x])"
+                => "['a', 'b']\n");
+            run_test!(comprehension_in_function: "def f(n):\n    return [i * n for i in \"ab\"]\nprint(f(2))"
+                => "['aa', 'bb']\n");
+            run_test!(generator_expressions_are_lazy: "def naturals():\n    n = 0\n    while True:\n        yield n\n        n += 1\n\nevens = (n for n in naturals() if n % 2 == 0)\nprint(next(evens), next(evens), next(evens))\ndef total(it):\n    t = 0\n    for v in it:\n        t += v\n    return t\nprint(total(x * 10 for x in [1, 2, 3]))"
+                => "0 2 4\n60\n");
+            run_test!(conditional_expressions: "x = 5\nprint(\"big\" if x > 3 else \"small\", 1 + 2 if False else 3 * 4)\nprint(1 if x < 0 else 2 if x < 3 else 3)\nf = lambda n: \"even\" if n % 2 == 0 else \"odd\"\nprint(f(2), f(3))"
+                => "big 12\n3\neven odd\n");
+            run_test!(conditional_expression_is_lazy: "def boom():\n    raise ValueError\nprint(1 if True else boom(), boom() if False else 2)"
+                => "1 2\n");
+            run_test!(conditional_in_comprehension: "print([n if n % 2 else 0 - n for n in [1, 2, 3] if n != 3])"
+                => "[1, -2]\n");
+            run_test!(walrus_where_python_allows_it: "def f(*a):\n    return a[0]\nxs = [7, 8]\nif a := 1:\n    print(f(b := 2), xs[c := 1], [d := 3, d], {e := 4}, (g := 5))\nprint(a, b, c, d, e, g, h := 6)"
+                => "2 8 [3, 3] {4} 5\n1 2 1 3 4 5 6\n");
+            run_test!(walrus_in_while: "it = iter(\"abc\")\nwhile (c := next(it, None)) != None:\n    print(c)\nwhile n := next(it, 0):\n    pass\nprint(c, n, (y := 10) + y)"
+                => "a\nb\nc\nNone 0 20\n");
+            run_test!(bad_input_is_value_error: "try:\n    x = input(\"\")\nexcept ValueError:\n    print(\"not a number\")", "abc\n"
+                => "not a number\n");
+
+            /// Run a program that should fail, returning its error.
+            fn run_err(src: &str, limits: Limits) -> (Error, String) {
+                let mut tokens = Tokenizer::lex(src).unwrap();
+                let prgm = Prgm::parse(&mut tokens).unwrap();
+                let mut output = Vec::new();
+                let mut ctx = Context::new("".as_bytes(), &mut output);
+                ctx.set_limits(limits);
+                let err = prgm.eval(&mut ctx).unwrap_err();
+                drop(ctx);
+                (err, String::from_utf8(output).unwrap())
+            }
+
+            #[test]
+            fn uncaught_exception() {
+                let (err, output) = run_err(
+                    "try:\n    raise ValueError(\"a\")\nexcept TypeError:\n    print(1)\nfinally:\n    print(2)",
+                    Limits::default(),
+                );
+                assert_eq!(
+                    err.kind,
+                    Kind::Exception(Exception {
+                        ty: ExcType::ValueError,
+                        message: "a".to_string()
+                    })
+                );
+                assert_eq!(err.span.start, Loc { row: 2, col: 5 });
+                assert!(err.traceback().ends_with("ValueError: a\n"));
+                assert_eq!(output, "2\n");
+            }
+
+            #[test]
+            fn traceback_through_functions() {
+                let (err, _) = run_err(
+                    "def g(x):\n    return 1 // x\ndef f():\n    return g(0)\nprint(f())",
+                    Limits::default(),
+                );
+                assert_eq!(err.kind, Kind::DivisionByZero);
+                assert_eq!(
+                    err.traceback(),
+                    "Traceback (most recent call last):\n  \
+                     row 5, col 7, in <module>\n  \
+                     row 4, col 12, in f\n  \
+                     row 2, col 12, in g\n\
+                     ZeroDivisionError: division by zero\n"
+                );
+            }
+
+            #[test]
+            fn binding_errors() {
+                let param = |kind: fn(String, String) -> Kind, param: &str| {
+                    kind("f".to_string(), param.to_string())
+                };
+                let missing = |name, param| Kind::MissingArgument { name, param };
+                let duplicate = |name, param| Kind::DuplicateArgument { name, param };
+                let unexpected = |name, param| Kind::UnexpectedKeyword { name, param };
+                for (call, kind) in [
+                    ("f(1)", param(missing, "b")),
+                    ("f(1, 2)", param(missing, "d")),
+                    ("f(1, 2, d=0, a=1)", param(duplicate, "a")),
+                    ("f(1, 2, d=2, e=3)", param(unexpected, "e")),
+                    (
+                        "f(1, 2, 3, 4)",
+                        Kind::Arity {
+                            name: "f".to_string(),
+                            expected: 3,
+                            got: 4,
+                        },
+                    ),
+                ] {
+                    let (err, _) = run_err(
+                        &format!("def f(a, b, c=1, *, d):\n    pass\nx = {}", call),
+                        Limits::default(),
+                    );
+                    assert_eq!(err.kind, kind, "{}", call);
+                    assert_eq!(
+                        err.span,
+                        Span {
+                            start: Loc { row: 3, col: 5 },
+                            end: Loc {
+                                row: 3,
+                                col: 4 + call.len()
+                            }
+                        }
+                    );
+                }
+            }
+
+            #[test]
+            fn unbounded_recursion_hits_depth_limit() {
+                let (err, _) = run_err(
+                    "def f():\n    return f()\nf()",
+                    Limits {
+                        max_depth: Some(50),
+                        ..Limits::default()
+                    },
+                );
+                assert_eq!(err.kind, Kind::DepthLimit);
+                assert_eq!(err.trace.len(), 50);
+            }
+
+            #[test]
+            fn missing_module() {
+                let (err, _) = run_err("import no_such_module", Limits::default());
+                assert_eq!(err.kind, Kind::ModuleNotFound("no_such_module".to_string()));
+                assert_eq!(
+                    err.kind.as_exception().unwrap().ty,
+                    ExcType::ModuleNotFoundError
+                );
+            }
+
+            #[test]
+            fn nonlocal_needs_binding() {
+                for (src, row) in [
+                    ("x = 1\ndef f():\n    nonlocal x", 3),
+                    (
+                        "def f():\n    def g():\n        nonlocal x\n        x = 1",
+                        3,
+                    ),
+                    (
+                        "def f():\n    global x\n    x = 1\n    def g():\n        nonlocal x",
+                        5,
+                    ),
+                ] {
+                    let mut tokens = Tokenizer::lex(src).unwrap();
+                    let err = Prgm::parse(&mut tokens).unwrap_err();
+                    assert_eq!(
+                        err.kind,
+                        Kind::Syntax("no binding for nonlocal `x` found".to_string())
+                    );
+                    assert_eq!(err.span.start.row, row);
+                }
+            }
+
+            #[test]
+            fn read_before_assignment() {
+                let (err, _) = run_err(
+                    "x = 1\ndef f():\n    print(x)\n    x = 2\nf()",
+                    Limits::default(),
+                );
+                assert_eq!(err.kind, Kind::UnboundLocal("x".to_string()));
+                assert_eq!(err.span.start.row, 3);
+                assert_eq!(
+                    err.kind.as_exception().unwrap().ty,
+                    ExcType::UnboundLocalError
+                );
+            }
+
+            #[test]
+            fn misplaced_jumps() {
+                for (src, message) in [
+                    ("return 1", "`return` outside function"),
+                    (
+                        "def f():\n    while 1:\n        pass\n    break",
+                        "`break` outside loop",
+                    ),
+                    (
+                        "while 1:\n    def f():\n        continue",
+                        "`continue` outside loop",
+                    ),
+                    (
+                        "nonlocal x",
+                        "nonlocal declaration not allowed at module level",
+                    ),
+                    (
+                        "def f(a, a): pass",
+                        "duplicate argument `a` in function definition",
+                    ),
+                    (
+                        "def f(a=1, b): pass",
+                        "non-default parameter follows default parameter",
+                    ),
+                    (
+                        "def f(*, a, *b): pass",
+                        "`*` parameter may appear only once",
+                    ),
+                    (
+                        "def f(**k, a): pass",
+                        "parameters cannot follow the `**` parameter",
+                    ),
+                    ("def f(a, *): pass", "named parameters must follow bare `*`"),
+                    ("lambda *, **k: 1", "named parameters must follow bare `*`"),
+                    ("f(a=1, 2)", "positional argument follows keyword argument"),
+                    (
+                        "f(**k, *a)",
+                        "positional argument follows keyword argument unpacking",
+                    ),
+                    ("f(a=1, a=2)", "keyword argument repeated: `a`"),
+                    ("f() = 1", "cannot assign to expression"),
+                    ("class A:\n    return 1", "`return` outside function"),
+                    ("yield 1", "`yield` outside function"),
+                    (
+                        "f(x for x in a, 1)",
+                        "generator expression must be parenthesized",
+                    ),
+                    (
+                        "f(1, x for x in a)",
+                        "generator expression must be parenthesized",
+                    ),
+                    (
+                        "def f():\n    class A:\n        yield",
+                        "`yield` outside function",
+                    ),
+                ] {
+                    let mut tokens = Tokenizer::lex(src).unwrap();
+                    assert_eq!(
+                        Prgm::parse(&mut tokens).unwrap_err().kind,
+                        Kind::Syntax(message.to_string())
+                    );
+                }
+            }
+
+            #[test]
+            fn walrus_targets() {
+                for (src, message, end) in [
+                    (
+                        "x.a := 1",
+                        "cannot use assignment expressions with attribute",
+                        3,
+                    ),
+                    (
+                        "f() := 1",
+                        "cannot use assignment expressions with function call",
+                        3,
+                    ),
+                    (
+                        "(1 := 1)",
+                        "cannot use assignment expressions with literal",
+                        2,
+                    ),
+                    (
+                        "(a + b := 1)",
+                        "cannot use assignment expressions with expression",
+                        6,
+                    ),
+                ] {
+                    let mut tokens = Tokenizer::lex(src).unwrap();
+                    let err = Prgm::parse(&mut tokens).unwrap_err();
+                    assert_eq!(err.kind, Kind::Syntax(message.to_string()));
+                    assert_eq!(err.span.end, Loc { row: 1, col: end }, "{}", src);
+                }
+                let mut tokens = Tokenizer::lex("x = 1 if y").unwrap();
+                let err = Prgm::parse(&mut tokens).unwrap_err();
+                assert_eq!(
+                    err.kind,
+                    Kind::Syntax("expected `else` after `if` expression".to_string())
+                );
+            }
+
+            #[test]
+            fn unparenthesized_walrus() {
+                for src in [
+                    "y := 3",
+                    "x = y := 1",
+                    "f(x=y := 1)",
+                    "def f():\n    return y := 1",
+                    "assert y := 1",
+                    "d = {1: y := 2}",
+                    "for x in y := z:\n    pass",
+                ] {
+                    let mut tokens = Tokenizer::lex(src).unwrap();
+                    let err = Prgm::parse(&mut tokens).unwrap_err();
+                    assert_eq!(
+                        err.kind,
+                        Kind::Syntax(
+                            "assignment expressions must be parenthesized here".to_string()
+                        ),
+                        "{}",
+                        src
+                    );
+                }
+                for src in ["g = lambda: y := 3", "d = {y := 1: 2}"] {
+                    let mut tokens = Tokenizer::lex(src).unwrap();
+                    assert!(Prgm::parse(&mut tokens).is_err(), "{}", src);
+                }
+            }
+
+            #[test]
+            fn class_errors() {
+                for (src, kind) in [
+                    (
+                        "class A:\n    pass\nA(1)",
+                        Kind::Type("`A` takes no arguments".to_string()),
+                    ),
+                    (
+                        "class A:\n    def __init__(self):\n        return 1\nA()",
+                        Kind::Type("`__init__` should return None, not int".to_string()),
+                    ),
+                    (
+                        "class A(1):\n    pass",
+                        Kind::Type("base class must be a class, not int".to_string()),
+                    ),
+                    (
+                        "class A:\n    def __str__(self):\n        return 1\nprint(A())",
+                        Kind::Type("`__str__` returned non-string (type int)".to_string()),
+                    ),
+                    (
+                        "x = 1\nx.y = 2",
+                        Kind::Attribute {
+                            ty: "int".to_string(),
+                            name: "y".to_string(),
+                        },
+                    ),
+                    (
+                        "for x in 1:\n    pass",
+                        Kind::Type("int object is not iterable".to_string()),
+                    ),
+                    (
+                        "next(\"ab\")",
+                        Kind::Type("str object is not an iterator".to_string()),
+                    ),
+                    (
+                        "def g():\n    yield next(it)\nit = g()\nnext(it)",
+                        Kind::Exception(Exception {
+                            ty: ExcType::ValueError,
+                            message: "generator already executing".to_string(),
+                        }),
+                    ),
+                    (
+                        "def f():\n    return super()\nf()",
+                        Kind::Exception(Exception {
+                            ty: ExcType::RuntimeError,
+                            message: "super(): no class to look methods up from".to_string(),
+                        }),
+                    ),
+                ] {
+                    assert_eq!(run_err(src, Limits::default()).0.kind, kind, "{}", src);
+                }
+            }
+
+            #[test]
+            fn raise_non_exception() {
+                let (err, _) = run_err("raise 1", Limits::default());
+                assert_eq!(
+                    err.kind,
+                    Kind::Type("exceptions must derive from BaseException".to_string())
+                );
+            }
+
+            #[test]
+            fn limits_are_not_caught() {
+                let (err, output) = run_err(
+                    "try:\n    x = 1\n    x = 2\n    x = 3\nexcept:\n    print(\"caught\")",
+                    Limits {
+                        max_steps: Some(4),
+                        ..Limits::default()
+                    },
+                );
+                assert_eq!(err.kind, Kind::StepLimit);
+                assert!(output.is_empty());
+            }
+
+            #[test]
+            fn else_needs_except() {
+                let mut tokens =
+                    Tokenizer::lex("try:\n    pass\nelse:\n    pass\nfinally:\n    pass").unwrap();
+                assert_eq!(Prgm::parse(&mut tokens).unwrap_err().kind, Kind::Parser);
+            }
+
+            #[test]
+            fn bad_input() {
+                let mut tokens = Tokenizer::lex("x = input(\"n? \")").unwrap();
+                let prgm = Prgm::parse(&mut tokens).unwrap();
+                let mut output = Vec::new();
+                let err = prgm
+                    .eval(&mut Context::new("nope\n".as_bytes(), &mut output))
+                    .unwrap_err();
+                assert_eq!(
+                    err.kind,
+                    Kind::Value("invalid literal for int(): `nope`".to_string())
+                );
+            }
+
+            #[test]
+            fn undefined_name() {
+                let mut tokens = Tokenizer::lex("x = 1\nprint(x + y)").unwrap();
+                let prgm = Prgm::parse(&mut tokens).unwrap();
+                let err = prgm
+                    .eval(&mut Context::new("".as_bytes(), Vec::new()))
+                    .unwrap_err();
+                assert_eq!(err.kind, Kind::UndefinedName("y".to_string()));
+                assert_eq!(err.span.start, Loc { row: 2, col: 11 });
+                assert!(err.trace.is_empty());
+            }
+
+            #[test]
+            fn type_error() {
+                let mut tokens = Tokenizer::lex("x = \"a\" - 1").unwrap();
+                let prgm = Prgm::parse(&mut tokens).unwrap();
+                let err = prgm
+                    .eval(&mut Context::new("".as_bytes(), Vec::new()))
+                    .unwrap_err();
+                assert_eq!(
+                    err.kind,
+                    Kind::Type("unsupported operand types for -: str and int".to_string())
+                );
+            }
+
+            #[test]
+            fn print_error_is_not_swallowed() {
+                let mut tokens = Tokenizer::lex("print(1, y)").unwrap();
+                let prgm = Prgm::parse(&mut tokens).unwrap();
+                let mut output = Vec::new();
+                assert!(prgm
+                    .eval(&mut Context::new("".as_bytes(), &mut output))
+                    .is_err());
+                assert!(output.is_empty());
+            }
+        }
+    }
 }
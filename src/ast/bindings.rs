@@ -0,0 +1,91 @@
+//! Working out, as a program is parsed, which names are local to each function.
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::error::{Error, Kind, Result};
+use crate::tokenizer::TokenStream;
+use crate::Span;
+
+/// The names bound in a function or class body being parsed.
+#[derive(PartialEq, Eq, Debug)]
+pub(crate) struct Bindings {
+    /// Whether it's a function body, whose bindings `nonlocal` can refer to, rather than a
+    /// class body.
+    function: bool,
+    bound: HashSet<String>,
+    globals: HashSet<String>,
+    /// The names declared `nonlocal`, and where.
+    nonlocals: Vec<(String, Span)>,
+    /// `nonlocal` declarations in the functions inside this body that haven't found their
+    /// binding yet.
+    pending: Vec<(String, Span)>,
+}
+
+impl TokenStream {
+    /// Start parsing a function body if `function`, otherwise a class body.
+    pub(crate) fn enter_body(&mut self, function: bool) {
+        self.bindings.push(Bindings {
+            function,
+            bound: HashSet::new(),
+            globals: HashSet::new(),
+            nonlocals: Vec::new(),
+            pending: Vec::new(),
+        });
+    }
+
+    /// Note that the body being parsed assigns `name`.
+    pub(crate) fn bind(&mut self, name: &str) {
+        if let Some(body) = self.bindings.last_mut() {
+            body.bound.insert(name.to_string());
+        }
+    }
+
+    /// Note that the body being parsed declares `name` `global`, or `nonlocal` at `span`.
+    pub(crate) fn declare(&mut self, name: &str, nonlocal: bool, span: Span) {
+        if let Some(body) = self.bindings.last_mut() {
+            if nonlocal {
+                body.nonlocals.push((name.to_string(), span));
+            } else {
+                body.globals.insert(name.to_string());
+            }
+        }
+    }
+
+    /// Finish parsing the innermost body, returning the names local to it: the ones it binds
+    /// that it doesn't declare `global` or `nonlocal`.
+    ///
+    /// Once the outermost function is done, any `nonlocal` left without an enclosing function
+    /// that binds the name is a syntax error.
+    pub(crate) fn leave_body(&mut self) -> Result<Rc<HashSet<String>>> {
+        let body = self
+            .bindings
+            .pop()
+            .expect("leaving a body that was entered");
+        let locals: HashSet<String> = body
+            .bound
+            .into_iter()
+            .filter(|name| {
+                !body.globals.contains(name) && body.nonlocals.iter().all(|(n, _)| n != name)
+            })
+            .collect();
+        // a function resolves the declarations inside it that name one of its locals, or one of
+        // its own `nonlocal`s, which has to resolve in turn
+        let inner = body.pending.into_iter().filter(|(name, _)| {
+            !body.function
+                || !(locals.contains(name) || body.nonlocals.iter().any(|(n, _)| n == name))
+        });
+        let pending: Vec<_> = body.nonlocals.iter().cloned().chain(inner).collect();
+        match self.bindings.last_mut() {
+            Some(outer) => outer.pending.extend(pending),
+            None => {
+                if let Some((name, span)) = pending.into_iter().next() {
+                    return Err(Error::new(
+                        Kind::Syntax(format!("no binding for nonlocal `{}` found", name)),
+                        span,
+                    ));
+                }
+            }
+        }
+        Ok(Rc::new(locals))
+    }
+}
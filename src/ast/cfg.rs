@@ -0,0 +1,519 @@
+//! Control-flow graphs of basic blocks, drawn with Graphviz.
+use super::dot::escape;
+use super::unparse::{handler_header, header};
+use super::{Blck, Prgm, Stmt, StmtData};
+
+impl Prgm {
+    /// A Graphviz digraph of the control flow in the program's top level, and in each function and
+    /// class body, as a cluster of basic blocks each.
+    ///
+    /// Edges out of a branch are labelled with when they're taken. An exception can leave a `try`
+    /// body from anywhere, but is drawn leaving from where the body starts, as a dashed edge to
+    /// each handler and to where it goes if none of them matches. `return`, `raise`, `break` and
+    /// `continue` go through the `finally` blocks they leave, which then carry on to where they
+    /// were going.
+    #[must_use]
+    pub fn cfg_dot(&self) -> String {
+        let mut units = vec![("<module>".to_string(), &self.main)];
+        let mut out =
+            String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+        let mut i = 0;
+        while let Some((name, body)) = units.get(i).cloned() {
+            let cfg = Cfg::build(&name, body, &mut units);
+            cfg.write_dot(i, &name, &mut out);
+            i += 1;
+        }
+        out + "}\n"
+    }
+}
+
+/// A basic block: statements that run one after another, then the edges out of it.
+#[derive(Default)]
+struct Block {
+    lines: Vec<String>,
+    edges: Vec<(usize, Option<&'static str>)>,
+}
+
+/// The control-flow graph of one function, class body or top level.
+struct Cfg {
+    /// The blocks, the first being where it starts and the second where it exits.
+    blocks: Vec<Block>,
+}
+
+const ENTRY: usize = 0;
+const EXIT: usize = 1;
+
+/// A statement that leaves the normal flow for somewhere further out.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Jump {
+    Return,
+    Raise,
+    Break,
+    Continue,
+}
+
+impl Jump {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Return => "return",
+            Self::Raise => "raise",
+            Self::Break => "break",
+            Self::Continue => "continue",
+        }
+    }
+}
+
+/// A statement around the current one that a `Jump` can stop at.
+enum Scope {
+    /// A loop, with the blocks `continue` and `break` go to.
+    Loop { head: usize, exit: usize },
+    /// A `try` body with handlers, where a `raise` is one of the exceptions the dashed edges out
+    /// of the body stand for.
+    Handlers,
+    /// A `try` with a `finally` block, which every jump out of it goes through, and the jumps
+    /// that do, to carry on with after it.
+    Finally { block: usize, pending: Vec<Jump> },
+}
+
+/// Builds a `Cfg`, tracking the block being added to and the statements it's in.
+struct Builder<'p, 'u> {
+    cfg: Cfg,
+    /// The block statements are being added to, or `None` if they can't be reached.
+    current: Option<usize>,
+    /// The loops and `try` statements around the current statement, innermost last.
+    scopes: Vec<Scope>,
+    /// The name of the unit being built, to name the functions and classes in it.
+    name: &'u str,
+    /// The units still to draw, which nested functions and classes are added to.
+    units: &'u mut Vec<(String, &'p Blck)>,
+}
+
+impl Cfg {
+    fn build<'p>(name: &str, body: &'p Blck, units: &mut Vec<(String, &'p Blck)>) -> Self {
+        let mut builder = Builder {
+            cfg: Self {
+                blocks: vec![Block::default(), Block::default()],
+            },
+            current: Some(ENTRY),
+            scopes: Vec::new(),
+            name,
+            units,
+        };
+        builder.block(body);
+        if let Some(current) = builder.current {
+            builder.edge(current, EXIT, None);
+        }
+        let mut cfg = builder.cfg;
+        cfg.simplify();
+        cfg
+    }
+
+    /// Skip over empty blocks that only lead on to another block, and drop blocks nothing reaches
+    /// that have nothing in them.
+    fn simplify(&mut self) {
+        let forward: Vec<Option<usize>> = (0..self.blocks.len())
+            .map(|i| match &self.blocks[i] {
+                Block { lines, edges } if i > EXIT && lines.is_empty() => match edges[..] {
+                    [(to, None)] => Some(to),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        let resolve = |mut to: usize| {
+            // chains of empty blocks are finite, since each one was made before the next
+            while let Some(next) = forward[to] {
+                to = next;
+            }
+            to
+        };
+        for block in &mut self.blocks {
+            for (to, _) in &mut block.edges {
+                *to = resolve(*to);
+            }
+        }
+        for (i, skipped) in forward.iter().enumerate() {
+            if skipped.is_some() {
+                self.blocks[i].edges.clear();
+            }
+        }
+    }
+
+    fn reached(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.blocks.len()];
+        reached[ENTRY] = true;
+        for block in &self.blocks {
+            for (to, _) in &block.edges {
+                reached[*to] = true;
+            }
+        }
+        reached
+    }
+
+    /// Write the graph as the `n`th cluster of a digraph.
+    fn write_dot(&self, n: usize, name: &str, out: &mut String) {
+        *out += &format!(
+            "    subgraph cluster_{} {{\n        label=\"{}\";\n",
+            n,
+            escape(name)
+        );
+        let reached = self.reached();
+        for (i, block) in self.blocks.iter().enumerate() {
+            let label = match i {
+                ENTRY if block.lines.is_empty() => "entry".to_string(),
+                EXIT => "exit".to_string(),
+                _ if block.lines.is_empty() && !reached[i] => continue,
+                _ => block.lines.iter().map(|l| escape(l) + "\\l").collect(),
+            };
+            let shape = if i == EXIT && block.lines.is_empty() {
+                ", shape=oval"
+            } else {
+                ""
+            };
+            *out += &format!("        b{}_{} [label=\"{}\"{}];\n", n, i, label, shape);
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            for (to, label) in &block.edges {
+                let attrs = match *label {
+                    Some(label @ "exception") => format!(" [label=\"{}\", style=dashed]", label),
+                    Some(label) => format!(" [label=\"{}\"]", label),
+                    None => String::new(),
+                };
+                *out += &format!("        b{}_{} -> b{}_{}{};\n", n, i, n, to, attrs);
+            }
+        }
+        *out += "    }\n";
+    }
+}
+
+impl<'p> Builder<'p, '_> {
+    fn new_block(&mut self) -> usize {
+        self.cfg.blocks.push(Block::default());
+        self.cfg.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: Option<&'static str>) {
+        self.cfg.blocks[from].edges.push((to, label));
+    }
+
+    /// The block to add to, starting one for unreachable code if need be.
+    fn current(&mut self) -> usize {
+        match self.current {
+            Some(current) => current,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+                block
+            }
+        }
+    }
+
+    /// Start a new block that `from` leads to.
+    fn branch(&mut self, from: usize, label: Option<&'static str>) -> usize {
+        let block = self.new_block();
+        self.edge(from, block, label);
+        self.current = Some(block);
+        block
+    }
+
+    /// Join the ends of branches, if any of them can be reached, into a new block.
+    fn join(&mut self, ends: &[Option<usize>]) {
+        self.current = None;
+        if ends.iter().any(Option::is_some) {
+            let block = self.new_block();
+            for end in ends.iter().flatten() {
+                self.edge(*end, block, None);
+            }
+            self.current = Some(block);
+        }
+    }
+
+    /// Add the edges for `jump` out of `from`, to the innermost scope that stops it, or out of the
+    /// unit.
+    fn jump(&mut self, from: usize, jump: Jump) {
+        self.leave(from, jump, jump.label());
+    }
+
+    /// Add the edges for `jump` out of `from`, as `jump` does, labelled `label`.
+    fn leave(&mut self, from: usize, jump: Jump, label: &'static str) {
+        for scope in self.scopes.iter_mut().rev() {
+            match scope {
+                Scope::Loop { head, exit } if matches!(jump, Jump::Break | Jump::Continue) => {
+                    let to = if jump == Jump::Break { *exit } else { *head };
+                    self.cfg.blocks[from].edges.push((to, None));
+                    return;
+                }
+                Scope::Handlers if jump == Jump::Raise => return,
+                Scope::Finally { block, pending } => {
+                    self.cfg.blocks[from].edges.push((*block, Some(label)));
+                    if !pending.contains(&jump) {
+                        pending.push(jump);
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.edge(from, EXIT, Some(label));
+    }
+
+    /// Add a function or class defined in this unit to the ones to draw.
+    fn unit(&mut self, name: &str, body: &'p Blck) {
+        let name = match self.name {
+            "<module>" => name.to_string(),
+            outer => format!("{}.{}", outer, name),
+        };
+        self.units.push((name, body));
+    }
+
+    fn block(&mut self, blck: &'p Blck) {
+        for stmt in &blck.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        let current = self.current();
+        self.cfg.blocks[current].lines.push(header(stmt));
+        match &stmt.data {
+            StmtData::Retn(_) | StmtData::Rais(_) | StmtData::Brek | StmtData::Cont => {
+                let jump = match stmt.data {
+                    StmtData::Retn(_) => Jump::Return,
+                    StmtData::Rais(_) => Jump::Raise,
+                    StmtData::Brek => Jump::Break,
+                    _ => Jump::Continue,
+                };
+                self.jump(current, jump);
+                self.current = None;
+            }
+            StmtData::Cond { body, orelse, .. } => {
+                self.branch(current, Some("true"));
+                self.block(body);
+                let body_end = self.current;
+                let orelse_end = match orelse {
+                    Some(orelse) => {
+                        self.branch(current, Some("false"));
+                        self.block(orelse);
+                        self.current
+                    }
+                    None => {
+                        // the false edge goes straight to where the branches join
+                        let block = self.new_block();
+                        self.edge(current, block, Some("false"));
+                        Some(block)
+                    }
+                };
+                self.join(&[body_end, orelse_end]);
+            }
+            StmtData::Whle { body, .. } | StmtData::For { body, .. } => {
+                let (taken, done) = if let StmtData::Whle { .. } = stmt.data {
+                    ("true", "false")
+                } else {
+                    ("next", "done")
+                };
+                // the loop's header gets a block of its own, to come back to
+                let head = if current != ENTRY && self.cfg.blocks[current].lines.len() == 1 {
+                    current
+                } else {
+                    let line = self.cfg.blocks[current].lines.pop();
+                    let head = self.branch(current, None);
+                    self.cfg.blocks[head].lines.extend(line);
+                    head
+                };
+                let exit = self.new_block();
+                self.edge(head, exit, Some(done));
+                self.scopes.push(Scope::Loop { head, exit });
+                self.branch(head, Some(taken));
+                self.block(body);
+                self.scopes.pop();
+                if let Some(end) = self.current {
+                    self.edge(end, head, None);
+                }
+                self.current = Some(exit);
+            }
+            StmtData::Try {
+                body,
+                handlers,
+                orelse,
+                finally,
+            } => {
+                let start = self.branch(current, None);
+                let handler_blocks: Vec<usize> = handlers
+                    .iter()
+                    .map(|handler| {
+                        let block = self.new_block();
+                        self.edge(start, block, Some("exception"));
+                        self.cfg.blocks[block].lines.push(handler_header(handler));
+                        block
+                    })
+                    .collect();
+                let finally_block = finally.as_ref().map(|_| {
+                    // an exception no handler catches still goes through `finally`
+                    let block = self.new_block();
+                    self.edge(start, block, Some("exception"));
+                    self.cfg.blocks[block].lines.push("finally".to_string());
+                    self.scopes.push(Scope::Finally {
+                        block,
+                        pending: vec![Jump::Raise],
+                    });
+                    block
+                });
+                if finally_block.is_none() {
+                    // and without one, goes on out of the `try`
+                    self.leave(start, Jump::Raise, "exception");
+                }
+
+                self.current = Some(start);
+                if !handler_blocks.is_empty() {
+                    self.scopes.push(Scope::Handlers);
+                }
+                self.block(body);
+                if !handler_blocks.is_empty() {
+                    self.scopes.pop();
+                }
+                if let Some(orelse) = orelse {
+                    if let Some(end) = self.current {
+                        let block = self.branch(end, None);
+                        self.cfg.blocks[block].lines.push("else".to_string());
+                        self.block(orelse);
+                    }
+                }
+                let mut ends = vec![self.current];
+                for (handler, block) in handlers.iter().zip(handler_blocks) {
+                    self.current = Some(block);
+                    self.block(&handler.body);
+                    ends.push(self.current);
+                }
+
+                let (Some(finally), Some(block)) = (finally, finally_block) else {
+                    self.join(&ends);
+                    return;
+                };
+                let Some(Scope::Finally { pending, .. }) = self.scopes.pop() else {
+                    unreachable!("pushed for this `finally`");
+                };
+                let falls_through = ends.iter().any(Option::is_some);
+                for end in ends.into_iter().flatten() {
+                    self.edge(end, block, None);
+                }
+                self.current = Some(block);
+                self.block(finally);
+                // after `finally`, carry on with whatever led to it
+                if let Some(end) = self.current {
+                    for jump in pending {
+                        self.jump(end, jump);
+                    }
+                    if !falls_through {
+                        self.current = None;
+                    }
+                }
+            }
+            StmtData::Defn { name, body, .. } => self.unit(name, body),
+            StmtData::Clss { name, body, .. } => self.unit(name, body),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Ast;
+    use crate::tokenizer::Tokenizer;
+
+    fn cfg(source: &str) -> String {
+        let prgm = Prgm::parse(&mut Tokenizer::lex(source).unwrap()).unwrap();
+        prgm.cfg_dot()
+    }
+
+    #[test]
+    fn straight_line() {
+        assert_eq!(
+            cfg("x = 1\nprint(x)"),
+            "digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n    \
+             subgraph cluster_0 {\n        label=\"<module>\";\n        \
+             b0_0 [label=\"x = 1\\lprint(x)\\l\"];\n        \
+             b0_1 [label=\"exit\", shape=oval];\n        \
+             b0_0 -> b0_1;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn branches_and_loops() {
+        let dot = cfg("def f(n):\n    \
+                       while n > 0:\n        \
+                       if n == 3:\n            break\n        \
+                       n -= 1\n    \
+                       return n\n\
+                       print(f(5))");
+        assert!(dot.contains("label=\"<module>\""));
+        assert!(dot.contains("label=\"f\""));
+        // the loop's header has a block of its own, which the body comes back to
+        assert!(dot.contains("b1_2 [label=\"while n > 0\\l\"]"));
+        assert!(dot.contains("b1_0 -> b1_2;"));
+        assert!(dot.contains("b1_2 -> b1_3 [label=\"false\"];"));
+        assert!(dot.contains("b1_2 -> b1_4 [label=\"true\"];"));
+        assert!(dot.contains("b1_4 [label=\"if n == 3\\l\"]"));
+        // `break` leaves the loop, and the body loops back through `n -= 1`
+        assert!(dot.contains("b1_5 [label=\"break\\l\"]"));
+        assert!(dot.contains("b1_5 -> b1_3;"));
+        assert!(dot.contains("b1_4 -> b1_7 [label=\"false\"];"));
+        assert!(dot.contains("b1_7 [label=\"n -= 1\\l\"]"));
+        assert!(dot.contains("b1_7 -> b1_2;"));
+        assert!(dot.contains("b1_3 [label=\"return n\\l\"]"));
+        assert!(dot.contains("b1_3 -> b1_1 [label=\"return\"];"));
+    }
+
+    #[test]
+    fn try_and_nested_units() {
+        let dot = cfg("class C:\n    def m(self):\n        \
+                       try:\n            g()\n        \
+                       except E as e:\n            raise\n        \
+                       finally:\n            h()");
+        assert!(dot.contains("label=\"C\""));
+        assert!(dot.contains("label=\"C.m\""));
+        assert!(dot.contains("[label=\"except E as e\\lraise\\l\"]"));
+        assert!(dot.contains("[label=\"exception\", style=dashed]"));
+        assert!(dot.contains("[label=\"finally\\lh()\\l\"]"));
+    }
+
+    #[test]
+    fn jumps_go_through_finally() {
+        let dot = cfg("def g():\n try:\n  return 1\n finally:\n  print(2)");
+        assert!(dot.contains("b1_3 [label=\"finally\\lprint(2)\\l\"];"));
+        assert!(dot.contains("b1_2 -> b1_3 [label=\"return\"];"));
+        assert!(dot.contains("b1_3 -> b1_1 [label=\"return\"];"));
+        assert!(!dot.contains("b1_2 -> b1_1"));
+
+        let dot = cfg(
+            "while x:\n    try:\n        break\n    finally:\n        f()\n\
+                       try:\n    raise E\nexcept E:\n    pass",
+        );
+        assert!(dot.contains("b0_5 -> b0_6 [label=\"break\"];"));
+        assert!(dot.contains("b0_6 -> b0_3;"));
+        assert!(dot.contains("b0_8 [label=\"except E\\lpass\\l\"];"));
+        // the `raise` is one of the exceptions the dashed edges stand for, not an edge of its own
+        assert!(dot.contains("b0_7 -> b0_8 [label=\"exception\", style=dashed];"));
+        assert!(!dot.contains("b0_7 -> b0_8 [label=\"raise\"];"));
+        assert!(!dot.contains("b0_7 -> b0_1 [label=\"raise\"];"));
+    }
+
+    #[test]
+    fn unmatched_exceptions_leave_the_try() {
+        let dot = cfg("def f():\n    \
+                       try:\n        g()\n    except E:\n        pass\n    \
+                       try:\n        \
+                       try:\n            h()\n        except F:\n            raise\n    \
+                       finally:\n        k()");
+        // out of the function, with no `finally` to go through
+        assert!(dot.contains("b1_2 [label=\"g()\\l\"]"));
+        assert!(dot.contains("b1_2 -> b1_3 [label=\"exception\", style=dashed];"));
+        assert!(dot.contains("b1_2 -> b1_1 [label=\"exception\", style=dashed];"));
+        // or to the `finally` of the `try` around it
+        assert!(dot.contains("b1_6 [label=\"finally\\lk()\\l\"]"));
+        assert!(dot.contains("b1_7 -> b1_8 [label=\"exception\", style=dashed];"));
+        assert!(dot.contains("b1_7 -> b1_6 [label=\"exception\", style=dashed];"));
+        assert!(dot.contains("b1_8 -> b1_6 [label=\"raise\"];"));
+        assert!(dot.contains("b1_6 -> b1_1 [label=\"raise\"];"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Drawing a parsed program as a Graphviz digraph.
+use super::{Ast, Prgm};
+
+impl Prgm {
+    /// The tree `dump` prints, as a Graphviz digraph: a box for each node, labelled like the
+    /// dump (e.g. `Plus` for `+`), and an ellipse for each name and literal.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let dump = self.dump(0);
+        let lines: Vec<(usize, &str)> = dump
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| (line.len() - line.trim_start().len(), line.trim_start()))
+            .collect();
+        let has_children = |i: usize| lines.get(i + 1).is_some_and(|(d, _)| *d > lines[i].0);
+
+        let mut out = String::from("digraph ast {\n    node [shape=box];\n");
+        // the nodes still open, deepest last, as (depth, id)
+        let mut parents: Vec<(usize, usize)> = Vec::new();
+        let mut skip = None;
+        for (i, &(depth, text)) in lines.iter().enumerate() {
+            if skip == Some(i) {
+                continue;
+            }
+            while parents.last().is_some_and(|(d, _)| *d >= depth) {
+                parents.pop();
+            }
+            // a literal or a lookup is drawn as just its value
+            let is_leaf = matches!(text, "Lkup" | "Nmbr" | "Strg" | "Inpt")
+                && has_children(i)
+                && !has_children(i + 1);
+            let (label, shape) = if is_leaf {
+                skip = Some(i + 1);
+                (lines[i + 1].1, "ellipse")
+            } else if has_children(i) {
+                (text, "box")
+            } else {
+                (text, "ellipse")
+            };
+            if shape == "box" {
+                out += &format!("    n{} [label=\"{}\"];\n", i, escape(label));
+            } else {
+                out += &format!(
+                    "    n{} [label=\"{}\", shape={}];\n",
+                    i,
+                    escape(label),
+                    shape
+                );
+            }
+            if let Some((_, parent)) = parents.last() {
+                out += &format!("    n{} -> n{};\n", parent, i);
+            }
+            parents.push((depth, i));
+        }
+        out + "}\n"
+    }
+}
+
+/// Escape `s` for a double-quoted DOT string.
+pub(super) fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    #[test]
+    fn expression_tree() {
+        let prgm = Prgm::parse(&mut Tokenizer::lex("x = 1 + y * \"s\"").unwrap()).unwrap();
+        assert_eq!(
+            prgm.to_dot(),
+            "digraph ast {\n    node [shape=box];\n    \
+             n0 [label=\"Prgm\"];\n    \
+             n1 [label=\"Blck\"];\n    n0 -> n1;\n    \
+             n2 [label=\"Asgn\"];\n    n1 -> n2;\n    \
+             n3 [label=\"x\", shape=ellipse];\n    n2 -> n3;\n    \
+             n4 [label=\"Plus\"];\n    n2 -> n4;\n    \
+             n5 [label=\"1\", shape=ellipse];\n    n4 -> n5;\n    \
+             n7 [label=\"Tmes\"];\n    n4 -> n7;\n    \
+             n8 [label=\"y\", shape=ellipse];\n    n7 -> n8;\n    \
+             n10 [label=\"\\\"s\\\"\", shape=ellipse];\n    n7 -> n10;\n\
+             }\n"
+        );
+    }
+}
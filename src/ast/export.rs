@@ -0,0 +1,541 @@
+//! Exporting a parsed program for other tools, as JSON or S-expressions.
+//!
+//! Both forms carry the same tree, described in `docs/ast-schema.md`; bump `AST_SCHEMA_VERSION` on any
+//! change to it.
+use super::{Arg, Ast, Blck, Clause, CompKind, Expn, Leaf, LeafData, Param, Prgm, Stmt, StmtData};
+use super::{Handler, Target};
+use crate::Span;
+
+/// The version of the exported tree's schema.
+pub const AST_SCHEMA_VERSION: u32 = 2;
+
+impl Prgm {
+    /// The program as JSON: `{"version": 2, "program": {"kind": "Prgm", ...}}`.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        Value::Record(vec![
+            ("version", Value::Int(AST_SCHEMA_VERSION)),
+            ("program", self.export()),
+        ])
+        .write_json(&mut out);
+        out
+    }
+
+    /// The program as an S-expression: `(slpy-ast 2 (Prgm ...))`.
+    #[must_use]
+    pub fn to_sexpr(&self) -> String {
+        let mut out = format!("(slpy-ast {} ", AST_SCHEMA_VERSION);
+        self.export().write_sexpr(&mut out);
+        out.push(')');
+        out
+    }
+
+    fn export(&self) -> Value {
+        node("Prgm", self.span(), vec![("body", block(&self.main))])
+    }
+}
+
+/// The tree both forms are written from.
+enum Value {
+    Null,
+    Bool(bool),
+    Int(u32),
+    Str(String),
+    Span(Span),
+    List(Vec<Value>),
+    /// Named fields, in order; a node if the first two are its `kind` and `span`.
+    Record(Vec<(&'static str, Value)>),
+}
+
+impl Value {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Self::Null => *out += "null",
+            Self::Bool(b) => *out += &b.to_string(),
+            Self::Int(n) => *out += &n.to_string(),
+            Self::Str(s) => write_str(s, out),
+            Self::Span(span) => {
+                *out += &format!(
+                    "{{\"start\":{{\"row\":{},\"col\":{}}},\"end\":{{\"row\":{},\"col\":{}}}}}",
+                    span.start.row, span.start.col, span.end.row, span.end.col
+                );
+            }
+            Self::List(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write_json(out);
+                }
+                out.push(']');
+            }
+            Self::Record(fields) => {
+                out.push('{');
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_str(name, out);
+                    out.push(':');
+                    value.write_json(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    /// Write the value as an S-expression: a node as `(Kind (span ...) (field value) ...)`, any
+    /// other record as `((field value) ...)`, and a list as `(item ...)`, except that a field
+    /// holding a list has its items spliced in, as `(field item ...)`.
+    fn write_sexpr(&self, out: &mut String) {
+        match self {
+            Self::Null => *out += "nil",
+            Self::Bool(b) => *out += if *b { "#t" } else { "#f" },
+            Self::Int(n) => *out += &n.to_string(),
+            Self::Str(s) => write_str(s, out),
+            Self::Span(span) => {
+                *out += &format!(
+                    "(span {} {} {} {})",
+                    span.start.row, span.start.col, span.end.row, span.end.col
+                );
+            }
+            Self::List(items) => {
+                out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    item.write_sexpr(out);
+                }
+                out.push(')');
+            }
+            Self::Record(fields) => {
+                out.push('(');
+                let (mut space, fields) = match &fields[..] {
+                    [("kind", Self::Str(kind)), ("span", span), rest @ ..] => {
+                        *out += kind;
+                        out.push(' ');
+                        span.write_sexpr(out);
+                        (true, rest)
+                    }
+                    fields => (false, fields),
+                };
+                for (name, value) in fields {
+                    if std::mem::replace(&mut space, true) {
+                        out.push(' ');
+                    }
+                    out.push('(');
+                    *out += name;
+                    let items = match value {
+                        Self::List(items) => &items[..],
+                        value => std::slice::from_ref(value),
+                    };
+                    for item in items {
+                        out.push(' ');
+                        item.write_sexpr(out);
+                    }
+                    out.push(')');
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// Write `s` as a double-quoted string, escaped as JSON requires.
+pub(crate) fn write_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => *out += "\\\"",
+            '\\' => *out += "\\\\",
+            '\n' => *out += "\\n",
+            '\r' => *out += "\\r",
+            '\t' => *out += "\\t",
+            c if c.is_control() => *out += &format!("\\u{:04x}", u32::from(c)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn node(kind: &str, span: Span, mut fields: Vec<(&'static str, Value)>) -> Value {
+    fields.splice(
+        0..0,
+        [
+            ("kind", Value::Str(kind.to_string())),
+            ("span", Value::Span(span)),
+        ],
+    );
+    Value::Record(fields)
+}
+
+fn string(s: &str) -> Value {
+    Value::Str(s.to_string())
+}
+
+fn optional(e: Option<&Expn>) -> Value {
+    e.map_or(Value::Null, expn)
+}
+
+fn block(blck: &Blck) -> Value {
+    Value::List(blck.stmts.iter().map(stmt).collect())
+}
+
+fn optional_block(blck: Option<&Blck>) -> Value {
+    blck.map_or(Value::Null, block)
+}
+
+fn names(names: &[String]) -> Value {
+    Value::List(names.iter().map(|n| string(n)).collect())
+}
+
+fn imported(names: &[(String, Option<String>)]) -> Value {
+    Value::List(
+        names
+            .iter()
+            .map(|(name, alias)| {
+                Value::Record(vec![
+                    ("name", string(name)),
+                    ("as", alias.as_deref().map_or(Value::Null, string)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn stmt(stmt: &Stmt) -> Value {
+    let (kind, fields) = match &stmt.data {
+        StmtData::Asgn(t, value) => ("Asgn", vec![("target", target(t)), ("value", expn(value))]),
+        StmtData::AugAsgn(t, op, value) => (
+            "AugAsgn",
+            vec![
+                ("target", target(t)),
+                ("op", string(op.symbol())),
+                ("value", expn(value)),
+            ],
+        ),
+        StmtData::Expn(value) => ("Expn", vec![("value", expn(value))]),
+        StmtData::Asrt { cond, message, .. } => (
+            "Asrt",
+            vec![
+                ("test", expn(cond)),
+                ("message", optional(message.as_ref())),
+            ],
+        ),
+        StmtData::Brek => ("Brek", vec![]),
+        StmtData::Cont => ("Cont", vec![]),
+        StmtData::Pass => ("Pass", vec![]),
+        StmtData::Cond { cond, body, orelse } => (
+            "Cond",
+            vec![
+                ("test", expn(cond)),
+                ("body", block(body)),
+                ("orelse", optional_block(orelse.as_ref())),
+            ],
+        ),
+        StmtData::Whle { cond, body } => {
+            ("Whle", vec![("test", expn(cond)), ("body", block(body))])
+        }
+        StmtData::For { name, iter, body } => (
+            "For",
+            vec![
+                ("name", string(name)),
+                ("iter", expn(iter)),
+                ("body", block(body)),
+            ],
+        ),
+        StmtData::Defn {
+            name,
+            params: ps,
+            body,
+            generator,
+            ..
+        } => (
+            "Defn",
+            vec![
+                ("name", string(name)),
+                ("params", params(ps)),
+                ("body", block(body)),
+                ("generator", Value::Bool(*generator)),
+            ],
+        ),
+        StmtData::Clss { name, base, body } => (
+            "Clss",
+            vec![
+                ("name", string(name)),
+                ("base", optional(base.as_ref())),
+                ("body", block(body)),
+            ],
+        ),
+        StmtData::Prnt(values) => (
+            "Prnt",
+            vec![("values", Value::List(values.iter().map(expn).collect()))],
+        ),
+        StmtData::Rais(value) => ("Rais", vec![("value", optional(value.as_ref()))]),
+        StmtData::Retn(value) => ("Retn", vec![("value", optional(value.as_ref()))]),
+        StmtData::Yild(value) => ("Yild", vec![("value", optional(value.as_ref()))]),
+        StmtData::Glbl(ns) => ("Glbl", vec![("names", names(ns))]),
+        StmtData::Nloc(ns) => ("Nloc", vec![("names", names(ns))]),
+        StmtData::Impt(ns) => ("Impt", vec![("names", imported(ns))]),
+        StmtData::From { module, names } => (
+            "From",
+            vec![("module", string(module)), ("names", imported(names))],
+        ),
+        StmtData::Try {
+            body,
+            handlers,
+            orelse,
+            finally,
+        } => (
+            "Try",
+            vec![
+                ("body", block(body)),
+                (
+                    "handlers",
+                    Value::List(handlers.iter().map(handler).collect()),
+                ),
+                ("orelse", optional_block(orelse.as_ref())),
+                ("finally", optional_block(finally.as_ref())),
+            ],
+        ),
+    };
+    node(kind, stmt.span, fields)
+}
+
+fn handler(handler: &Handler) -> Value {
+    node(
+        "Xcpt",
+        Span {
+            start: handler.span.start,
+            end: handler.body.span().end,
+        },
+        vec![
+            (
+                "class",
+                handler
+                    .class
+                    .as_ref()
+                    .map_or(Value::Null, |(class, _)| string(class)),
+            ),
+            ("name", handler.name.as_deref().map_or(Value::Null, string)),
+            ("body", block(&handler.body)),
+        ],
+    )
+}
+
+fn target(target: &Target) -> Value {
+    match target {
+        Target::Name(name, span) => node("Lkup", *span, vec![("name", string(name))]),
+        Target::Attr(obj, name, span) => node(
+            "Attr",
+            *span,
+            vec![("obj", expn(obj)), ("name", string(name))],
+        ),
+        Target::Index(obj, key, span) => {
+            node("Index", *span, vec![("obj", expn(obj)), ("key", expn(key))])
+        }
+    }
+}
+
+fn params(params: &[Param]) -> Value {
+    Value::List(
+        params
+            .iter()
+            .map(|param| {
+                let (star, name, default) = match param {
+                    Param::Named(name, default) => ("", name, optional(default.as_ref())),
+                    Param::Star(name) => ("*", name, Value::Null),
+                    Param::DStar(name) => ("**", name, Value::Null),
+                };
+                Value::Record(vec![
+                    ("star", string(star)),
+                    ("name", string(name)),
+                    ("default", default),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn arg(arg: &Arg) -> Value {
+    let (star, name, value) = match arg {
+        Arg::Pos(value) => ("", None, value),
+        Arg::Star(value) => ("*", None, value),
+        Arg::Kw(name, value) => ("", Some(name), value),
+        Arg::DStar(value) => ("**", None, value),
+    };
+    Value::Record(vec![
+        ("star", string(star)),
+        ("name", name.map_or(Value::Null, |n| string(n))),
+        ("value", expn(value)),
+    ])
+}
+
+fn clause(clause: &Clause) -> Value {
+    match clause {
+        Clause::For(name, iter) => Value::Record(vec![("for", string(name)), ("in", expn(iter))]),
+        Clause::If(cond) => Value::Record(vec![("if", expn(cond))]),
+    }
+}
+
+fn expn(e: &Expn) -> Value {
+    let (kind, fields) = match e {
+        Expn::Attr { obj, name, .. } => ("Attr", vec![("obj", expn(obj)), ("name", string(name))]),
+        Expn::Index { obj, key, .. } => ("Index", vec![("obj", expn(obj)), ("key", expn(key))]),
+        Expn::BinOp { left, right, op } => (
+            "BinOp",
+            vec![
+                ("op", string(op.symbol())),
+                ("left", expn(left)),
+                ("right", expn(right)),
+            ],
+        ),
+        Expn::Call { func, args, .. } => (
+            "Call",
+            vec![
+                ("func", expn(func)),
+                ("args", Value::List(args.iter().map(arg).collect())),
+            ],
+        ),
+        Expn::Lmbd {
+            params: ps, body, ..
+        } => ("Lmbd", vec![("params", params(ps)), ("body", expn(body))]),
+        Expn::Tern { cond, body, orelse } => (
+            "Tern",
+            vec![
+                ("test", expn(cond)),
+                ("body", expn(body)),
+                ("orelse", expn(orelse)),
+            ],
+        ),
+        Expn::Wlrs { name, value, .. } => {
+            ("Wlrs", vec![("name", string(name)), ("value", expn(value))])
+        }
+        Expn::List { items, .. } => (
+            "List",
+            vec![("items", Value::List(items.iter().map(expn).collect()))],
+        ),
+        Expn::Set { items, .. } => (
+            "Set",
+            vec![("items", Value::List(items.iter().map(expn).collect()))],
+        ),
+        Expn::Dict { entries, .. } => (
+            "Dict",
+            vec![(
+                "entries",
+                Value::List(
+                    entries
+                        .iter()
+                        .map(|(k, v)| Value::Record(vec![("key", expn(k)), ("value", expn(v))]))
+                        .collect(),
+                ),
+            )],
+        ),
+        Expn::Comp {
+            kind, elt, clauses, ..
+        } => {
+            let mut fields = vec![("elt", expn(elt))];
+            let kind = match kind {
+                CompKind::List => "LsCm",
+                CompKind::Set => "StCm",
+                CompKind::Dict(value) => {
+                    fields.push(("value", expn(value)));
+                    "DcCm"
+                }
+                CompKind::Gen(_) => "GnXp",
+            };
+            fields.push(("clauses", Value::List(clauses.iter().map(clause).collect())));
+            (kind, fields)
+        }
+        Expn::Leaf(leaf) => return self::leaf(leaf),
+    };
+    node(kind, e.span(), fields)
+}
+
+fn leaf(leaf: &Leaf) -> Value {
+    let (kind, field) = match &leaf.data {
+        LeafData::Name(name) => ("Lkup", ("name", string(name))),
+        LeafData::Nmbr(n) => ("Nmbr", ("value", Value::Int(*n))),
+        LeafData::Strg(s) => ("Strg", ("value", string(s))),
+        LeafData::Inpt(prompt) => ("Inpt", ("prompt", string(prompt))),
+    };
+    node(kind, leaf.span, vec![field])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    fn parse(source: &str) -> Prgm {
+        Prgm::parse(&mut Tokenizer::lex(source).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            parse("x = \"a\\b\"\nf(*y)").to_json(),
+            "{\"version\":2,\"program\":{\"kind\":\"Prgm\",\
+             \"span\":{\"start\":{\"row\":1,\"col\":1},\"end\":{\"row\":2,\"col\":5}},\
+             \"body\":[{\"kind\":\"Asgn\",\
+             \"span\":{\"start\":{\"row\":1,\"col\":1},\"end\":{\"row\":1,\"col\":9}},\
+             \"target\":{\"kind\":\"Lkup\",\
+             \"span\":{\"start\":{\"row\":1,\"col\":1},\"end\":{\"row\":1,\"col\":1}},\
+             \"name\":\"x\"},\
+             \"value\":{\"kind\":\"Strg\",\
+             \"span\":{\"start\":{\"row\":1,\"col\":5},\"end\":{\"row\":1,\"col\":9}},\
+             \"value\":\"a\\\\b\"}},\
+             {\"kind\":\"Expn\",\
+             \"span\":{\"start\":{\"row\":2,\"col\":1},\"end\":{\"row\":2,\"col\":5}},\
+             \"value\":{\"kind\":\"Call\",\
+             \"span\":{\"start\":{\"row\":2,\"col\":1},\"end\":{\"row\":2,\"col\":5}},\
+             \"func\":{\"kind\":\"Lkup\",\
+             \"span\":{\"start\":{\"row\":2,\"col\":1},\"end\":{\"row\":2,\"col\":1}},\
+             \"name\":\"f\"},\
+             \"args\":[{\"star\":\"*\",\"name\":null,\"value\":{\"kind\":\"Lkup\",\
+             \"span\":{\"start\":{\"row\":2,\"col\":4},\"end\":{\"row\":2,\"col\":4}},\
+             \"name\":\"y\"}}]}}]}}"
+        );
+    }
+
+    #[test]
+    fn sexpr() {
+        assert_eq!(
+            parse("def f(a=1):\n    return [b for b in a if b]\nf()").to_sexpr(),
+            "(slpy-ast 2 (Prgm (span 1 1 3 3) (body \
+             (Defn (span 1 1 2 30) (name \"f\") \
+             (params ((star \"\") (name \"a\") (default (Nmbr (span 1 9 1 9) (value 1))))) \
+             (body (Retn (span 2 5 2 30) (value (LsCm (span 2 12 2 30) \
+             (elt (Lkup (span 2 13 2 13) (name \"b\"))) \
+             (clauses ((for \"b\") (in (Lkup (span 2 24 2 24) (name \"a\")))) \
+             ((if (Lkup (span 2 29 2 29) (name \"b\"))))))))) \
+             (generator #f)) \
+             (Expn (span 3 1 3 3) (value (Call (span 3 1 3 3) \
+             (func (Lkup (span 3 1 3 1) (name \"f\"))) (args)))))))"
+        );
+    }
+
+    #[test]
+    fn subscripts() {
+        let sexpr = parse("xs[i] += a[0]").to_sexpr();
+        assert!(sexpr.contains(
+            "(AugAsgn (span 1 1 1 13) (target (Index (span 1 1 1 5) \
+             (obj (Lkup (span 1 1 1 2) (name \"xs\"))) (key (Lkup (span 1 4 1 4) (name \"i\"))))) \
+             (op \"+\") (value (Index (span 1 10 1 13) \
+             (obj (Lkup (span 1 10 1 10) (name \"a\"))) (key (Nmbr (span 1 12 1 12) (value 0)))))"
+        ));
+    }
+
+    #[test]
+    fn handlers_span_their_bodies() {
+        let json = parse("try:\n    pass\nexcept E as e:\n    pass").to_json();
+        assert!(json.contains(
+            "{\"kind\":\"Xcpt\",\
+             \"span\":{\"start\":{\"row\":3,\"col\":1},\"end\":{\"row\":4,\"col\":8}},\
+             \"class\":\"E\",\"name\":\"e\",\"body\":[{\"kind\":\"Pass\""
+        ));
+    }
+}
@@ -0,0 +1,518 @@
+//! Turning a parsed program back into canonical source.
+use super::{
+    Arg, BinOp, Blck, Clause, CompKind, Expn, Leaf, LeafData, Param, Prgm, Stmt, StmtData,
+};
+use super::{Ast, Handler, Target};
+use crate::tokenizer::{Comment, Token, TokenKind, TokenStream};
+use crate::Loc;
+
+/// The binding power that only an atom satisfies, so anything else gets parenthesized.
+const ATOM: u8 = u8::MAX;
+
+impl Prgm {
+    /// Print the program, parsed from `tokens`, as canonical source, keeping the comments next
+    /// to the statements they were next to.
+    ///
+    /// Statements get four spaces of indentation per block and operators one space on each side,
+    /// and expressions only get the parentheses they need. Runs of blank lines become one.
+    #[must_use]
+    pub fn unparse(&self, tokens: &TokenStream) -> String {
+        let mut printer = Printer {
+            out: String::new(),
+            comments: tokens.comments(),
+            tokens: tokens.tokens(),
+            last_row: None,
+            opened: false,
+        };
+        if !self.main.stmts.is_empty() {
+            printer.block(&self.main, 0, Some(usize::MAX));
+        }
+        while let Some((comment, rest)) = printer.comments.split_first() {
+            printer.comments = rest;
+            let row = comment.span.start.row;
+            printer.line(0, Some((row, row)), &comment.text);
+        }
+        printer.out
+    }
+}
+
+struct Printer<'a> {
+    out: String,
+    /// The comments not yet printed.
+    comments: &'a [Comment],
+    /// The program's tokens, to find the rows of what the tree doesn't keep, like `else`.
+    tokens: &'a [Token],
+    /// The source row of the last line printed, to keep blank lines between statements.
+    last_row: Option<usize>,
+    /// Whether the last line printed opened a block.
+    opened: bool,
+}
+
+impl Printer<'_> {
+    /// Print a line of code from source rows `rows`, after the comments above it and with the
+    /// comments on its rows at the end.
+    fn stmt_line(&mut self, indent: usize, rows: Option<(usize, usize)>, text: &str) {
+        if let Some((row, _)) = rows {
+            self.comments_before(row, indent, 0);
+        }
+        self.line(indent, rows, text);
+    }
+
+    /// Print the comments before `row` that are indented at least to `col`, as lines of their
+    /// own.
+    fn comments_before(&mut self, row: usize, indent: usize, col: usize) {
+        while let Some((comment, rest)) = self.comments.split_first() {
+            if comment.span.start.row >= row || comment.span.start.col < col {
+                break;
+            }
+            self.comments = rest;
+            let row = comment.span.start.row;
+            self.line(indent, Some((row, row)), &comment.text);
+        }
+    }
+
+    /// Print a line from the source rows `rows`, first to last, ending it with the comments on
+    /// them.
+    fn line(&mut self, indent: usize, rows: Option<(usize, usize)>, text: &str) {
+        if let (Some((row, _)), Some(last)) = (rows, self.last_row) {
+            if row > last + 1 && !self.opened {
+                self.out.push('\n');
+            }
+        }
+        self.out += &"    ".repeat(indent);
+        self.out += text;
+        if let Some((_, end)) = rows {
+            while let Some((comment, rest)) = self.comments.split_first() {
+                if comment.span.start.row > end {
+                    break;
+                }
+                self.comments = rest;
+                self.out += "  ";
+                self.out += &comment.text;
+            }
+        }
+        self.out.push('\n');
+        self.last_row = rows.map(|(_, end)| end).or(self.last_row);
+        self.opened = text.ends_with(':') && !text.starts_with('#');
+    }
+
+    /// The row of the first `kw` after `after`, which starts a clause like `else:`.
+    fn keyword_row(&self, kw: &str, after: Loc) -> Option<usize> {
+        let i = self
+            .tokens
+            .partition_point(|t| (t.span.start.row, t.span.start.col) <= (after.row, after.col));
+        self.tokens[i..]
+            .iter()
+            .find(|t| matches!(&t.kind, TokenKind::Ident(name) if name == kw))
+            .map(|t| t.span.start.row)
+    }
+
+    /// The rows of the header of the compound statement starting at `start`, up to the colon
+    /// that ends it.
+    fn header_rows(&self, start: Loc) -> Option<(usize, usize)> {
+        let i = self
+            .tokens
+            .partition_point(|t| (t.span.start.row, t.span.start.col) < (start.row, start.col));
+        // brackets and `lambda`s have colons of their own
+        let (mut depth, mut lambdas) = (0_usize, 0_usize);
+        let end = self.tokens[i..].iter().find_map(|t| {
+            match &t.kind {
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+                    depth = depth.saturating_sub(1);
+                }
+                TokenKind::Ident(name) if depth == 0 && name == "lambda" => lambdas += 1,
+                TokenKind::Colon if depth == 0 && lambdas > 0 => lambdas -= 1,
+                TokenKind::Colon if depth == 0 => return Some(t.span.start.row),
+                _ => {}
+            }
+            None
+        })?;
+        Some((start.row, end))
+    }
+
+    /// Print the statements in `blck`, then the comments indented like them before `until`, the
+    /// row of whatever comes after the block if it's known.
+    fn block(&mut self, blck: &Blck, indent: usize, until: Option<usize>) {
+        if blck.stmts.is_empty() {
+            // only possible if the block's asserts were stripped
+            self.line(indent, None, "pass");
+            return;
+        }
+        for (i, stmt) in blck.stmts.iter().enumerate() {
+            let next = blck.stmts.get(i + 1).map(|s| s.span.start.row).or(until);
+            self.stmt(stmt, indent, next);
+        }
+        if let (Some(until), Some(first)) = (until, blck.stmts.front()) {
+            self.comments_before(until, indent, first.span.start.col);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, indent: usize, until: Option<usize>) {
+        let body = match &stmt.data {
+            StmtData::Cond { .. } => return self.cond("if", stmt, indent, until),
+            StmtData::Try { .. } => return self.try_stmt(stmt, indent, until),
+            StmtData::Whle { body, .. }
+            | StmtData::For { body, .. }
+            | StmtData::Clss { body, .. } => body,
+            StmtData::Defn { body, .. } => body.as_ref(),
+            _ => {
+                let rows = Some((stmt.span.start.row, stmt.span.end.row));
+                return self.stmt_line(indent, rows, &header(stmt));
+            }
+        };
+        let rows = self.header_rows(stmt.span.start);
+        self.stmt_line(indent, rows, &(header(stmt) + ":"));
+        self.block(body, indent + 1, until);
+    }
+
+    /// Print an `if` statement, or the `elif` it is if `kw` says so.
+    fn cond(&mut self, kw: &str, stmt: &Stmt, indent: usize, until: Option<usize>) {
+        let StmtData::Cond { cond, body, orelse } = &stmt.data else {
+            unreachable!("only called on `if` statements")
+        };
+        let rows = self.header_rows(stmt.span.start);
+        self.stmt_line(indent, rows, &format!("{} {}:", kw, named(cond)));
+        match orelse.as_ref().map(|b| (b, b.stmts.front())) {
+            Some((orelse, Some(elif)))
+                if orelse.stmts.len() == 1 && matches!(elif.data, StmtData::Cond { .. }) =>
+            {
+                self.block(body, indent + 1, Some(elif.span.start.row));
+                self.cond("elif", elif, indent, until);
+            }
+            Some((orelse, _)) => {
+                let row = self.keyword_row("else", body.span().end);
+                self.block(body, indent + 1, row);
+                self.stmt_line(indent, row.map(|row| (row, row)), "else:");
+                self.block(orelse, indent + 1, until);
+            }
+            None => self.block(body, indent + 1, until),
+        }
+    }
+
+    fn try_stmt(&mut self, stmt: &Stmt, indent: usize, until: Option<usize>) {
+        let StmtData::Try {
+            body,
+            handlers,
+            orelse,
+            finally,
+        } = &stmt.data
+        else {
+            unreachable!("only called on `try` statements")
+        };
+        let row = stmt.span.start.row;
+        self.stmt_line(indent, Some((row, row)), "try:");
+        let else_row = orelse
+            .as_ref()
+            .and_then(|_| self.keyword_row("else", handlers.last()?.body.span().end));
+        let finally_row = finally.as_ref().and_then(|_| {
+            let before = orelse.as_ref().or(handlers.last().map(|h| &h.body));
+            self.keyword_row("finally", before.unwrap_or(body).span().end)
+        });
+        let after = |i: usize| match handlers.get(i) {
+            Some(handler) => Some(handler.span.start.row),
+            None => else_row.or(finally_row).or(until),
+        };
+        self.block(body, indent + 1, after(0));
+        for (i, handler) in handlers.iter().enumerate() {
+            let row = handler.span.start.row;
+            self.stmt_line(indent, Some((row, row)), &(handler_header(handler) + ":"));
+            self.block(&handler.body, indent + 1, after(i + 1));
+        }
+        if let Some(orelse) = orelse {
+            self.stmt_line(indent, else_row.map(|row| (row, row)), "else:");
+            self.block(orelse, indent + 1, finally_row.or(until));
+        }
+        if let Some(finally) = finally {
+            self.stmt_line(indent, finally_row.map(|row| (row, row)), "finally:");
+            self.block(finally, indent + 1, until);
+        }
+    }
+}
+
+/// A simple statement, or the first line of a compound one without its colon.
+pub(super) fn header(stmt: &Stmt) -> String {
+    match &stmt.data {
+        StmtData::Asgn(target, value) => {
+            format!("{} = {}", unparse_target(target), expn(value, 0))
+        }
+        StmtData::AugAsgn(target, op, value) => format!(
+            "{} {}= {}",
+            unparse_target(target),
+            op.symbol(),
+            expn(value, 0)
+        ),
+        StmtData::Expn(e) => expn(e, 0),
+        StmtData::Asrt { cond, message, .. } => match message {
+            Some(message) => format!("assert {}, {}", expn(cond, 0), expn(message, 0)),
+            None => format!("assert {}", expn(cond, 0)),
+        },
+        StmtData::Brek => "break".to_string(),
+        StmtData::Cont => "continue".to_string(),
+        StmtData::Pass => "pass".to_string(),
+        StmtData::Prnt(expns) => format!("print({})", list(expns)),
+        StmtData::Rais(e) => keyword("raise", e.as_ref()),
+        StmtData::Retn(e) => keyword("return", e.as_ref()),
+        StmtData::Yild(e) => keyword("yield", e.as_ref()),
+        StmtData::Glbl(names) => format!("global {}", names.join(", ")),
+        StmtData::Nloc(names) => format!("nonlocal {}", names.join(", ")),
+        StmtData::Impt(names) => format!("import {}", imported(names)),
+        StmtData::From { module, names } => {
+            format!("from {} import {}", module, imported(names))
+        }
+        StmtData::Cond { cond, .. } => format!("if {}", named(cond)),
+        StmtData::Whle { cond, .. } => format!("while {}", named(cond)),
+        StmtData::For { name, iter, .. } => format!("for {} in {}", name, expn(iter, 0)),
+        StmtData::Defn { name, params, .. } => {
+            format!("def {}({})", name, unparse_params(params))
+        }
+        StmtData::Clss { name, base, .. } => match base {
+            Some(base) => format!("class {}({})", name, expn(base, 0)),
+            None => format!("class {}", name),
+        },
+        StmtData::Try { .. } => "try".to_string(),
+    }
+}
+
+/// The first line of an `except` clause, without its colon.
+pub(super) fn handler_header(handler: &Handler) -> String {
+    match (&handler.class, &handler.name) {
+        (Some((class, _)), Some(name)) => format!("except {} as {}", class, name),
+        (Some((class, _)), None) => format!("except {}", class),
+        (None, _) => "except".to_string(),
+    }
+}
+
+/// A statement that's a keyword with an optional expression, like `return`.
+fn keyword(kw: &str, e: Option<&Expn>) -> String {
+    e.map_or_else(|| kw.to_string(), |e| format!("{} {}", kw, expn(e, 0)))
+}
+
+fn imported(names: &[(String, Option<String>)]) -> String {
+    names
+        .iter()
+        .map(|(name, alias)| match alias {
+            Some(alias) => format!("{} as {}", name, alias),
+            None => name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn unparse_target(target: &Target) -> String {
+    match target {
+        Target::Name(name, _) => name.clone(),
+        Target::Attr(obj, name, _) => format!("{}.{}", expn(obj, ATOM), name),
+        Target::Index(obj, key, _) => format!("{}[{}]", expn(obj, ATOM), named(key)),
+    }
+}
+
+fn unparse_params(params: &[Param]) -> String {
+    params
+        .iter()
+        .map(|param| match param {
+            Param::Named(name, None) => name.clone(),
+            Param::Named(name, Some(default)) => format!("{}={}", name, expn(default, 0)),
+            Param::Star(name) => format!("*{}", name),
+            Param::DStar(name) => format!("**{}", name),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn list(expns: &[Expn]) -> String {
+    expns.iter().map(named).collect::<Vec<_>>().join(", ")
+}
+
+fn clauses(clauses: &[Clause]) -> String {
+    clauses
+        .iter()
+        .map(|clause| match clause {
+            Clause::For(name, iter) => format!(" for {} in {}", name, expn(iter, 1)),
+            Clause::If(cond) => format!(" if {}", expn(cond, 1)),
+        })
+        .collect()
+}
+
+/// Print `e` where Python allows an unparenthesized `:=`, like an `if` condition or an argument.
+fn named(e: &Expn) -> String {
+    match e {
+        Expn::Wlrs { name, value, .. } => format!("{} := {}", name, expn(value, 0)),
+        e => expn(e, 0),
+    }
+}
+
+/// Print `e` where the parser reads an expression that binds at least as tightly as `min`: 0 for
+/// anything but an unparenthesized `:=`, 1 for anything but conditional expressions and `lambda`
+/// too, or a binding power from `BinOp::bp`, up to `ATOM`.
+fn expn(e: &Expn, min: u8) -> String {
+    let (text, bp) = match e {
+        Expn::Attr { obj, name, .. } => return format!("{}.{}", expn(obj, ATOM), name),
+        Expn::Index { obj, key, .. } => return format!("{}[{}]", expn(obj, ATOM), named(key)),
+        Expn::Call { func, args, .. } => {
+            let args = match &args[..] {
+                // a lone generator expression needs no parentheses of its own
+                [Arg::Pos(Expn::Comp {
+                    kind: CompKind::Gen(_),
+                    elt,
+                    clauses: cs,
+                    ..
+                })] => named(elt) + &clauses(cs),
+                _ => args.iter().map(unparse_arg).collect::<Vec<_>>().join(", "),
+            };
+            return format!("{}({})", expn(func, ATOM), args);
+        }
+        Expn::BinOp { left, right, op } => {
+            let (l_bp, r_bp) = op.bp();
+            // the left operand is only read as one if it stops binding before `op`
+            let left_min = match &**left {
+                Expn::BinOp { op: inner, .. } if inner.bp().1 > l_bp => 1,
+                _ => ATOM,
+            };
+            let text = format!(
+                "{} {} {}",
+                expn(left, left_min),
+                BinOp::symbol(*op),
+                expn(right, r_bp)
+            );
+            (text, l_bp)
+        }
+        Expn::Lmbd { params, body, .. } => {
+            let text = if params.is_empty() {
+                format!("lambda: {}", expn(body, 0))
+            } else {
+                format!("lambda {}: {}", unparse_params(params), expn(body, 0))
+            };
+            (text, 0)
+        }
+        Expn::Tern { cond, body, orelse } => {
+            let text = format!(
+                "{} if {} else {}",
+                expn(body, 1),
+                expn(cond, 1),
+                expn(orelse, 0)
+            );
+            (text, 0)
+        }
+        Expn::Wlrs { .. } => return format!("({})", named(e)),
+        Expn::List { items, .. } => return format!("[{}]", list(items)),
+        Expn::Set { items, .. } => return format!("{{{}}}", list(items)),
+        Expn::Dict { entries, .. } => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", expn(k, 0), expn(v, 0)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return format!("{{{}}}", entries);
+        }
+        Expn::Comp {
+            kind,
+            elt,
+            clauses: cs,
+            ..
+        } => {
+            return match kind {
+                CompKind::List => format!("[{}{}]", named(elt), clauses(cs)),
+                CompKind::Set => format!("{{{}{}}}", named(elt), clauses(cs)),
+                CompKind::Dict(value) => {
+                    format!("{{{}: {}{}}}", expn(elt, 0), expn(value, 0), clauses(cs))
+                }
+                CompKind::Gen(_) => format!("({}{})", named(elt), clauses(cs)),
+            };
+        }
+        Expn::Leaf(leaf) => return unparse_leaf(leaf),
+    };
+    if bp < min {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn unparse_arg(arg: &Arg) -> String {
+    match arg {
+        Arg::Pos(e) => named(e),
+        Arg::Star(e) => format!("*{}", expn(e, 0)),
+        Arg::Kw(name, e) => format!("{}={}", name, expn(e, 0)),
+        Arg::DStar(e) => format!("**{}", expn(e, 0)),
+    }
+}
+
+fn unparse_leaf(leaf: &Leaf) -> String {
+    match &leaf.data {
+        LeafData::Name(name) => name.clone(),
+        LeafData::Nmbr(n) => n.to_string(),
+        LeafData::Strg(s) => string(s),
+        LeafData::Inpt(prompt) => format!("input({})", string(prompt)),
+    }
+}
+
+/// `s` as a string literal. Only a `"""` string can have held a `"`.
+fn string(s: &str) -> String {
+    if s.contains('"') {
+        format!("\"\"\"{}\"\"\"", s)
+    } else {
+        format!("\"{}\"", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    fn unparse(source: &str) -> String {
+        let mut tokens = Tokenizer::lex(source).unwrap();
+        Prgm::parse(&mut tokens).unwrap().unparse(&tokens)
+    }
+
+    /// An unparsing test case, which also checks that the output parses to the same program and
+    /// is printed unchanged.
+    macro_rules! unparse_test {
+        ($name:ident: $in:expr => $out:expr) => {
+            #[test]
+            fn $name() {
+                let out = unparse($in);
+                assert_eq!(out, $out);
+                let dump = |s: &str| {
+                    Prgm::parse(&mut Tokenizer::lex(s).unwrap())
+                        .unwrap()
+                        .dump(0)
+                };
+                assert_eq!(dump(&out), dump($in));
+                assert_eq!(unparse(&out), out);
+            }
+        };
+    }
+
+    unparse_test!(spacing: "x=1+2*3\nf( a,b )" => "x = 1 + 2 * 3\nf(a, b)\n");
+    unparse_test!(redundant_parens: "x = (1 * 2) + (3)\ny = (a + b) - c" =>
+        "x = 1 * 2 + 3\ny = a + b - c\n");
+    unparse_test!(needed_parens: "x = (a + b) * c\ny = a - (b - c)\nz = a < (b < c)" =>
+        "x = (a + b) * c\ny = a - (b - c)\nz = a < (b < c)\n");
+    unparse_test!(power_is_right_associative: "x = (a ** b) ** c\ny = a ** (b ** c)" =>
+        "x = (a ** b) ** c\ny = a ** b ** c\n");
+    unparse_test!(atoms: "x = (a + b).c\ny = (lambda: 1)()\nz = (a if b else c).d" =>
+        "x = (a + b).c\ny = (lambda: 1)()\nz = (a if b else c).d\n");
+    unparse_test!(subscripts: "x[ i ] += (a + b)[0][k]" => "x[i] += (a + b)[0][k]\n");
+    unparse_test!(strings: "x = \"\"\"a \"b\"\nc\"\"\"  # x\ny = \"\"\"d\"\"\"" => "x = \"\"\"a \"b\"\nc\"\"\"  # x\ny = \"d\"\n");
+    unparse_test!(loose_expressions: "x = (a if b else c) + 1\ny = (a if (b if c else d) else e)\nz = [(n := 1) + 1]" =>
+        "x = (a if b else c) + 1\ny = a if (b if c else d) else e\nz = [(n := 1) + 1]\n");
+    unparse_test!(walrus: "x = (y := 3)\nf((a := 1), x=(b := 2))\ng = lambda: (c := 3)\nif (d := 4):\n    print((e := 5), [(h := 6), (h := 7) + 1][(i := 0)])\nwhile n := next(it, 0): pass" =>
+        "x = (y := 3)\nf(a := 1, x=(b := 2))\ng = lambda: (c := 3)\nif d := 4:\n    print(e := 5, [h := 6, (h := 7) + 1][i := 0])\nwhile n := next(it, 0):\n    pass\n");
+    unparse_test!(comprehensions: "a = [x for x in (y if z else w) if x]\nb = {k: v for k in d}\nc = sum(x for x in y)\nd = f((x for x in y), 1)" =>
+        "a = [x for x in (y if z else w) if x]\nb = {k: v for k in d}\nc = sum(x for x in y)\nd = f((x for x in y), 1)\n");
+    unparse_test!(params_and_args: "def f(a, b = 1, *args, c, **kw):\n  return g(*args, k = 1, **kw)" =>
+        "def f(a, b=1, *args, c, **kw):\n    return g(*args, k=1, **kw)\n");
+    unparse_test!(suites: "if a: x = 1\nelif b:\n  pass\nelse:\n    while c: break" =>
+        "if a:\n    x = 1\nelif b:\n    pass\nelse:\n    while c:\n        break\n");
+    unparse_test!(try_stmt: "try:\n  raise\nexcept E as e: pass\nexcept:\n  pass\nelse:\n  pass\nfinally:\n  pass" =>
+        "try:\n    raise\nexcept E as e:\n    pass\nexcept:\n    pass\nelse:\n    pass\nfinally:\n    pass\n");
+    unparse_test!(comments: "# top\nx = 1 # x\n\n\n\ndef f():\n        # body\n        return 1\n        # end of f\n# after f\ny = 2" =>
+        "# top\nx = 1  # x\n\ndef f():\n    # body\n    return 1\n    # end of f\n# after f\ny = 2\n");
+    unparse_test!(only_comments: "# a\n\n# b\n" => "# a\n\n# b\n");
+    unparse_test!(comments_around_clauses: "if x:\n    y = 1\n    # end of if body\n# before else\nelse:  # otherwise\n    # else body\n    y = 2\ntry:\n    pass\n    # end of try\nexcept E:\n    pass\nelse:\n    pass\n# before finally\nfinally:  # always\n    pass" =>
+        "if x:\n    y = 1\n    # end of if body\n# before else\nelse:  # otherwise\n    # else body\n    y = 2\ntry:\n    pass\n    # end of try\nexcept E:\n    pass\nelse:\n    pass\n# before finally\nfinally:  # always\n    pass\n");
+    unparse_test!(comments_in_multiline_statements: "x = [1,  # one\n     2]  # two\nif (a +  # a\n        b):  # b\n    # body\n    pass\nf(lambda: 1,\n  2)  # f" =>
+        "x = [1, 2]  # one  # two\nif a + b:  # a  # b\n    # body\n    pass\nf(lambda: 1, 2)  # f\n");
+}
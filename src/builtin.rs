@@ -0,0 +1,44 @@
+//! Functions built into the interpreter, which unlike natives can see the running program.
+
+/// A built-in function.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Builtin {
+    /// `super()`, or `super(class, obj)`.
+    Super,
+    /// `iter(obj)`, an iterator over a container's items.
+    Iter,
+    /// `next(it)`, or `next(it, default)`, the next value from an iterator.
+    Next,
+    /// `exit()`, or `exit(status)`, which stops the program.
+    Exit,
+    /// `getenv(name)`, or `getenv(name, default)`, the value of an environment variable.
+    Getenv,
+}
+
+impl Builtin {
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Super,
+        Self::Iter,
+        Self::Next,
+        Self::Exit,
+        Self::Getenv,
+    ];
+
+    /// The function's name.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Super => "super",
+            Self::Iter => "iter",
+            Self::Next => "next",
+            Self::Exit => "exit",
+            Self::Getenv => "getenv",
+        }
+    }
+
+    /// Look a function up by name.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+}
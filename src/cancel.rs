@@ -0,0 +1,34 @@
+//! Stopping a running program from another thread.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle that asks a running program to stop.
+///
+/// The evaluator checks it before every step, and a cancelled program fails with
+/// `Kind::Cancelled`. A request holds until the evaluation it stops returns, so `finally` blocks
+/// are stopped too rather than running on; then it's cleared, so the same context can run more
+/// code afterwards. A request made while nothing is running stops the next evaluation.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// Ask the program to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Withdraw a request to stop that hasn't been acted on yet.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether there's a request to stop.
+    pub(crate) fn is_requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// The underlying flag, for APIs (like signal handlers) that set it directly.
+    pub(crate) fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.0)
+    }
+}
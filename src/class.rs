@@ -0,0 +1,125 @@
+//! Classes defined in slpy and their instances.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::Function;
+use crate::object::SlpyObject;
+
+/// A class defined with `class`, compared by identity.
+pub struct Class {
+    pub(crate) name: String,
+    pub(crate) base: Option<Rc<Class>>,
+    /// The methods and class attributes, from its body or assigned later.
+    attrs: RefCell<HashMap<String, SlpyObject>>,
+}
+
+impl Class {
+    pub(crate) fn new(name: String, base: Option<Rc<Self>>) -> Self {
+        Self {
+            name,
+            base,
+            attrs: RefCell::default(),
+        }
+    }
+
+    /// Look an attribute up in this class or the classes it inherits from.
+    pub(crate) fn lookup(&self, name: &str) -> Option<SlpyObject> {
+        self.attrs
+            .borrow()
+            .get(name)
+            .cloned()
+            .or_else(|| self.base.as_ref()?.lookup(name))
+    }
+
+    pub(crate) fn set(&self, name: String, val: SlpyObject) {
+        self.attrs.borrow_mut().insert(name, val);
+    }
+}
+
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Class {}
+
+impl std::fmt::Debug for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class '{}'>", self.name)
+    }
+}
+
+/// An instance of a `Class`, compared by identity unless its class defines `__eq__`.
+pub struct Instance {
+    pub(crate) class: Rc<Class>,
+    attrs: RefCell<HashMap<String, SlpyObject>>,
+}
+
+impl Instance {
+    pub(crate) fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            attrs: RefCell::default(),
+        }
+    }
+
+    /// Look up an attribute set on the instance itself.
+    pub(crate) fn get(&self, name: &str) -> Option<SlpyObject> {
+        self.attrs.borrow().get(name).cloned()
+    }
+
+    pub(crate) fn set(&self, name: String, val: SlpyObject) {
+        self.attrs.borrow_mut().insert(name, val);
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Instance {}
+
+impl std::fmt::Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} object>", self.class.name)
+    }
+}
+
+/// A function looked up on an object, which is passed as its first argument.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Method {
+    pub(crate) receiver: SlpyObject,
+    pub(crate) func: Rc<Function>,
+}
+
+impl Method {
+    /// Bind `val` to `receiver` if it's a function; other attributes are left alone.
+    pub(crate) fn bind(receiver: &SlpyObject, val: SlpyObject) -> SlpyObject {
+        match val {
+            SlpyObject::Function(func) => SlpyObject::Method(Rc::new(Self {
+                receiver: receiver.clone(),
+                func,
+            })),
+            val => val,
+        }
+    }
+}
+
+/// What `super()` returns: `receiver`, but with methods looked up from the classes `class`
+/// inherits from.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Super {
+    pub(crate) class: Rc<Class>,
+    pub(crate) receiver: SlpyObject,
+}
+
+impl Super {
+    pub(crate) fn get(&self, name: &str) -> Option<SlpyObject> {
+        let val = self.class.base.as_ref()?.lookup(name)?;
+        Some(Method::bind(&self.receiver, val))
+    }
+}
@@ -0,0 +1,187 @@
+//! Variable scopes.
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::object::SlpyObject;
+
+/// A chain of scopes, innermost first. The outermost scope holds the globals.
+///
+/// Functions keep the scope they were defined in, so closures see later changes to the variables
+/// they capture.
+#[derive(Clone, Default)]
+pub(crate) struct Env(Rc<RefCell<Scope>>);
+
+#[derive(Default)]
+struct Scope {
+    vars: HashMap<String, SlpyObject>,
+    /// The names a function's body binds, which are local to its calls even before they're
+    /// assigned.
+    locals: Rc<HashSet<String>>,
+    /// Names declared `global` in this scope.
+    globals: HashSet<String>,
+    /// Names declared `nonlocal` in this scope.
+    nonlocals: HashSet<String>,
+    parent: Option<Env>,
+}
+
+impl Env {
+    /// A new scope inside this one, e.g. for a function call.
+    pub fn child(&self) -> Self {
+        Self(Rc::new(RefCell::new(Scope {
+            parent: Some(self.clone()),
+            ..Scope::default()
+        })))
+    }
+
+    /// A new scope for a call to a function whose body binds `locals`.
+    pub fn call(&self, locals: Rc<HashSet<String>>) -> Self {
+        Self(Rc::new(RefCell::new(Scope {
+            locals,
+            parent: Some(self.clone()),
+            ..Scope::default()
+        })))
+    }
+
+    /// The global scope at the end of the chain.
+    fn root(&self) -> Self {
+        match &self.0.borrow().parent {
+            Some(parent) => parent.root(),
+            None => self.clone(),
+        }
+    }
+
+    /// The innermost function scope, starting here, that binds `name`.
+    fn enclosing(&self, name: &str) -> Option<Self> {
+        let scope = self.0.borrow();
+        let parent = scope.parent.as_ref()?;
+        if scope.vars.contains_key(name) || scope.locals.contains(name) {
+            Some(self.clone())
+        } else {
+            parent.enclosing(name)
+        }
+    }
+
+    /// Look up a variable. A function's local that hasn't been assigned yet hides any variable
+    /// of the same name outside it.
+    pub fn get(&self, name: &str) -> Option<SlpyObject> {
+        let scope = self.0.borrow();
+        if scope.globals.contains(name) {
+            return self.root().0.borrow().vars.get(name).cloned();
+        }
+        match scope.vars.get(name) {
+            Some(val) => Some(val.clone()),
+            None if scope.locals.contains(name) => None,
+            None => scope.parent.as_ref()?.get(name),
+        }
+    }
+
+    /// Whether `name` is local to the function this scope is a call to.
+    pub fn is_local(&self, name: &str) -> bool {
+        self.0.borrow().locals.contains(name)
+    }
+
+    /// Assign a variable, in whichever scope it's declared to belong to.
+    pub fn set(&self, name: String, val: SlpyObject) {
+        let target = {
+            let scope = self.0.borrow();
+            if scope.globals.contains(&name) {
+                Some(self.root())
+            } else if scope.nonlocals.contains(&name) {
+                scope.parent.as_ref().and_then(|p| p.enclosing(&name))
+            } else {
+                None
+            }
+        };
+        target
+            .unwrap_or_else(|| self.clone())
+            .0
+            .borrow_mut()
+            .vars
+            .insert(name, val);
+    }
+
+    /// The variables bound in this scope itself.
+    pub fn vars(&self) -> Vec<(String, SlpyObject)> {
+        let scope = self.0.borrow();
+        scope
+            .vars
+            .iter()
+            .map(|(name, val)| (name.clone(), val.clone()))
+            .collect()
+    }
+
+    /// Whether `other` is this same scope.
+    pub fn is(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Remove a variable from this scope.
+    pub fn remove(&self, name: &str) {
+        self.0.borrow_mut().vars.remove(name);
+    }
+
+    /// Make `name` refer to the global variable in this scope.
+    pub fn declare_global(&self, name: String) {
+        self.0.borrow_mut().globals.insert(name);
+    }
+
+    /// Make `name` refer to the variable in the nearest enclosing function that binds it, which
+    /// the parser has checked there is.
+    pub fn declare_nonlocal(&self, name: String) {
+        self.0.borrow_mut().nonlocals.insert(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inner_scopes_shadow() {
+        let globals = Env::default();
+        globals.set("x".to_string(), SlpyObject::Int(1));
+        let inner = globals.child();
+        assert_eq!(inner.get("x"), Some(SlpyObject::Int(1)));
+        inner.set("x".to_string(), SlpyObject::Int(2));
+        assert_eq!(inner.get("x"), Some(SlpyObject::Int(2)));
+        assert_eq!(globals.get("x"), Some(SlpyObject::Int(1)));
+    }
+
+    #[test]
+    fn declarations() {
+        let globals = Env::default();
+        globals.set("x".to_string(), SlpyObject::Int(1));
+        let outer = globals.child();
+        outer.set("x".to_string(), SlpyObject::Int(2));
+        let inner = outer.child();
+
+        inner.declare_global("x".to_string());
+        assert_eq!(inner.get("x"), Some(SlpyObject::Int(1)));
+        inner.set("x".to_string(), SlpyObject::Int(3));
+        assert_eq!(globals.get("x"), Some(SlpyObject::Int(3)));
+
+        let inner = outer.child();
+        inner.declare_nonlocal("x".to_string());
+        inner.set("x".to_string(), SlpyObject::Int(4));
+        assert_eq!(outer.get("x"), Some(SlpyObject::Int(4)));
+    }
+
+    #[test]
+    fn unassigned_locals_hide_outer_variables() {
+        let globals = Env::default();
+        globals.set("x".to_string(), SlpyObject::Int(1));
+        let locals = Rc::new(HashSet::from(["x".to_string(), "y".to_string()]));
+        let call = globals.call(Rc::clone(&locals));
+        assert_eq!(call.get("x"), None);
+        assert!(call.is_local("x"));
+        call.set("x".to_string(), SlpyObject::Int(2));
+        assert_eq!(call.get("x"), Some(SlpyObject::Int(2)));
+
+        // a closure finds the binding even before it's assigned
+        let inner = call.child();
+        inner.declare_nonlocal("y".to_string());
+        inner.set("y".to_string(), SlpyObject::Int(3));
+        assert_eq!(call.get("y"), Some(SlpyObject::Int(3)));
+    }
+}
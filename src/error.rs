@@ -1,4 +1,7 @@
 // TODO: error
+use std::sync::Arc;
+
+use crate::exception::{ExcType, Exception};
 use crate::tokenizer::TokenKind;
 use crate::Span;
 
@@ -6,14 +9,211 @@ use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, PartialEq, Eq, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("{kind} from {} to {}", .span.start, .span.end)]
 pub struct Error {
     pub kind: Kind,
     pub span: Span,
+    /// The file `span` is in, if the code came from one.
+    pub file: Option<Arc<String>>,
+    /// The calls the error unwound through, outermost first.
+    pub trace: Vec<Frame>,
+}
+
+impl Error {
+    #[must_use]
+    pub const fn new(kind: Kind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            file: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Record that the error unwound out of a call to `name` at `span`.
+    #[must_use]
+    pub fn in_call(mut self, name: &str, span: Span) -> Self {
+        self.trace.insert(
+            0,
+            Frame {
+                name: name.to_string(),
+                span,
+                file: None,
+            },
+        );
+        self
+    }
+
+    /// Record that the error was raised in code read from `file`, unless that's already known.
+    #[must_use]
+    pub(crate) fn in_file(mut self, file: Option<&Arc<String>>) -> Self {
+        // until the error unwinds out of a call, it's still in the code it was raised in
+        if self.trace.is_empty() && self.file.is_none() {
+            self.file = file.cloned();
+        }
+        self
+    }
+
+    /// Record that the error unwound out of code read from `callee`, called from code read from
+    /// `caller` as `name` at `span`.
+    #[must_use]
+    pub(crate) fn in_call_from(
+        self,
+        name: &str,
+        span: Span,
+        callee: Option<&Arc<String>>,
+        caller: Option<&Arc<String>>,
+    ) -> Self {
+        let mut err = self.in_file(callee).in_call(name, span);
+        err.trace[0].file = caller.cloned();
+        err
+    }
+
+    /// Render the error for a user: a traceback if it happened while running, or just the error if
+    /// the program couldn't be read.
+    #[must_use]
+    pub fn report(&self) -> String {
+        if self.kind.is_syntax() {
+            match &self.file {
+                Some(file) => format!("Error: {}: {}\n", file, self),
+                None => format!("Error: {}\n", self),
+            }
+        } else {
+            self.traceback()
+        }
+    }
+
+    /// Render the error like a Python traceback, most recent call last.
+    #[must_use]
+    pub fn traceback(&self) -> String {
+        let at = |file: &Option<Arc<String>>, span: Span| match file {
+            Some(file) => format!("{}: {}", file, span.start),
+            None => span.start.to_string(),
+        };
+        let mut out = String::from("Traceback (most recent call last):\n");
+        let mut function = "<module>";
+        for frame in &self.trace {
+            out += &format!("  {}, in {}\n", at(&frame.file, frame.span), function);
+            function = frame.name.as_str();
+        }
+        let message = self
+            .kind
+            .as_exception()
+            .map_or_else(|| self.kind.to_string(), |e| e.to_string());
+        out + &format!(
+            "  {}, in {}\n{}\n",
+            at(&self.file, self.span),
+            function,
+            message
+        )
+    }
+}
+
+/// A call an error unwound through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The function that was called.
+    pub name: String,
+    /// Where it was called from.
+    pub span: Span,
+    /// The file `span` is in, if the code came from one.
+    pub file: Option<Arc<String>>,
+}
+
+impl Kind {
+    /// Whether the error comes from reading the program rather than running it.
+    #[must_use]
+    pub const fn is_syntax(&self) -> bool {
+        matches!(
+            self,
+            Self::Tokenization
+                | Self::Parser
+                | Self::UnexpectedEof
+                | Self::Indentation
+                | Self::Syntax(_)
+                | Self::WrongChar { .. }
+        )
+    }
+
+    /// The exception a program sees for this error, if it can catch it.
+    ///
+    /// Syntax errors, exceeded limits, cancellation and `exit` can't be caught, though `finally`
+    /// blocks still run as they unwind.
+    #[must_use]
+    pub fn as_exception(&self) -> Option<Exception> {
+        let ty = match self {
+            Self::Exception(e) => return Some(e.clone()),
+            Self::UndefinedName(_) => ExcType::NameError,
+            Self::UnboundLocal(_) => ExcType::UnboundLocalError,
+            Self::Attribute { .. } => ExcType::AttributeError,
+            Self::DivisionByZero => ExcType::ZeroDivisionError,
+            Self::Overflow => ExcType::OverflowError,
+            Self::Type(message) => {
+                return Some(Exception {
+                    ty: ExcType::TypeError,
+                    message: message.clone(),
+                })
+            }
+            Self::Arity { .. }
+            | Self::MissingArgument { .. }
+            | Self::DuplicateArgument { .. }
+            | Self::UnexpectedKeyword { .. } => ExcType::TypeError,
+            Self::Value(message) => {
+                return Some(Exception {
+                    ty: ExcType::ValueError,
+                    message: message.clone(),
+                })
+            }
+            Self::Index(message) => {
+                return Some(Exception {
+                    ty: ExcType::IndexError,
+                    message: message.clone(),
+                })
+            }
+            Self::Key(key) => {
+                return Some(Exception {
+                    ty: ExcType::KeyError,
+                    message: key.clone(),
+                })
+            }
+            Self::Interpretation | Self::Native { .. } => ExcType::RuntimeError,
+            Self::Assertion { .. } => ExcType::AssertionError,
+            Self::Io(_) => ExcType::OSError,
+            Self::Import(message) => {
+                return Some(Exception {
+                    ty: ExcType::ImportError,
+                    message: message.clone(),
+                })
+            }
+            Self::ModuleNotFound(name) => {
+                return Some(Exception {
+                    ty: ExcType::ModuleNotFoundError,
+                    message: format!("no module named `{}`", name),
+                })
+            }
+            Self::Tokenization
+            | Self::Parser
+            | Self::UnexpectedEof
+            | Self::Indentation
+            | Self::Syntax(_)
+            | Self::WrongChar { .. }
+            | Self::StepLimit
+            | Self::DepthLimit
+            | Self::ValueSizeLimit
+            | Self::OutputLimit
+            | Self::Timeout
+            | Self::Cancelled
+            | Self::Exit(_) => return None,
+        };
+        Some(Exception {
+            ty,
+            message: self.to_string(),
+        })
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum Kind {
     #[error("tokenization failed")]
     Tokenization,
@@ -24,9 +224,177 @@ pub enum Kind {
     #[error("unexpected end of file")]
     UnexpectedEof,
 
+    #[error("unindent does not match any outer indentation level")]
+    Indentation,
+
+    #[error("{0}")]
+    Syntax(String),
+
     #[error("interpretation failed")]
     Interpretation,
 
+    #[error("name `{0}` is not defined")]
+    UndefinedName(String),
+
+    /// A function read one of its local variables before assigning it.
+    #[error("local variable `{0}` referenced before assignment")]
+    UnboundLocal(String),
+
+    #[error("{ty} object has no attribute `{name}`")]
+    Attribute { ty: String, name: String },
+
     #[error("expected {expected}, but saw {got}")]
-    WrongChar { expected: TokenKind, got: TokenKind },
+    WrongChar {
+        // boxed to keep `Error` small, since every `Result` carries one
+        expected: Box<TokenKind>,
+        got: Box<TokenKind>,
+    },
+
+    #[error("division by zero")]
+    DivisionByZero,
+
+    #[error("integer overflow")]
+    Overflow,
+
+    #[error("type error: {0}")]
+    Type(String),
+
+    #[error("value error: {0}")]
+    Value(String),
+
+    #[error("index error: {0}")]
+    Index(String),
+
+    /// A dict had no entry for the key, given as its `repr`.
+    #[error("key error: {0}")]
+    Key(String),
+
+    #[error("{0}")]
+    Exception(Exception),
+
+    #[error("`{expr}` is false{}", .message.as_ref().map_or(String::new(), |m| format!(": {}", m)))]
+    Assertion {
+        /// The source text of the condition.
+        expr: String,
+        message: Option<String>,
+    },
+
+    #[error("`{name}` takes {expected} argument(s) but {got} were given")]
+    Arity {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+
+    #[error("`{name}` missing required argument `{param}`")]
+    MissingArgument { name: String, param: String },
+
+    #[error("`{name}` got multiple values for argument `{param}`")]
+    DuplicateArgument { name: String, param: String },
+
+    #[error("`{name}` got an unexpected keyword argument `{param}`")]
+    UnexpectedKeyword { name: String, param: String },
+
+    #[error("`{name}` failed: {message}")]
+    Native { name: String, message: String },
+
+    #[error("step limit exceeded")]
+    StepLimit,
+
+    #[error("call depth limit exceeded")]
+    DepthLimit,
+
+    #[error("value size limit exceeded")]
+    ValueSizeLimit,
+
+    #[error("output limit exceeded")]
+    OutputLimit,
+
+    #[error("time limit exceeded")]
+    Timeout,
+
+    #[error("cancelled")]
+    Cancelled,
+
+    /// The program called `exit(status)`.
+    #[error("exited with status {0}")]
+    Exit(i32),
+
+    #[error("i/o error: {0}")]
+    Io(String),
+
+    #[error("import error: {0}")]
+    Import(String),
+
+    #[error("no module named `{0}`")]
+    ModuleNotFound(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Loc;
+
+    fn at(row: usize, col: usize) -> Span {
+        Span {
+            start: Loc { row, col },
+            end: Loc { row, col },
+        }
+    }
+
+    #[test]
+    fn traceback_at_top_level() {
+        let err = Error::new(Kind::UndefinedName("y".to_string()), at(2, 5));
+        assert_eq!(
+            err.traceback(),
+            "Traceback (most recent call last):\n  \
+             row 2, col 5, in <module>\n\
+             NameError: name `y` is not defined\n"
+        );
+    }
+
+    #[test]
+    fn traceback_through_calls() {
+        let err = Error::new(Kind::DivisionByZero, at(7, 12))
+            .in_call("g", at(4, 5))
+            .in_call("f", at(10, 1));
+        assert_eq!(
+            err.trace
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>(),
+            ["f", "g"]
+        );
+        assert_eq!(
+            err.traceback(),
+            "Traceback (most recent call last):\n  \
+             row 10, col 1, in <module>\n  \
+             row 4, col 5, in f\n  \
+             row 7, col 12, in g\n\
+             ZeroDivisionError: division by zero\n"
+        );
+    }
+
+    #[test]
+    fn traceback_across_files() {
+        let err = Error::new(Kind::DivisionByZero, at(3, 9)).in_call_from(
+            "f",
+            at(2, 1),
+            Some(&Arc::new("lib.slpy".to_string())),
+            None,
+        );
+        assert_eq!(
+            err.traceback(),
+            "Traceback (most recent call last):\n  \
+             row 2, col 1, in <module>\n  \
+             lib.slpy: row 3, col 9, in f\n\
+             ZeroDivisionError: division by zero\n"
+        );
+        let mut err = Error::new(Kind::Parser, at(1, 4));
+        err.file = Some(Arc::new("lib.slpy".to_string()));
+        assert_eq!(
+            err.report(),
+            "Error: lib.slpy: parsing failed from row 1, col 4 to row 1, col 4\n"
+        );
+    }
 }
@@ -0,0 +1,153 @@
+//! Exceptions programs can raise and catch.
+use std::fmt::Display;
+
+/// A built-in exception class.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExcType {
+    BaseException,
+    Exception,
+    ArithmeticError,
+    ZeroDivisionError,
+    OverflowError,
+    NameError,
+    UnboundLocalError,
+    AttributeError,
+    TypeError,
+    ValueError,
+    LookupError,
+    IndexError,
+    KeyError,
+    RuntimeError,
+    AssertionError,
+    StopIteration,
+    ImportError,
+    ModuleNotFoundError,
+    OSError,
+}
+
+impl ExcType {
+    pub(crate) const ALL: [Self; 19] = [
+        Self::BaseException,
+        Self::Exception,
+        Self::ArithmeticError,
+        Self::ZeroDivisionError,
+        Self::OverflowError,
+        Self::NameError,
+        Self::UnboundLocalError,
+        Self::AttributeError,
+        Self::TypeError,
+        Self::ValueError,
+        Self::LookupError,
+        Self::IndexError,
+        Self::KeyError,
+        Self::RuntimeError,
+        Self::AssertionError,
+        Self::StopIteration,
+        Self::ImportError,
+        Self::ModuleNotFoundError,
+        Self::OSError,
+    ];
+
+    /// The class's name.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::BaseException => "BaseException",
+            Self::Exception => "Exception",
+            Self::ArithmeticError => "ArithmeticError",
+            Self::ZeroDivisionError => "ZeroDivisionError",
+            Self::OverflowError => "OverflowError",
+            Self::NameError => "NameError",
+            Self::UnboundLocalError => "UnboundLocalError",
+            Self::AttributeError => "AttributeError",
+            Self::TypeError => "TypeError",
+            Self::ValueError => "ValueError",
+            Self::LookupError => "LookupError",
+            Self::IndexError => "IndexError",
+            Self::KeyError => "KeyError",
+            Self::RuntimeError => "RuntimeError",
+            Self::AssertionError => "AssertionError",
+            Self::StopIteration => "StopIteration",
+            Self::ImportError => "ImportError",
+            Self::ModuleNotFoundError => "ModuleNotFoundError",
+            Self::OSError => "OSError",
+        }
+    }
+
+    /// Look a class up by name.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// The class this one inherits from.
+    #[must_use]
+    pub const fn parent(self) -> Option<Self> {
+        match self {
+            Self::BaseException => None,
+            Self::Exception => Some(Self::BaseException),
+            Self::ZeroDivisionError | Self::OverflowError => Some(Self::ArithmeticError),
+            Self::ModuleNotFoundError => Some(Self::ImportError),
+            Self::UnboundLocalError => Some(Self::NameError),
+            Self::IndexError | Self::KeyError => Some(Self::LookupError),
+            Self::ArithmeticError
+            | Self::NameError
+            | Self::AttributeError
+            | Self::TypeError
+            | Self::ValueError
+            | Self::LookupError
+            | Self::RuntimeError
+            | Self::AssertionError
+            | Self::StopIteration
+            | Self::ImportError
+            | Self::OSError => Some(Self::Exception),
+        }
+    }
+
+    /// Whether this class is `other` or inherits from it.
+    #[must_use]
+    pub fn is_subclass(self, other: Self) -> bool {
+        std::iter::successors(Some(self), |t| t.parent()).any(|t| t == other)
+    }
+}
+
+/// A raised exception.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Exception {
+    pub ty: ExcType,
+    pub message: String,
+}
+
+impl Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.ty.name())
+        } else {
+            write!(f, "{}: {}", self.ty.name(), self.message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierarchy() {
+        assert!(ExcType::ZeroDivisionError.is_subclass(ExcType::ZeroDivisionError));
+        assert!(ExcType::ZeroDivisionError.is_subclass(ExcType::ArithmeticError));
+        assert!(ExcType::ZeroDivisionError.is_subclass(ExcType::BaseException));
+        assert!(!ExcType::ZeroDivisionError.is_subclass(ExcType::ValueError));
+        assert!(!ExcType::Exception.is_subclass(ExcType::NameError));
+        assert!(ExcType::KeyError.is_subclass(ExcType::LookupError));
+        assert!(ExcType::UnboundLocalError.is_subclass(ExcType::NameError));
+    }
+
+    #[test]
+    fn names() {
+        for ty in ExcType::ALL {
+            assert_eq!(ExcType::from_name(ty.name()), Some(ty));
+        }
+        assert_eq!(ExcType::from_name("print"), None);
+    }
+}
@@ -0,0 +1,758 @@
+//! The embedding API.
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::ast::{Ast, Context, Expn, Prgm};
+use crate::cancel::CancelHandle;
+use crate::error::{Error, Kind, Result};
+use crate::limits::Limits;
+use crate::native::NativeFn;
+use crate::object::{IntoSlpy, SlpyObject};
+use crate::signature::Signature;
+use crate::tokenizer::{TokenKind, Tokenizer};
+
+/// An slpy interpreter whose globals persist across calls.
+///
+/// ```
+/// use slpy_rust::{Interpreter, SlpyObject};
+///
+/// let mut output = Vec::new();
+/// let mut interp = Interpreter::with_io("".as_bytes(), &mut output);
+/// interp.set("width", 3);
+/// interp.eval_str("area = width * width\nprint(area)").unwrap();
+/// assert_eq!(interp.get("area"), Some(SlpyObject::Int(9)));
+/// assert_eq!(interp.eval_expr("area + 1").unwrap(), SlpyObject::Int(10));
+/// drop(interp);
+/// assert_eq!(output, b"9\n");
+/// ```
+#[derive(Default)]
+pub struct Interpreter<'io> {
+    ctx: Context<'io>,
+    strip_asserts: bool,
+}
+
+impl<'io> Interpreter<'io> {
+    /// Create an interpreter that reads `input` from `input` and `print`s to `output`.
+    pub fn with_io(input: impl BufRead + 'io, output: impl Write + 'io) -> Self {
+        Self {
+            ctx: Context::new(input, output),
+            strip_asserts: false,
+        }
+    }
+
+    /// Bound the work each call to `eval_str` or `eval_expr` may do.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.ctx.set_limits(limits);
+    }
+
+    /// Leave `assert` statements out of programs passed to `eval_str`, and the modules they
+    /// import, like `python -O`.
+    pub fn set_strip_asserts(&mut self, strip: bool) {
+        self.strip_asserts = strip;
+        self.ctx.set_strip_asserts(strip);
+    }
+
+    /// Give programs `args` as their `argv` list, starting with the program's own name.
+    pub fn set_argv(&mut self, args: impl IntoIterator<Item = String>) {
+        self.ctx.set_argv(args);
+    }
+
+    /// Look for imported modules in `dir` when they aren't next to the file importing them.
+    pub fn add_module_path(&mut self, dir: impl Into<PathBuf>) {
+        self.ctx.add_module_path(dir);
+    }
+
+    /// A handle that stops whatever this interpreter is running, e.g. from another thread.
+    #[must_use]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.ctx.cancel_handle()
+    }
+
+    /// Run a program, keeping any globals it assigns.
+    ///
+    /// # Errors
+    /// If tokenizing, parsing or evaluation fails.
+    pub fn eval_str(&mut self, source: &str) -> Result<()> {
+        self.ctx.reset_usage();
+        let mut tokens = Tokenizer::lex(source)?;
+        tokens.set_strip_asserts(self.strip_asserts);
+        let prgm = Prgm::parse(&mut tokens)?;
+        let result = prgm.eval(&mut self.ctx);
+        self.ctx.finish(result)
+    }
+
+    /// Run the program in the file at `path`, which errors then report, and next to which its
+    /// imports are looked for.
+    ///
+    /// # Errors
+    /// If the file can't be read, or tokenizing, parsing or evaluation fails.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            let start = crate::Loc { row: 1, col: 1 };
+            let mut err = Error::new(Kind::Io(e.to_string()), crate::Span { start, end: start });
+            err.file = Some(Arc::new(path.display().to_string()));
+            err
+        })?;
+        self.eval_source(&source, path.display().to_string())
+    }
+
+    /// Run a program as if it were read from the file `name`, e.g. `<stdin>`, which errors then
+    /// report, and next to which its imports are looked for.
+    ///
+    /// # Errors
+    /// If tokenizing, parsing or evaluation fails.
+    pub fn eval_source(&mut self, source: &str, name: impl Into<String>) -> Result<()> {
+        let file = Arc::new(name.into());
+        let in_file = |mut e: Error| {
+            e.file = Some(Arc::clone(&file));
+            e
+        };
+        self.ctx.reset_usage();
+        let mut tokens = Tokenizer::lex(source).map_err(in_file)?;
+        tokens.set_strip_asserts(self.strip_asserts);
+        let prgm = Prgm::parse(&mut tokens).map_err(in_file)?;
+        let result = self.ctx.eval_file(&prgm, file);
+        self.ctx.finish(result)
+    }
+
+    /// Evaluate a single expression.
+    ///
+    /// # Errors
+    /// If tokenizing, parsing or evaluation fails, or there is anything after the expression.
+    pub fn eval_expr(&mut self, source: &str) -> Result<SlpyObject> {
+        let mut tokens = Tokenizer::lex(source)?;
+        let expn = Expn::parse(&mut tokens)?;
+        if tokens
+            .current()
+            .is_some_and(|t| t.kind == TokenKind::NewLine)
+        {
+            tokens.advance();
+        }
+        if let Some(tkn) = tokens.current() {
+            return Err(Error::new(Kind::Parser, tkn.span));
+        }
+        self.ctx.reset_usage();
+        let result = expn.eval(&mut self.ctx);
+        self.ctx.finish(result)
+    }
+
+    /// Get the value of a global.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<SlpyObject> {
+        self.ctx.get(name)
+    }
+
+    /// Set the value of a global.
+    pub fn set(&mut self, name: impl Into<String>, val: impl IntoSlpy) {
+        self.ctx.set(name.into(), val.into_slpy());
+    }
+
+    /// Register a Rust closure as a function callable from slpy as `name`.
+    ///
+    /// ```
+    /// use slpy_rust::{Interpreter, SlpyObject};
+    ///
+    /// let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+    /// interp.register_fn("double", |n: i32| n * 2);
+    /// assert_eq!(interp.eval_expr("double(21)").unwrap(), SlpyObject::Int(42));
+    /// ```
+    pub fn register_fn<Args>(&mut self, name: impl Into<String>, f: impl NativeFn<Args> + 'io) {
+        self.ctx.register_fn(name, f);
+    }
+
+    /// Register a Rust closure as a function whose parameters are described by `sig`, so it can
+    /// take defaults and keyword arguments. `f` takes one argument per slot of `sig`.
+    ///
+    /// # Panics
+    /// If `f` doesn't take one argument per slot of `sig`.
+    pub fn register_fn_with_signature<Args>(
+        &mut self,
+        name: impl Into<String>,
+        sig: Signature,
+        f: impl NativeFn<Args> + 'io,
+    ) {
+        self.ctx.register_fn_with_signature(name, sig, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globals_persist() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        interp.eval_str("x = 2").unwrap();
+        interp.eval_str("y = x ** 3").unwrap();
+        assert_eq!(interp.get("y"), Some(SlpyObject::Int(8)));
+        assert_eq!(interp.get("z"), None);
+    }
+
+    #[test]
+    fn default_interpreters_share_stdin() {
+        let mut first = Interpreter::default();
+        first.eval_str("x = 1").unwrap();
+        let mut second = Interpreter::default();
+        second.eval_str("x = 2").unwrap();
+        assert_eq!(first.get("x"), Some(SlpyObject::Int(1)));
+    }
+
+    #[test]
+    fn set_then_eval() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        interp.set("limit", 10);
+        assert_eq!(interp.eval_expr("limit % 3").unwrap(), SlpyObject::Int(1));
+    }
+
+    #[test]
+    fn eval_expr_rejects_trailing_tokens() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        let err = interp.eval_expr("1\nx = 2").unwrap_err();
+        assert_eq!(err.kind, Kind::Parser);
+        assert_eq!(interp.get("x"), None);
+    }
+
+    #[test]
+    fn runtime_errors_are_spanned() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        let err = interp.eval_str("x = 1\ny = 4 // (x - 1)").unwrap_err();
+        assert_eq!(err.kind, Kind::DivisionByZero);
+        assert_eq!(err.span.start.row, 2);
+        // the first statement still ran
+        assert_eq!(interp.get("x"), Some(SlpyObject::Int(1)));
+    }
+
+    #[test]
+    fn overflow() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        assert_eq!(
+            interp.eval_expr("2 ** 40").unwrap_err().kind,
+            Kind::Overflow
+        );
+    }
+
+    #[test]
+    fn failed_assert() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        let err = interp
+            .eval_str("x = 2\nassert x - 2, \"x is \" * x\nx = 3")
+            .unwrap_err();
+        assert_eq!(
+            err.kind,
+            Kind::Assertion {
+                expr: "x - 2".to_string(),
+                message: Some("x is x is ".to_string())
+            }
+        );
+        assert_eq!(err.span.start, crate::Loc { row: 2, col: 8 });
+        assert!(err
+            .traceback()
+            .ends_with("AssertionError: `x - 2` is false: x is x is \n"));
+        assert_eq!(interp.get("x"), Some(SlpyObject::Int(2)));
+    }
+
+    #[test]
+    fn stripped_asserts() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        interp.set_strip_asserts(true);
+        interp
+            .eval_str("assert 0, undefined\nx = 1\nassert x - 1")
+            .unwrap();
+        assert_eq!(interp.get("x"), Some(SlpyObject::Int(1)));
+    }
+
+    #[test]
+    fn lookup_and_emit() {
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+
+        let config = HashMap::from([("threshold".to_string(), 10)]);
+        let emitted = RefCell::new(Vec::new());
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        interp.register_fn("lookup", |key: String| {
+            config
+                .get(&key)
+                .copied()
+                .ok_or_else(|| format!("no key {}", key))
+        });
+        interp.register_fn("emit", |val: SlpyObject| emitted.borrow_mut().push(val));
+        interp
+            .eval_str("x = lookup(\"threshold\") * 2\ny = emit(x)\nemit(\"done\")")
+            .unwrap();
+        assert_eq!(interp.get("y"), Some(SlpyObject::None));
+
+        let err = interp.eval_str("lookup(\"missing\")").unwrap_err();
+        assert_eq!(
+            err.kind,
+            Kind::Native {
+                name: "lookup".to_string(),
+                message: "no key missing".to_string()
+            }
+        );
+        drop(interp);
+        assert_eq!(
+            emitted.into_inner(),
+            vec![SlpyObject::Int(20), SlpyObject::Str("done".to_string())]
+        );
+    }
+
+    #[test]
+    fn native_errors_point_at_call() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        interp.register_fn("add", |a: i32, b: i32| a + b);
+
+        let err = interp.eval_str("x = 1\ny = 2 + add(1)").unwrap_err();
+        assert_eq!(
+            err.kind,
+            Kind::Arity {
+                name: "add".to_string(),
+                expected: 2,
+                got: 1
+            }
+        );
+        assert_eq!(err.span.start, crate::Loc { row: 2, col: 9 });
+        assert_eq!(err.span.end, crate::Loc { row: 2, col: 14 });
+
+        let err = interp.eval_expr("add(1, \"2\")").unwrap_err();
+        assert_eq!(
+            err.kind,
+            Kind::Type("argument 2 to `add`: expected int, got str".to_string())
+        );
+    }
+
+    #[test]
+    fn native_signature() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        let sig = Signature::new()
+            .param("sep")
+            .var_args("parts")
+            .param_with_default("end", "");
+        interp.register_fn_with_signature(
+            "join",
+            sig,
+            |sep: String, parts: Vec<String>, end: String| parts.join(&sep) + &end,
+        );
+        assert_eq!(
+            interp
+                .eval_expr("join(\"-\", \"a\", \"b\", end=\"!\")")
+                .unwrap(),
+            SlpyObject::Str("a-b!".to_string())
+        );
+        assert_eq!(
+            interp.eval_expr("join(sep=\",\")").unwrap(),
+            SlpyObject::Str(String::new())
+        );
+        assert_eq!(
+            interp.eval_expr("join(\"-\", sep=\",\")").unwrap_err().kind,
+            Kind::DuplicateArgument {
+                name: "join".to_string(),
+                param: "sep".to_string()
+            }
+        );
+        assert_eq!(
+            interp.eval_expr("join()").unwrap_err().kind,
+            Kind::MissingArgument {
+                name: "join".to_string(),
+                param: "sep".to_string()
+            }
+        );
+        assert_eq!(
+            interp.eval_expr("join(\"\", start=1)").unwrap_err().kind,
+            Kind::UnexpectedKeyword {
+                name: "join".to_string(),
+                param: "start".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn keywords_to_plain_native() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        interp.register_fn("double", |n: i32| n * 2);
+        assert_eq!(
+            interp.eval_expr("double(n=1)").unwrap_err().kind,
+            Kind::UnexpectedKeyword {
+                name: "double".to_string(),
+                param: "n".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_function() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        assert_eq!(
+            interp.eval_expr("nope()").unwrap_err().kind,
+            Kind::UndefinedName("nope".to_string())
+        );
+    }
+
+    #[test]
+    fn argv() {
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        assert_eq!(interp.eval_expr("argv").unwrap().to_string(), "[]");
+        interp.set_argv(["prog.slpy", "a", "b"].map(String::from));
+        interp
+            .eval_str("n = 0\nfor arg in argv:\n    n += 1\nlast = arg")
+            .unwrap();
+        assert_eq!(interp.get("n"), Some(SlpyObject::Int(3)));
+        assert_eq!(interp.get("last"), Some(SlpyObject::Str("b".to_string())));
+    }
+
+    #[test]
+    fn exit_unwinds_through_finally() {
+        let mut output = Vec::new();
+        let mut interp = Interpreter::with_io("".as_bytes(), &mut output);
+        let err = interp
+            .eval_str(
+                "def f():\n    try:\n        exit(3)\n    except:\n        print(\"caught\")\n    \
+                 finally:\n        print(\"cleanup\")\nf()\nprint(\"after\")",
+            )
+            .unwrap_err();
+        assert_eq!(err.kind, Kind::Exit(3));
+        assert_eq!(interp.eval_expr("exit()").unwrap_err().kind, Kind::Exit(0));
+        assert_eq!(
+            interp.eval_expr("exit(\"no\")").unwrap_err().kind,
+            Kind::Type("exit status must be an int, not str".to_string())
+        );
+        drop(interp);
+        assert_eq!(output, b"cleanup\n");
+    }
+
+    #[test]
+    fn getenv() {
+        std::env::set_var("SLPY_TEST_GETENV", "set");
+        let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+        assert_eq!(
+            interp.eval_expr("getenv(\"SLPY_TEST_GETENV\")").unwrap(),
+            SlpyObject::Str("set".to_string())
+        );
+        assert_eq!(
+            interp.eval_expr("getenv(\"SLPY_TEST_UNSET\")").unwrap(),
+            SlpyObject::None
+        );
+        assert_eq!(
+            interp.eval_expr("getenv(\"SLPY_TEST_UNSET\", 1)").unwrap(),
+            SlpyObject::Int(1)
+        );
+    }
+
+    mod imports {
+        use super::*;
+
+        /// A fresh directory holding `files`, named for the test using it.
+        fn project(test: &str, files: &[(&str, &str)]) -> PathBuf {
+            let dir = std::env::temp_dir().join(format!("slpy-{}-{}", std::process::id(), test));
+            let _ = std::fs::remove_dir_all(&dir);
+            for (name, source) in files {
+                let path = dir.join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, source).unwrap();
+            }
+            dir
+        }
+
+        fn run(dir: &Path, main: &str) -> (Result<()>, String) {
+            let mut output = Vec::new();
+            let mut interp = Interpreter::with_io("".as_bytes(), &mut output);
+            interp.add_module_path(dir.join("lib"));
+            let result = interp.eval_file(dir.join(main));
+            drop(interp);
+            (result, String::from_utf8(output).unwrap())
+        }
+
+        #[test]
+        fn namespaces_and_cache() {
+            let dir = project(
+                "namespaces",
+                &[
+                    (
+                        "main.slpy",
+                        "import helpers\nimport helpers as h\nfrom helpers import double, n as m\n\
+                         from extra import triple\nn = 10\nh.n += 1\n\
+                         print(helpers.double(n), double(2), m, helpers.n, triple(1), h == helpers)",
+                    ),
+                    (
+                        "helpers.slpy",
+                        "print(\"loading\")\nn = 1\ndef double(x):\n    return x * 2 + n - 1",
+                    ),
+                    ("lib/extra.slpy", "def triple(x):\n    return x * 3"),
+                ],
+            );
+            let (result, output) = run(&dir, "main.slpy");
+            result.unwrap();
+            assert_eq!(output, "loading\n21 5 1 2 3 True\n");
+        }
+
+        #[test]
+        fn relative_to_importing_file() {
+            let dir = project(
+                "relative",
+                &[
+                    ("main.slpy", "from sub import f\nprint(f())"),
+                    (
+                        "lib/sub.slpy",
+                        "from helper import g\ndef f():\n    return g()",
+                    ),
+                    ("lib/helper.slpy", "def g():\n    return \"lib\""),
+                    ("helper.slpy", "def g():\n    return \"top\""),
+                ],
+            );
+            let (result, output) = run(&dir, "main.slpy");
+            result.unwrap();
+            assert_eq!(output, "lib\n");
+        }
+
+        #[test]
+        fn circular() {
+            let dir = project(
+                "circular",
+                &[
+                    ("main.slpy", "import a"),
+                    ("a.slpy", "import b"),
+                    ("b.slpy", "x = 1\nimport a"),
+                ],
+            );
+            let (result, _) = run(&dir, "main.slpy");
+            let err = result.unwrap_err();
+            assert_eq!(
+                err.kind,
+                Kind::Import("circular import of `a`: a -> b -> a".to_string())
+            );
+            assert_eq!(err.span.start, crate::Loc { row: 2, col: 1 });
+            assert!(err.file.unwrap().ends_with("b.slpy"));
+        }
+
+        #[test]
+        fn missing_name() {
+            let dir = project(
+                "missing_name",
+                &[
+                    (
+                        "main.slpy",
+                        "try:\n    from m import nope\nexcept ImportError as e:\n    print(e)",
+                    ),
+                    ("m.slpy", "x = 1"),
+                ],
+            );
+            let (result, output) = run(&dir, "main.slpy");
+            result.unwrap();
+            assert_eq!(output, "cannot import name `nope` from `m`\n");
+        }
+
+        #[test]
+        fn errors_report_the_module() {
+            let dir = project(
+                "errors",
+                &[
+                    ("main.slpy", "import m\nm.f()"),
+                    ("m.slpy", "def f():\n    return 1 // 0"),
+                    ("bad.slpy", "import syntax"),
+                    ("syntax.slpy", "x = = 1"),
+                ],
+            );
+            let main = dir.join("main.slpy").display().to_string();
+            let m = dir.join("m.slpy").display().to_string();
+            let (result, _) = run(&dir, "main.slpy");
+            assert_eq!(
+                result.unwrap_err().traceback(),
+                format!(
+                    "Traceback (most recent call last):\n  \
+                     {}: row 2, col 1, in <module>\n  \
+                     {}: row 2, col 12, in f\n\
+                     ZeroDivisionError: division by zero\n",
+                    main, m
+                )
+            );
+
+            let (result, _) = run(&dir, "bad.slpy");
+            let err = result.unwrap_err();
+            assert_eq!(err.kind, Kind::Parser);
+            assert_eq!(
+                err.file.as_deref(),
+                Some(&dir.join("syntax.slpy").display().to_string())
+            );
+        }
+    }
+
+    mod limits {
+        use super::*;
+
+        fn limited(limits: Limits) -> Interpreter<'static> {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            interp.set_limits(limits);
+            interp
+        }
+
+        #[test]
+        fn steps() {
+            let mut interp = limited(Limits {
+                max_steps: Some(10),
+                ..Limits::default()
+            });
+            interp.eval_str("x = 1 + 2\ny = x * x").unwrap();
+            let err = interp
+                .eval_str("a = 1\nb = 2\nc = 3\nd = 4\ne = 5\nf = 6")
+                .unwrap_err();
+            assert_eq!(err.kind, Kind::StepLimit);
+            assert_eq!(err.span.start.row, 6);
+            // each call gets a fresh budget
+            interp.eval_str("x = 1 + 2\ny = x * x").unwrap();
+        }
+
+        #[test]
+        fn depth() {
+            let mut interp = limited(Limits {
+                max_depth: Some(0),
+                ..Limits::default()
+            });
+            interp.register_fn("f", || 1);
+            assert_eq!(interp.eval_expr("f()").unwrap_err().kind, Kind::DepthLimit);
+        }
+
+        #[test]
+        fn runaway_recursion_is_limited_by_default() {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            let err = interp
+                .eval_str("def f(n):\n    return f(n + 1)\nf(0)")
+                .unwrap_err();
+            assert_eq!(err.kind, Kind::DepthLimit);
+            // the interpreter is still usable
+            assert_eq!(interp.eval_expr("1 + 1").unwrap(), SlpyObject::Int(2));
+        }
+
+        #[test]
+        fn deep_recursion_does_not_overflow_the_stack() {
+            // far deeper than the 2 MiB stack of a test thread holds without help
+            let mut interp = limited(Limits {
+                max_depth: Some(20_000),
+                ..Limits::default()
+            });
+            interp
+                .eval_str("def down(n):\n    if n == 0:\n        return 0\n    return down(n - 1)\nx = down(10000)")
+                .unwrap();
+            let err = interp.eval_str("y = down(30000)").unwrap_err();
+            assert_eq!(err.kind, Kind::DepthLimit);
+        }
+
+        #[test]
+        fn value_size() {
+            let mut interp = limited(Limits {
+                max_value_size: Some(8),
+                ..Limits::default()
+            });
+            interp.eval_str("x = \"ab\" * 4").unwrap();
+            let err = interp.eval_str("y = x + \"c\"").unwrap_err();
+            assert_eq!(err.kind, Kind::ValueSizeLimit);
+            let err = interp.eval_str("y = \"ab\" * 2000000000").unwrap_err();
+            assert_eq!(err.kind, Kind::ValueSizeLimit);
+            let err = interp
+                .eval_str("d = {}\nfor c in \"abcdefghij\":\n    d[c] = 1")
+                .unwrap_err();
+            assert_eq!(err.kind, Kind::ValueSizeLimit);
+            assert_eq!(
+                interp
+                    .eval_expr("d")
+                    .unwrap()
+                    .to_string()
+                    .matches(':')
+                    .count(),
+                8
+            );
+        }
+
+        #[test]
+        fn output() {
+            let mut output = Vec::new();
+            let mut interp = Interpreter::with_io("".as_bytes(), &mut output);
+            interp.set_limits(Limits {
+                max_output: Some(5),
+                ..Limits::default()
+            });
+            let err = interp
+                .eval_str("print(12)\nprint(345)\nprint(6)")
+                .unwrap_err();
+            assert_eq!(err.kind, Kind::OutputLimit);
+            assert_eq!(err.span.start.row, 2);
+            drop(interp);
+            assert_eq!(output, b"12\n34");
+        }
+
+        #[test]
+        fn timeout() {
+            let mut interp = limited(Limits {
+                max_time: Some(std::time::Duration::ZERO),
+                ..Limits::default()
+            });
+            interp.eval_str("x = 1").unwrap();
+            let err = interp.eval_str(&"x = 1\n".repeat(600)).unwrap_err();
+            assert_eq!(err.kind, Kind::Timeout);
+        }
+    }
+
+    mod cancel {
+        use super::*;
+
+        #[test]
+        fn from_inside() {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            let handle = interp.cancel_handle();
+            interp.register_fn("stop", move || handle.cancel());
+            let err = interp.eval_str("x = 1\nstop()\ny = 2").unwrap_err();
+            assert_eq!(err.kind, Kind::Cancelled);
+            assert_eq!(err.span.start.row, 3);
+            assert_eq!(interp.get("y"), None);
+
+            // the request was used up, and the globals survived
+            interp.eval_str("y = x + 1").unwrap();
+            assert_eq!(interp.get("y"), Some(SlpyObject::Int(2)));
+        }
+
+        #[test]
+        fn from_another_thread() {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            let handle = interp.cancel_handle();
+            std::thread::spawn(move || handle.cancel()).join().unwrap();
+            assert_eq!(interp.eval_expr("1").unwrap_err().kind, Kind::Cancelled);
+            assert_eq!(interp.eval_expr("1").unwrap(), SlpyObject::Int(1));
+        }
+
+        #[test]
+        fn stops_a_running_loop() {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            let handle = interp.cancel_handle();
+            let canceller = std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                handle.cancel();
+            });
+            let err = interp.eval_str("while True:\n    pass").unwrap_err();
+            canceller.join().unwrap();
+            assert_eq!(err.kind, Kind::Cancelled);
+        }
+
+        #[test]
+        fn stops_finally_blocks_too() {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            let handle = interp.cancel_handle();
+            interp.register_fn("stop", move || handle.cancel());
+            let src = "try:\n    stop()\n    x = 1\nfinally:\n    while True:\n        pass";
+            let err = interp.eval_str(src).unwrap_err();
+            assert_eq!(err.kind, Kind::Cancelled);
+            // stopped at the start of the `finally` block's loop
+            assert_eq!(err.span.start.row, 5);
+            assert_eq!(interp.get("x"), None);
+            assert_eq!(interp.eval_expr("1").unwrap(), SlpyObject::Int(1));
+        }
+
+        #[test]
+        fn reset() {
+            let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+            let handle = interp.cancel_handle();
+            handle.cancel();
+            handle.reset();
+            assert_eq!(interp.eval_expr("1").unwrap(), SlpyObject::Int(1));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Iterators: over the items of a container, or the values a generator yields.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ast::{Function, Resume};
+use crate::env::Env;
+use crate::object::SlpyObject;
+
+/// An iterator over a snapshot of a container's items, compared by identity.
+pub struct SeqIter {
+    items: RefCell<std::vec::IntoIter<SlpyObject>>,
+}
+
+impl SeqIter {
+    pub(crate) fn new(items: Vec<SlpyObject>) -> Self {
+        Self {
+            items: RefCell::new(items.into_iter()),
+        }
+    }
+
+    pub(crate) fn next(&self) -> Option<SlpyObject> {
+        self.items.borrow_mut().next()
+    }
+}
+
+impl PartialEq for SeqIter {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for SeqIter {}
+
+impl std::fmt::Debug for SeqIter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<iterator object>")
+    }
+}
+
+/// The frame of a call to a generator function, which runs a little each time the next value is
+/// asked for. Compared by identity.
+pub struct Generator {
+    pub(crate) func: Rc<Function>,
+    pub(crate) state: RefCell<GenState>,
+}
+
+pub(crate) enum GenState {
+    /// Waiting to run, with the generator's locals and the `yield` it stopped at; `at` is empty
+    /// before it first runs.
+    Suspended {
+        scope: Env,
+        at: Vec<Resume>,
+    },
+    Running,
+    Finished,
+}
+
+impl Generator {
+    pub(crate) fn new(func: Rc<Function>, scope: Env) -> Self {
+        Self {
+            func,
+            state: RefCell::new(GenState::Suspended {
+                scope,
+                at: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Generator {}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<generator object {}>", self.func.name)
+    }
+}
@@ -1,16 +1,38 @@
 #![allow(dead_code)]
 
 mod ast;
+mod builtin;
+mod cancel;
+mod class;
+mod env;
 mod error;
+mod exception;
+mod interpreter;
+mod iter;
+mod limits;
+mod module;
+mod native;
+mod object;
 mod repl;
+mod signature;
 mod tokenizer;
 
-pub use error::{Error, Result};
+pub use ast::{Context, AST_SCHEMA_VERSION};
+pub use cancel::CancelHandle;
+pub use error::{Error, Kind, Result};
+pub use exception::{ExcType, Exception};
+pub use interpreter::Interpreter;
+pub use limits::{Limits, DEFAULT_MAX_DEPTH};
+pub use native::{NativeError, NativeFn, NativeReturn};
+pub use object::{FromSlpy, IntoSlpy, SlpyObject};
 pub use repl::repl;
+pub use signature::Signature;
+pub use tokenizer::{Comment, Op, Token, TokenKind, TokenStream, Trivia, TriviaKind};
 
 use crate::ast::{Ast, Prgm};
-use crate::tokenizer::Tokenizer;
-use std::fs;
+use crate::tokenizer::{list, list_json, Tokenizer};
+use std::io::Write;
+use std::path::PathBuf;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Loc {
@@ -30,26 +52,136 @@ pub struct Span {
     pub end: Loc,
 }
 
-/// Dump the AST.
+/// How `dump` prints the AST.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DumpFormat {
+    /// An indented tree, for people.
+    Tree,
+    /// JSON, following the schema in `docs/ast-schema.md`.
+    Json,
+    /// S-expressions, carrying the same tree as the JSON.
+    Sexpr,
+    /// A Graphviz digraph of the tree.
+    Dot,
+    /// A Graphviz digraph of the control flow through each function, in basic blocks.
+    Cfg,
+}
+
+/// Dump the AST of `source`.
 ///
 /// # Errors
-/// If parsing fils.
-pub fn dump(source: String) -> Result<()> {
-    let contents = fs::read_to_string(source).expect("Should have been able to read the file");
-    let mut tokens = Tokenizer::lex(contents.as_str())?;
+/// If lexing or parsing fails, or stdout can't be written.
+pub fn dump(source: &str, format: DumpFormat) -> Result<()> {
+    let mut tokens = Tokenizer::lex(source)?;
     let parsed = Prgm::parse(&mut tokens)?;
-    print!("{}", parsed.dump(0));
+    print(&match format {
+        DumpFormat::Tree => parsed.dump(0),
+        DumpFormat::Json => parsed.to_json() + "\n",
+        DumpFormat::Sexpr => parsed.to_sexpr() + "\n",
+        DumpFormat::Dot => parsed.to_dot(),
+        DumpFormat::Cfg => parsed.cfg_dot(),
+    })
+}
+
+/// Write `text` to stdout. A reader that's gone, like `head` once it has read enough, isn't an
+/// error: there's just no one left to write to.
+fn print(text: &str) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    match out.write_all(text.as_bytes()).and_then(|()| out.flush()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+            let start = Loc { row: 1, col: 1 };
+            Err(Error::new(
+                Kind::Io(e.to_string()),
+                Span { start, end: start },
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// How `dump_tokens` prints the tokens.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokensFormat {
+    /// A token a line, as `row:col-row:col  KIND  lexeme`.
+    Text,
+    /// A JSON array of tokens.
+    Json,
+}
+
+/// Print the tokens lexed from `source`, with any errors lexing it shown where they happened.
+///
+/// # Errors
+/// If stdout can't be written, or else if lexing fails anywhere, with the first error, once all
+/// the tokens have been printed.
+pub fn dump_tokens(source: &str, format: TokensFormat) -> Result<()> {
+    let tokens = Tokenizer::lex_recovering(source);
+    print(&match format {
+        TokensFormat::Text => list(source, &tokens),
+        TokensFormat::Json => list_json(source, &tokens) + "\n",
+    })?;
+    tokens.into_iter().find_map(Result::err).map_or(Ok(()), Err)
+}
 
+/// Lex `source` for tools that need all of it, like formatters and highlighters: each token
+/// carries the whitespace, comments and blank lines before it as its `trivia`, so the tokens and
+/// their trivia are the source, byte for byte.
+///
+/// ```
+/// use slpy_rust::{lex_lossless, TriviaKind};
+///
+/// let source = "x = 1  # one\n";
+/// let tokens = lex_lossless(source).unwrap();
+/// assert_eq!(tokens.to_source(), source);
+/// let comment = &tokens.tokens()[3].trivia[1];
+/// assert_eq!((comment.kind, comment.text.as_str()), (TriviaKind::Comment, "# one"));
+/// ```
+///
+/// # Errors
+/// If lexing fails.
+pub fn lex_lossless(source: &str) -> Result<TokenStream> {
+    Tokenizer::lex_lossless(source)
+}
+
+/// Check that `source` is a well-formed program, without running it.
+///
+/// # Errors
+/// If lexing or parsing fails.
+pub fn check(source: &str) -> Result<()> {
+    let mut tokens = Tokenizer::lex(source)?;
+    Prgm::parse(&mut tokens)?;
     Ok(())
 }
 
-/// Run the source file.
+/// Print `source` as canonical slpy, keeping its comments.
 ///
 /// # Errors
-/// If parsing or evaluation fails.
-pub fn run(source: String) -> Result<()> {
-    let contents = fs::read_to_string(source).expect("Should have been able to read the file");
-    let mut tokens = Tokenizer::lex(contents.as_str())?;
+/// If lexing or parsing fails.
+pub fn pretty_print(source: &str) -> Result<String> {
+    let mut tokens = Tokenizer::lex(source)?;
     let parsed = Prgm::parse(&mut tokens)?;
-    parsed.eval(&mut ast::Context::default())
+    Ok(parsed.unparse(&tokens))
+}
+
+/// Run `source`, read from the file `name`, with `args` after its name in `argv`, within `limits`,
+/// leaving out `assert` statements if `strip_asserts`, and looking for modules it imports in
+/// `module_path` when they aren't next to the importing file.
+///
+/// # Errors
+/// If parsing or evaluation fails, the program exceeds one of the limits, or it calls `exit`.
+pub fn run(
+    source: &str,
+    name: &str,
+    args: Vec<String>,
+    limits: Limits,
+    strip_asserts: bool,
+    module_path: Vec<PathBuf>,
+) -> Result<()> {
+    let mut interp = Interpreter::default();
+    interp.set_argv(std::iter::once(name.to_string()).chain(args));
+    interp.set_limits(limits);
+    interp.set_strip_asserts(strip_asserts);
+    for dir in module_path {
+        interp.add_module_path(dir);
+    }
+    interp.eval_source(source, name)
 }
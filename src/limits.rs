@@ -0,0 +1,56 @@
+//! Execution budgets.
+use std::time::{Duration, Instant};
+
+/// Bounds on how much work a program may do. `None` means unbounded.
+///
+/// Each limit that is hit produces its own error kind, so a host can tell which one was exceeded.
+/// By default, only call depth is bounded, at `DEFAULT_MAX_DEPTH`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Limits {
+    /// The number of statements and expressions evaluated.
+    pub max_steps: Option<u64>,
+    /// How deeply function calls may nest. Unbounded, runaway recursion only stops when memory
+    /// runs out.
+    pub max_depth: Option<usize>,
+    /// The length of any one value; for strings, in bytes.
+    pub max_value_size: Option<usize>,
+    /// The number of bytes `print` and `input` prompts may write.
+    pub max_output: Option<usize>,
+    /// How long the program may run for, checked every 1024 steps.
+    pub max_time: Option<Duration>,
+}
+
+/// How deeply calls may nest by default, like Python's recursion limit.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_value_size: None,
+            max_output: None,
+            max_time: None,
+        }
+    }
+}
+
+/// How much of each budget has been used so far.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Usage {
+    pub steps: u64,
+    pub depth: usize,
+    pub output: usize,
+    pub started: Instant,
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Self {
+            steps: 0,
+            depth: 0,
+            output: 0,
+            started: Instant::now(),
+        }
+    }
+}
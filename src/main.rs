@@ -1,31 +1,337 @@
 //! The Rust implementation of slpy.
-use clap::Parser;
-use slpy_rust::{dump, repl, run};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use clap::{Args, CommandFactory, ErrorKind, Parser, Subcommand, ValueEnum};
+use slpy_rust::{
+    check, dump, dump_tokens, pretty_print, repl, run, DumpFormat, Error, Kind, Limits,
+    TokensFormat, DEFAULT_MAX_DEPTH,
+};
+
+/// The program ran, but raised an error it didn't catch or went over a limit.
+const EXIT_RUNTIME: i32 = 1;
+/// The program couldn't be tokenized or parsed.
+const EXIT_SYNTAX: i32 = 65;
+/// The program couldn't be read.
+const EXIT_NO_INPUT: i32 = 66;
+/// The subcommand isn't supported yet.
+const EXIT_UNSUPPORTED: i32 = 69;
 
 /// The slpy programming language.
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
-struct Args {
-    /// Name of the person to greet
-    #[clap(value_parser)]
-    file: Option<String>,
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    trailing_var_arg = true,
+    after_help = "With no subcommand, runs FILE like `slpy run`, or starts the REPL if there's no \
+                  FILE either.\n\nExit codes: 0 on success, 1 for an error while running, 2 for \
+                  bad arguments, 65 for a syntax error, 66 if the program can't be read and 69 for \
+                  a subcommand that isn't supported yet."
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
 
-    #[clap(short, long)]
-    dump: bool,
+    #[clap(flatten)]
+    run: RunArgs,
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
-    // TODO: debugging, pretty-printing, better argument handling
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a program
+    #[clap(trailing_var_arg = true)]
+    Run(RunArgs),
 
-    if let Some(file) = args.file {
-        if args.dump {
-            dump(file)?;
-        } else {
-            run(file)?;
+    /// Check programs for syntax errors without running them
+    Check {
+        /// The programs to check, or `-` for stdin
+        #[clap(value_parser, required = true)]
+        files: Vec<String>,
+    },
+
+    /// Print a program's syntax tree, control flow or tokens instead of running it
+    Dump {
+        /// Print the AST as an indented `tree`, as `json` or `sexpr` for other tools, or as Graphviz
+        /// `dot`; or print the control flow as `cfg`
+        #[clap(short, long, value_enum, value_name = "FORMAT", default_value = "tree")]
+        format: Dump,
+
+        /// Print the tokens instead, carrying on past errors: as `text` (the default), or as `json`
+        #[clap(
+            long,
+            value_enum,
+            value_name = "FORMAT",
+            min_values = 0,
+            require_equals = true,
+            default_missing_value = "text",
+            conflicts_with = "format"
+        )]
+        tokens: Option<Tokens>,
+
+        /// The program, or `-` for stdin
+        #[clap(value_parser)]
+        file: String,
+    },
+
+    /// Rewrite files in the canonical style, keeping their comments
+    Fmt {
+        /// Don't rewrite anything; list the files that would change, and fail if there are any
+        #[clap(long)]
+        check: bool,
+
+        /// The files to format, or `-` to format stdin to stdout
+        #[clap(value_parser, required = true)]
+        files: Vec<String>,
+    },
+
+    /// Start the interactive prompt
+    Repl,
+
+    /// Compile a program (not supported yet: the program is only checked, then this exits with 69)
+    Compile {
+        /// The program, or `-` for stdin
+        #[clap(value_parser)]
+        file: String,
+    },
+}
+
+/// Where the program to run comes from, and how to run it.
+#[derive(Args, Debug)]
+struct RunArgs {
+    /// Run CODE instead of a file, like `python -c`
+    #[clap(short = 'c', value_name = "CODE")]
+    code: Option<String>,
+
+    /// The program to run, or `-` for stdin, then the arguments for it
+    #[clap(value_parser, value_name = "FILE [ARGS]")]
+    args: Vec<String>,
+
+    /// Stop after evaluating this many statements and expressions
+    #[clap(long)]
+    max_steps: Option<u64>,
+
+    /// Stop when function calls nest deeper than this [default: 1000]
+    #[clap(long)]
+    max_depth: Option<usize>,
+
+    /// Stop when a value (e.g. a string) grows longer than this
+    #[clap(long)]
+    max_value_size: Option<usize>,
+
+    /// Stop after printing this many bytes
+    #[clap(long)]
+    max_output: Option<usize>,
+
+    /// Stop after running for this many seconds
+    #[clap(long)]
+    timeout: Option<f64>,
+
+    /// Leave out `assert` statements, like `python -O`
+    #[clap(short = 'O', long)]
+    no_asserts: bool,
+
+    /// Also look for imported modules in this directory; can be given more than once
+    #[clap(short = 'I', long = "module-path", value_name = "DIR")]
+    module_path: Vec<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Dump {
+    Tree,
+    Json,
+    Sexpr,
+    Dot,
+    Cfg,
+}
+
+impl From<Dump> for DumpFormat {
+    fn from(dump: Dump) -> Self {
+        match dump {
+            Dump::Tree => Self::Tree,
+            Dump::Json => Self::Json,
+            Dump::Sexpr => Self::Sexpr,
+            Dump::Dot => Self::Dot,
+            Dump::Cfg => Self::Cfg,
         }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Tokens {
+    Text,
+    Json,
+}
+
+impl From<Tokens> for TokensFormat {
+    fn from(tokens: Tokens) -> Self {
+        match tokens {
+            Tokens::Text => Self::Text,
+            Tokens::Json => Self::Json,
+        }
+    }
+}
+
+/// Read the program in `file`, or stdin if it's `-`, with the name errors in it are reported
+/// under. Exits if it can't be read.
+fn read(file: &str) -> (String, String) {
+    let (source, name) = if file == "-" {
+        let mut source = String::new();
+        let read = std::io::stdin().read_to_string(&mut source);
+        (read.map(|_| source), "<stdin>".to_string())
+    } else {
+        (fs::read_to_string(file), file.to_string())
+    };
+    match source {
+        Ok(source) => (source, name),
+        Err(e) => {
+            eprintln!("Error: cannot read {}: {}", name, e);
+            exit(EXIT_NO_INPUT);
+        }
+    }
+}
+
+/// Report `e`, an error in the program `name`, and exit with the code for its kind, or with the
+/// status the program passed to `exit`.
+fn fail(mut e: Error, name: &str) -> ! {
+    if let Kind::Exit(status) = e.kind {
+        exit(status);
+    }
+    if e.file.is_none() {
+        e.file = Some(Arc::new(name.to_string()));
+    }
+    eprint!("{}", e.report());
+    exit(if e.kind.is_syntax() {
+        EXIT_SYNTAX
     } else {
-        repl()?;
+        EXIT_RUNTIME
+    });
+}
+
+/// Run the program `args` describe, or start the REPL if there isn't one.
+fn run_program(args: RunArgs, repl_if_none: bool) -> anyhow::Result<()> {
+    // with -c, every argument is the program's
+    let mut argv = args.args;
+    let (source, name) = if let Some(code) = args.code {
+        (code, "<string>".to_string())
+    } else if !argv.is_empty() {
+        read(&argv.remove(0))
+    } else if repl_if_none {
+        repl().unwrap_or_else(|e| fail(e, "<stdin>"));
+        return Ok(());
+    } else {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "no program to run: give a FILE, `-` for stdin, or -c CODE",
+            )
+            .exit()
+    };
+    let limits = Limits {
+        max_steps: args.max_steps,
+        max_depth: Some(args.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)),
+        max_value_size: args.max_value_size,
+        max_output: args.max_output,
+        max_time: args.timeout.map(Duration::try_from_secs_f64).transpose()?,
+    };
+    if let Err(e) = run(
+        &source,
+        &name,
+        argv,
+        limits,
+        args.no_asserts,
+        args.module_path,
+    ) {
+        fail(e, &name);
+    }
+    Ok(())
+}
+
+/// Format `files` in place, or only check that they're formatted.
+fn fmt(files: &[String], check: bool) -> anyhow::Result<()> {
+    let mut unformatted = false;
+    for file in files {
+        let (source, name) = read(file);
+        let formatted = pretty_print(&source).unwrap_or_else(|e| fail(e, &name));
+        if file == "-" && !check {
+            print(&formatted)?;
+            continue;
+        }
+        if formatted == source {
+            continue;
+        }
+        if check {
+            print(&format!("would reformat {}\n", name))?;
+            unformatted = true;
+        } else {
+            fs::write(file, formatted).with_context(|| format!("cannot write {}", name))?;
+        }
+    }
+    if unformatted {
+        exit(EXIT_RUNTIME);
+    }
+    Ok(())
+}
+
+/// Write `text` to stdout. A reader that's gone, like `head` once it has read enough, isn't an
+/// error: there's just no one left to write to.
+fn print(text: &str) -> anyhow::Result<()> {
+    let mut out = std::io::stdout().lock();
+    match out.write_all(text.as_bytes()).and_then(|()| out.flush()) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.context("cannot write to stdout"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        None => run_program(cli.run, true)?,
+        Some(Command::Run(args)) => run_program(args, false)?,
+        Some(Command::Check { files }) => {
+            for file in files {
+                let (source, name) = read(&file);
+                if let Err(e) = check(&source) {
+                    fail(e, &name);
+                }
+            }
+        }
+        Some(Command::Dump {
+            format,
+            tokens,
+            file,
+        }) => {
+            let (source, name) = read(&file);
+            if let Some(tokens) = tokens {
+                // the errors have been printed with the tokens
+                if dump_tokens(&source, tokens.into()).is_err() {
+                    exit(EXIT_SYNTAX);
+                }
+            } else if let Err(e) = dump(&source, format.into()) {
+                fail(e, &name);
+            }
+        }
+        Some(Command::Fmt { check, files }) => fmt(&files, check)?,
+        Some(Command::Repl) => repl().unwrap_or_else(|e| fail(e, "<stdin>")),
+        Some(Command::Compile { file }) => {
+            let (source, name) = read(&file);
+            if let Err(e) = check(&source) {
+                fail(e, &name);
+            }
+            eprintln!(
+                "Error: compiling isn't supported yet; {} has no syntax errors",
+                name
+            );
+            exit(EXIT_UNSUPPORTED);
+        }
     }
 
     Ok(())
@@ -0,0 +1,97 @@
+//! Modules: slpy files run by `import`, each with its own globals.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::env::Env;
+use crate::object::SlpyObject;
+
+/// An imported file's namespace, compared by identity.
+pub struct Module {
+    pub(crate) name: String,
+    pub(crate) globals: Env,
+}
+
+impl Module {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            globals: Env::default(),
+        }
+    }
+
+    /// Look up one of the module's globals.
+    pub(crate) fn get(&self, name: &str) -> Option<SlpyObject> {
+        self.globals.get(name)
+    }
+
+    pub(crate) fn set(&self, name: String, val: SlpyObject) {
+        self.globals.set(name, val);
+    }
+}
+
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Module {}
+
+impl std::fmt::Debug for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module '{}'>", self.name)
+    }
+}
+
+/// The modules a program has imported, and where to look for more.
+#[derive(Default)]
+pub(crate) struct Modules {
+    /// Directories to look in after the importing file's own, in order.
+    pub(crate) path: Vec<PathBuf>,
+    /// Whether to leave `assert` statements out of the modules, like the main program.
+    pub(crate) strip_asserts: bool,
+    /// The modules that finished running, by canonical path, so each file only runs once.
+    loaded: HashMap<PathBuf, Rc<Module>>,
+    /// The modules still running, outermost first, to catch circular imports.
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl Modules {
+    /// The file for module `name`, imported by a file in `dir`.
+    pub(crate) fn find(&self, name: &str, dir: &Path) -> Option<PathBuf> {
+        std::iter::once(dir)
+            .chain(self.path.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(format!("{}.slpy", name)))
+            .find(|path| path.is_file())
+    }
+
+    pub(crate) fn get(&self, key: &Path) -> Option<Rc<Module>> {
+        self.loaded.get(key).cloned()
+    }
+
+    /// If the module at `key` is still running, the chain of imports that leads back to it, e.g.
+    /// `a -> b -> a`.
+    pub(crate) fn cycle(&self, key: &Path, name: &str) -> Option<String> {
+        let start = self.loading.iter().position(|(path, _)| path == key)?;
+        let names: Vec<_> = self.loading[start..]
+            .iter()
+            .map(|(_, name)| name.as_str())
+            .chain([name])
+            .collect();
+        Some(names.join(" -> "))
+    }
+
+    /// Note that the module at `key` has started running.
+    pub(crate) fn start(&mut self, key: PathBuf, name: String) {
+        self.loading.push((key, name));
+    }
+
+    /// Note that the innermost running module has finished, keeping it if it ran successfully.
+    pub(crate) fn finish(&mut self, module: Option<Rc<Module>>) {
+        let (key, _) = self.loading.pop().expect("a module is running");
+        if let Some(module) = module {
+            self.loaded.insert(key, module);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Rust functions callable from slpy.
+use std::fmt::Display;
+
+use crate::object::{FromSlpy, IntoSlpy, SlpyObject};
+use crate::signature::Signature;
+
+/// Why a native function call failed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum NativeError {
+    /// The argument at this (zero-based) position couldn't be converted.
+    Argument(usize, String),
+    /// The function itself returned an error.
+    Raised(String),
+}
+
+/// A value a native function can return: anything convertible to an `SlpyObject`, or a `Result`
+/// of one whose error becomes an slpy error at the call site.
+pub trait NativeReturn {
+    /// # Errors
+    /// If the function returned an error.
+    fn into_native_result(self) -> Result<SlpyObject, NativeError>;
+}
+
+macro_rules! infallible_return {
+    ($($ty:ty),*) => {
+        $(
+            impl NativeReturn for $ty {
+                fn into_native_result(self) -> Result<SlpyObject, NativeError> {
+                    Ok(self.into_slpy())
+                }
+            }
+        )*
+    };
+}
+
+infallible_return!(SlpyObject, (), bool, i32, String, &str);
+
+impl<T: IntoSlpy> NativeReturn for Option<T> {
+    fn into_native_result(self) -> Result<SlpyObject, NativeError> {
+        Ok(self.into_slpy())
+    }
+}
+
+impl<T: IntoSlpy, E: Display> NativeReturn for Result<T, E> {
+    fn into_native_result(self) -> Result<SlpyObject, NativeError> {
+        self.map(IntoSlpy::into_slpy)
+            .map_err(|e| NativeError::Raised(e.to_string()))
+    }
+}
+
+/// A Rust closure that can be registered as an slpy function.
+///
+/// Implemented for closures of up to four arguments whose arguments implement `FromSlpy` and whose
+/// return type implements `NativeReturn`. `Args` is the tuple of argument types; it only exists to
+/// tell the implementations apart.
+pub trait NativeFn<Args> {
+    /// The number of arguments the function takes.
+    const ARITY: usize;
+
+    /// Call the function with exactly `ARITY` arguments.
+    ///
+    /// # Errors
+    /// If an argument can't be converted or the function returns an error.
+    fn call(&mut self, args: Vec<SlpyObject>) -> Result<SlpyObject, NativeError>;
+}
+
+macro_rules! native_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> NativeFn<($($arg,)*)> for F
+        where
+            F: FnMut($($arg),*) -> R,
+            R: NativeReturn,
+            $($arg: FromSlpy),*
+        {
+            const ARITY: usize = <[&str]>::len(&[$(stringify!($arg)),*]);
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&mut self, args: Vec<SlpyObject>) -> Result<SlpyObject, NativeError> {
+                let mut args = args.into_iter().enumerate();
+                $(
+                    let (i, arg) = args.next().expect("arity has been checked");
+                    let $arg = $arg::from_slpy(arg).map_err(|e| NativeError::Argument(i, e))?;
+                )*
+                self($($arg),*).into_native_result()
+            }
+        }
+    };
+}
+
+native_fn!();
+native_fn!(A);
+native_fn!(A, B);
+native_fn!(A, B, C);
+native_fn!(A, B, C, D);
+
+/// A registered native function.
+pub struct Native<'io> {
+    pub arity: usize,
+    /// The parameters it declares, if it takes more than positional arguments.
+    pub sig: Option<Signature>,
+    pub func: Box<dyn FnMut(Vec<SlpyObject>) -> Result<SlpyObject, NativeError> + 'io>,
+}
+
+impl<'io> Native<'io> {
+    pub fn new<Args, F: NativeFn<Args> + 'io>(mut f: F) -> Self {
+        Self {
+            arity: F::ARITY,
+            sig: None,
+            func: Box::new(move |args| f.call(args)),
+        }
+    }
+
+    /// # Panics
+    /// If `f` doesn't take one argument for each of the signature's slots.
+    pub fn with_signature<Args, F: NativeFn<Args> + 'io>(sig: Signature, f: F) -> Self {
+        assert_eq!(
+            F::ARITY,
+            sig.slots(),
+            "a native function must take one argument per parameter"
+        );
+        Self {
+            sig: Some(sig),
+            ..Self::new(f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arity() {
+        assert_eq!(Native::new(|| 1).arity, 0);
+        assert_eq!(Native::new(|a: i32, b: i32| a + b).arity, 2);
+        assert_eq!(Native::new(|_: String, _: i32, _: SlpyObject| ()).arity, 3);
+    }
+
+    #[test]
+    fn converts_arguments() {
+        let mut native = Native::new(|s: String, n: i32| s.repeat(n as usize));
+        assert_eq!(
+            (native.func)(vec![SlpyObject::Str("ab".to_string()), SlpyObject::Int(2)]),
+            Ok(SlpyObject::Str("abab".to_string()))
+        );
+        assert_eq!(
+            (native.func)(vec![SlpyObject::Int(2), SlpyObject::Int(2)]),
+            Err(NativeError::Argument(
+                0,
+                "expected str, got int".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn raises() {
+        let mut native = Native::new(|n: i32| if n > 0 { Ok(n) } else { Err("not positive") });
+        assert_eq!(
+            (native.func)(vec![SlpyObject::Int(0)]),
+            Err(NativeError::Raised("not positive".to_string()))
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! Runtime values and conversions between them and Rust types.
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::ast::Function;
+use crate::builtin::Builtin;
+use crate::class::{Class, Instance, Method, Super};
+use crate::exception::{ExcType, Exception};
+use crate::iter::{Generator, SeqIter};
+use crate::module::Module;
+
+/// A value a program computes with.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SlpyObject {
+    None,
+    Bool(bool),
+    Int(i32),
+    Str(String),
+    Tuple(Rc<[SlpyObject]>),
+    /// A list, shared between everything that refers to it.
+    List(Rc<RefCell<Vec<SlpyObject>>>),
+    /// A set, shared between everything that refers to it, with its items in insertion order.
+    Set(Rc<RefCell<Vec<SlpyObject>>>),
+    /// A dict, shared between everything that refers to it, with its entries in insertion order.
+    Dict(Rc<RefCell<Vec<(SlpyObject, SlpyObject)>>>),
+    /// A function defined in slpy, compared by identity.
+    Function(Rc<Function>),
+    /// A function registered from Rust, by name.
+    Native(String),
+    /// A function built into the interpreter.
+    Builtin(Builtin),
+    /// A class defined in slpy.
+    Class(Rc<Class>),
+    /// An instance of a class defined in slpy, shared between everything that refers to it.
+    Instance(Rc<Instance>),
+    /// A method bound to the object it was looked up on.
+    Method(Rc<Method>),
+    /// The result of `super()`.
+    Super(Rc<Super>),
+    /// An iterator over a container's items.
+    Iter(Rc<SeqIter>),
+    /// What calling a generator function returns.
+    Generator(Rc<Generator>),
+    /// An imported module.
+    Module(Rc<Module>),
+    /// An exception class.
+    ExcType(ExcType),
+    /// An exception instance.
+    Exception(Exception),
+}
+
+impl SlpyObject {
+    /// The name of the value's type, as a program would see it.
+    #[must_use]
+    pub fn type_name(&self) -> String {
+        match self {
+            Self::None => "NoneType",
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Str(_) => "str",
+            Self::Tuple(_) => "tuple",
+            Self::List(_) => "list",
+            Self::Set(_) => "set",
+            Self::Dict(_) => "dict",
+            Self::Function(_) => "function",
+            Self::Native(_) | Self::Builtin(_) => "builtin_function_or_method",
+            Self::Class(_) | Self::ExcType(_) => "type",
+            Self::Instance(obj) => return obj.class.name.clone(),
+            Self::Method(_) => "method",
+            Self::Super(_) => "super",
+            Self::Iter(_) => "iterator",
+            Self::Generator(_) => "generator",
+            Self::Module(_) => "module",
+            Self::Exception(e) => e.ty.name(),
+        }
+        .to_string()
+    }
+
+    /// Whether the value counts as true in a condition.
+    #[must_use]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::None => false,
+            Self::Bool(b) => *b,
+            Self::Int(n) => *n != 0,
+            Self::Str(s) => !s.is_empty(),
+            Self::Tuple(items) => !items.is_empty(),
+            Self::List(items) | Self::Set(items) => !items.borrow().is_empty(),
+            Self::Dict(entries) => !entries.borrow().is_empty(),
+            Self::Function(_)
+            | Self::Native(_)
+            | Self::Builtin(_)
+            | Self::Class(_)
+            | Self::Instance(_)
+            | Self::Method(_)
+            | Self::Super(_)
+            | Self::Iter(_)
+            | Self::Generator(_)
+            | Self::Module(_)
+            | Self::ExcType(_)
+            | Self::Exception(_) => true,
+        }
+    }
+
+    pub(crate) fn list_of(items: Vec<Self>) -> Self {
+        Self::List(Rc::new(RefCell::new(items)))
+    }
+
+    /// The part of the value that stops it being a dict key or set item, if any: a mutable
+    /// container, which could change after being put in, perhaps inside a tuple.
+    pub(crate) fn unhashable(&self) -> Option<&Self> {
+        match self {
+            Self::List(_) | Self::Set(_) | Self::Dict(_) => Some(self),
+            Self::Tuple(items) => items.iter().find_map(Self::unhashable),
+            _ => None,
+        }
+    }
+
+    /// A set of `items`, keeping the first of any duplicates. The items must be hashable.
+    pub(crate) fn set_of(items: Vec<Self>) -> Self {
+        let mut set = Vec::new();
+        for item in items {
+            if !set.contains(&item) {
+                set.push(item);
+            }
+        }
+        Self::Set(Rc::new(RefCell::new(set)))
+    }
+
+    /// A dict of `entries`, where a repeated key takes the later value but keeps its first place.
+    /// The keys must be hashable.
+    pub(crate) fn dict_of(entries: Vec<(Self, Self)>) -> Self {
+        let mut dict: Vec<(Self, Self)> = Vec::new();
+        for (key, val) in entries {
+            match dict.iter_mut().find(|(k, _)| *k == key) {
+                Some(entry) => entry.1 = val,
+                None => dict.push((key, val)),
+            }
+        }
+        Self::Dict(Rc::new(RefCell::new(dict)))
+    }
+
+    /// Look up the attribute `name`, binding methods to the object they're looked up on.
+    pub(crate) fn get_attr(&self, name: &str) -> Option<Self> {
+        match self {
+            Self::Instance(obj) => obj
+                .get(name)
+                .or_else(|| Some(Method::bind(self, obj.class.lookup(name)?))),
+            Self::Class(class) => class.lookup(name),
+            Self::Super(sup) => sup.get(name),
+            Self::Module(module) => module.get(name),
+            _ => None,
+        }
+    }
+
+    /// Set the attribute `name`, returning false if the value can't have attributes.
+    pub(crate) fn set_attr(&self, name: String, val: Self) -> bool {
+        match self {
+            Self::Instance(obj) => obj.set(name, val),
+            Self::Class(class) => class.set(name, val),
+            Self::Module(module) => module.set(name, val),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl SlpyObject {
+    /// The value as it appears inside a container: like `Display`, but with strings quoted.
+    #[must_use]
+    pub fn repr(&self) -> String {
+        let mut out = String::new();
+        self.write_repr(&mut out, &mut Vec::new());
+        out
+    }
+
+    /// Write the value's `repr` to `out`. `open` holds the containers being written, so one that
+    /// contains itself is written as `[...]` or `{...}` inside itself instead of forever.
+    fn write_repr(&self, out: &mut String, open: &mut Vec<*const ()>) {
+        match self {
+            Self::Str(s) if s.contains('\'') => *out += &format!("\"{}\"", s),
+            Self::Str(s) => *out += &format!("'{}'", s),
+            _ => self.write(out, open),
+        }
+    }
+
+    /// Write the value as `Display` does to `out`, with `open` as for `write_repr`.
+    fn write(&self, out: &mut String, open: &mut Vec<*const ()>) {
+        let (id, brackets) = match self {
+            Self::Tuple(items) if items.len() == 1 => {
+                out.push('(');
+                items[0].write_repr(out, open);
+                *out += ",)";
+                return;
+            }
+            Self::Tuple(items) => (Rc::as_ptr(items).cast::<()>(), ("(", ")")),
+            Self::List(items) => (Rc::as_ptr(items).cast::<()>(), ("[", "]")),
+            Self::Set(items) if items.borrow().is_empty() => {
+                *out += "set()";
+                return;
+            }
+            Self::Set(items) => (Rc::as_ptr(items).cast::<()>(), ("{", "}")),
+            Self::Dict(entries) => (Rc::as_ptr(entries).cast::<()>(), ("{", "}")),
+            _ => {
+                *out += &self.to_string();
+                return;
+            }
+        };
+        *out += brackets.0;
+        if open.contains(&id) {
+            *out += "...";
+        } else {
+            open.push(id);
+            match self {
+                Self::Tuple(items) => Self::write_items(items, out, open),
+                Self::List(items) | Self::Set(items) => {
+                    Self::write_items(&items.borrow(), out, open)
+                }
+                Self::Dict(entries) => {
+                    for (i, (key, val)) in entries.borrow().iter().enumerate() {
+                        if i > 0 {
+                            *out += ", ";
+                        }
+                        key.write_repr(out, open);
+                        *out += ": ";
+                        val.write_repr(out, open);
+                    }
+                }
+                _ => unreachable!("only containers get this far"),
+            }
+            open.pop();
+        }
+        *out += brackets.1;
+    }
+
+    fn write_items(items: &[Self], out: &mut String, open: &mut Vec<*const ()>) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                *out += ", ";
+            }
+            item.write_repr(out, open);
+        }
+    }
+}
+
+impl Display for SlpyObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Bool(true) => write!(f, "True"),
+            Self::Bool(false) => write!(f, "False"),
+            Self::Int(n) => write!(f, "{}", n),
+            Self::Str(s) => write!(f, "{}", s),
+            Self::Tuple(_) | Self::List(_) | Self::Set(_) | Self::Dict(_) => {
+                let mut out = String::new();
+                self.write(&mut out, &mut Vec::new());
+                write!(f, "{}", out)
+            }
+            Self::Function(func) => write!(f, "<function {}>", func.name),
+            Self::Native(name) => write!(f, "<built-in function {}>", name),
+            Self::Builtin(builtin) => write!(f, "<built-in function {}>", builtin.name()),
+            Self::Class(class) => write!(f, "<class '{}'>", class.name),
+            Self::Instance(obj) => write!(f, "<{} object>", obj.class.name),
+            Self::Method(method) => write!(f, "<bound method {}>", method.func.name),
+            Self::Super(sup) => write!(f, "<super: <class '{}'>>", sup.class.name),
+            Self::Iter(_) => write!(f, "<iterator object>"),
+            Self::Generator(gen) => write!(f, "<generator object {}>", gen.func.name),
+            Self::Module(module) => write!(f, "<module '{}'>", module.name),
+            Self::ExcType(ty) => write!(f, "<class '{}'>", ty.name()),
+            Self::Exception(e) => write!(f, "{}", e.message),
+        }
+    }
+}
+
+/// Convert an `SlpyObject` into a Rust value.
+pub trait FromSlpy: Sized {
+    /// # Errors
+    /// If the value has the wrong type, with a message saying what was expected.
+    fn from_slpy(val: SlpyObject) -> Result<Self, String>;
+}
+
+/// Convert a Rust value into an `SlpyObject`.
+pub trait IntoSlpy {
+    fn into_slpy(self) -> SlpyObject;
+}
+
+fn expected(name: &str, got: &SlpyObject) -> String {
+    format!("expected {}, got {}", name, got.type_name())
+}
+
+impl FromSlpy for SlpyObject {
+    fn from_slpy(val: SlpyObject) -> Result<Self, String> {
+        Ok(val)
+    }
+}
+
+impl FromSlpy for i32 {
+    fn from_slpy(val: SlpyObject) -> Result<Self, String> {
+        match val {
+            SlpyObject::Int(n) => Ok(n),
+            _ => Err(expected("int", &val)),
+        }
+    }
+}
+
+impl FromSlpy for bool {
+    fn from_slpy(val: SlpyObject) -> Result<Self, String> {
+        match val {
+            SlpyObject::Bool(b) => Ok(b),
+            _ => Err(expected("bool", &val)),
+        }
+    }
+}
+
+impl FromSlpy for String {
+    fn from_slpy(val: SlpyObject) -> Result<Self, String> {
+        match val {
+            SlpyObject::Str(s) => Ok(s),
+            _ => Err(expected("str", &val)),
+        }
+    }
+}
+
+impl<T: FromSlpy> FromSlpy for Vec<T> {
+    fn from_slpy(val: SlpyObject) -> Result<Self, String> {
+        match val {
+            SlpyObject::Tuple(items) => items.iter().cloned().map(T::from_slpy).collect(),
+            _ => Err(expected("tuple", &val)),
+        }
+    }
+}
+
+impl<T: FromSlpy> FromSlpy for Option<T> {
+    fn from_slpy(val: SlpyObject) -> Result<Self, String> {
+        match val {
+            SlpyObject::None => Ok(None),
+            _ => T::from_slpy(val).map(Some),
+        }
+    }
+}
+
+impl IntoSlpy for SlpyObject {
+    fn into_slpy(self) -> SlpyObject {
+        self
+    }
+}
+
+impl IntoSlpy for () {
+    fn into_slpy(self) -> SlpyObject {
+        SlpyObject::None
+    }
+}
+
+impl IntoSlpy for bool {
+    fn into_slpy(self) -> SlpyObject {
+        SlpyObject::Bool(self)
+    }
+}
+
+impl IntoSlpy for i32 {
+    fn into_slpy(self) -> SlpyObject {
+        SlpyObject::Int(self)
+    }
+}
+
+impl IntoSlpy for String {
+    fn into_slpy(self) -> SlpyObject {
+        SlpyObject::Str(self)
+    }
+}
+
+impl IntoSlpy for &str {
+    fn into_slpy(self) -> SlpyObject {
+        SlpyObject::Str(self.to_string())
+    }
+}
+
+impl<T: IntoSlpy> IntoSlpy for Option<T> {
+    fn into_slpy(self) -> SlpyObject {
+        self.map_or(SlpyObject::None, IntoSlpy::into_slpy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(i32::from_slpy(7.into_slpy()), Ok(7));
+        assert_eq!(String::from_slpy("hi".into_slpy()), Ok("hi".to_string()));
+        assert_eq!(Option::<i32>::from_slpy(None::<i32>.into_slpy()), Ok(None));
+    }
+
+    #[test]
+    fn wrong_type() {
+        assert_eq!(
+            i32::from_slpy(SlpyObject::Str("x".to_string())),
+            Err("expected int, got str".to_string())
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(SlpyObject::None.to_string(), "None");
+        assert_eq!(SlpyObject::Int(-3).to_string(), "-3");
+        assert_eq!(SlpyObject::Bool(false).to_string(), "False");
+        let pair = SlpyObject::Tuple(Rc::from([SlpyObject::Int(1), "a".into_slpy()]));
+        assert_eq!(pair.to_string(), "(1, 'a')");
+        assert_eq!(
+            SlpyObject::Tuple(Rc::from([pair.clone()])).to_string(),
+            "((1, 'a'),)"
+        );
+        let dict = SlpyObject::Dict(Rc::new(RefCell::new(vec![("k".into_slpy(), pair)])));
+        assert_eq!(dict.to_string(), "{'k': (1, 'a')}");
+        let set = SlpyObject::set_of(vec![2.into_slpy(), 1.into_slpy(), 2.into_slpy()]);
+        assert_eq!(set.to_string(), "{2, 1}");
+        assert_eq!(SlpyObject::set_of(Vec::new()).to_string(), "set()");
+        assert_eq!(
+            SlpyObject::list_of(vec!["a".into_slpy()]).to_string(),
+            "['a']"
+        );
+        assert_eq!(SlpyObject::Str("a b".to_string()).to_string(), "a b");
+    }
+
+    #[test]
+    fn display_containing_itself() {
+        let list = SlpyObject::list_of(vec![1.into_slpy()]);
+        let SlpyObject::List(items) = &list else {
+            unreachable!()
+        };
+        items.borrow_mut().push(list.clone());
+        let dict = SlpyObject::dict_of(vec![("xs".into_slpy(), list.clone())]);
+        items.borrow_mut().push(dict.clone());
+        assert_eq!(list.to_string(), "[1, [...], {'xs': [...]}]");
+        assert_eq!(dict.to_string(), "{'xs': [1, [...], {...}]}");
+    }
+}
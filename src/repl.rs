@@ -1,30 +1,147 @@
 //! The REPL.
-use std::io::Write;
+use signal_hook::consts::SIGINT;
 
-use crate::ast::{Ast, Context, Expn, Stmt};
-use crate::tokenizer::Tokenizer;
-use crate::Result;
+use crate::ast::{Ast, Context, Stmt};
+use crate::error::{Error, Kind, Result};
+use crate::object::SlpyObject;
+use crate::tokenizer::{TokenKind, Tokenizer};
+
+mod editor;
+
+use editor::{Lines, Read};
 
 /// Run the REPL.
 ///
+/// A statement that goes on past its first line, like a block, is read line by line after a
+/// `... ` prompt, until it's complete. Errors in a statement, including Ctrl-C while it runs, are
+/// reported and the session carries on.
+///
+/// On a terminal, lines can be edited, Tab completes keywords and the names defined so far, and
+/// the history is kept in `~/.slpy_history` between sessions. Otherwise, lines are read as they
+/// come.
+///
 /// # Errors
-/// If the code is wrong.
+/// Only `Kind::Exit`, if a line calls `exit`; otherwise the REPL runs until the end of input.
 pub fn repl() -> Result<()> {
     let mut source = String::new();
     let mut ctx = Context::default();
+    let mut lines = Lines::new();
+    let cancel = ctx.cancel_handle();
+    // Ctrl-C stops the running line instead of the whole process
+    signal_hook::flag::register(SIGINT, cancel.flag()).expect("can register a SIGINT handler");
     loop {
-        // TODO: handle EOF
-        print!(">>> ");
-        std::io::stdout().flush().expect("can flush stdout");
-        if std::io::stdin().read_line(&mut source).is_ok() {
-            let tokens = Tokenizer::lex(source.as_str())?;
-            if let Ok(n) = Expn::parse_and_eval(tokens, &mut ctx) {
-                println!("{}", n);
-            } else {
-                let tokens = Tokenizer::lex(source.as_str())?;
-                Stmt::parse_and_eval(tokens, &mut ctx)?;
+        let prompt = if source.is_empty() { ">>> " } else { "... " };
+        match lines.read(prompt, &mut ctx) {
+            Read::Eof => {
+                lines.save();
+                println!();
+                return Ok(());
+            }
+            Read::Interrupted => {}
+            Read::Line(line) => {
+                source += &line;
+                source.push('\n');
+                if incomplete(&source) {
+                    continue;
+                }
+                // forget a Ctrl-C pressed at the prompt
+                cancel.reset();
+                ctx.reset_usage();
+                match eval_line(&source, &mut ctx) {
+                    Err(e) if matches!(e.kind, Kind::Exit(_)) => {
+                        lines.save();
+                        return Err(e);
+                    }
+                    Err(e) => eprint!("{}", e.report()),
+                    Ok(()) => {}
+                }
             }
         }
         source.clear();
     }
 }
+
+/// Whether `source` is the start of a statement that needs more lines: it has an open bracket or
+/// string, ends in a block's header, or opens a block that no blank line has closed yet.
+fn incomplete(source: &str) -> bool {
+    let mut tokens = match Tokenizer::lex(source) {
+        Ok(tokens) => tokens,
+        Err(e) => return e.kind == Kind::UnexpectedEof,
+    };
+    if tokens
+        .current()
+        .is_none_or(|t| t.kind == TokenKind::NewLine)
+    {
+        return false;
+    }
+    let opens_block = tokens.tokens().iter().any(|t| t.kind == TokenKind::Indent);
+    // a header with its block still to come, even if it's nested in another block
+    let last = tokens
+        .tokens()
+        .iter()
+        .rfind(|t| !matches!(t.kind, TokenKind::NewLine | TokenKind::Dedent));
+    if last.is_some_and(|t| t.kind == TokenKind::Colon) {
+        return true;
+    }
+    match Stmt::parse(&mut tokens) {
+        Err(e) => e.kind == Kind::UnexpectedEof,
+        Ok(_) => opens_block && !source.lines().last().is_some_and(|l| l.trim().is_empty()),
+    }
+}
+
+/// Evaluate a statement, printing its value if it's an expression.
+fn eval_line(source: &str, ctx: &mut Context) -> Result<()> {
+    let mut tokens = Tokenizer::lex(source)?;
+    if tokens
+        .current()
+        .is_none_or(|t| t.kind == TokenKind::NewLine)
+    {
+        return Ok(());
+    }
+    let stmt = Stmt::parse(&mut tokens)?;
+    if let Some(tkn) = tokens.current().filter(|t| t.kind != TokenKind::NewLine) {
+        return Err(Error::new(Kind::Parser, tkn.span));
+    }
+
+    match stmt.eval_interactive(ctx)? {
+        Some(SlpyObject::None) | None => {}
+        Some(n) => println!("{}", n),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete() {
+        assert!(!incomplete("x = 1\n"));
+        assert!(!incomplete("\n"));
+        assert!(!incomplete("print(x\n)\n"));
+        // errors are reported straight away
+        assert!(!incomplete("x = )\n"));
+    }
+
+    #[test]
+    fn open_brackets_and_strings() {
+        assert!(incomplete("print(1,\n"));
+        assert!(incomplete("xs = [1, {2,\n"));
+        assert!(incomplete("s = \"abc\n"));
+        assert!(!incomplete("s = \"abc\ndef\"\n"));
+        assert!(incomplete("s = \"\"\"abc\n"));
+        assert!(incomplete("s = \"\"\"abc\n\"\"\n"));
+        assert!(!incomplete("s = \"\"\"abc\n\"def\"\"\"\n"));
+    }
+
+    #[test]
+    fn blocks_end_at_a_blank_line() {
+        assert!(incomplete("if x:\n"));
+        assert!(incomplete("def f(n):\n    return n\n"));
+        assert!(incomplete("if x:\n    y = 1\nelse:\n"));
+        assert!(incomplete("def f(n):\n    if n:\n"));
+        assert!(incomplete("if x:\n    y = 1\nelse:\n    y = 2\n"));
+        assert!(!incomplete("if x:\n    y = 1\nelse:\n    y = 2\n\n"));
+        assert!(!incomplete("while x:\n    x -= 1\n    \n"));
+    }
+}
@@ -0,0 +1,223 @@
+//! Reading the REPL's input, with line editing, history and completion on a terminal.
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Editor, Helper};
+
+use crate::ast::Context;
+
+/// The words that aren't names but can still be completed: the keywords, and `print` and `input`,
+/// which the parser treats as keywords.
+const KEYWORDS: [&str; 26] = [
+    "as", "assert", "break", "class", "continue", "def", "elif", "else", "except", "finally",
+    "for", "from", "global", "if", "import", "in", "input", "lambda", "nonlocal", "pass", "print",
+    "raise", "return", "try", "while", "yield",
+];
+
+/// What reading a line got.
+pub(super) enum Read {
+    /// A line, without its line break.
+    Line(String),
+    /// Ctrl-C at the prompt, which drops the statement being typed.
+    Interrupted,
+    Eof,
+}
+
+/// Where the REPL reads lines from.
+pub(super) enum Lines {
+    /// A terminal, with line editing, completion, and history saved to `history`.
+    Editor {
+        editor: Box<Editor<Completion, FileHistory>>,
+        history: Option<PathBuf>,
+    },
+    /// Anything else, e.g. a pipe, read through the context's input.
+    Plain,
+}
+
+impl Lines {
+    /// Edit lines if stdin is a terminal, starting with the history of earlier sessions.
+    pub(super) fn new() -> Self {
+        if !std::io::stdin().is_terminal() {
+            return Self::Plain;
+        }
+        let config = Config::builder()
+            .completion_type(CompletionType::List)
+            .build();
+        let Ok(mut editor) = Editor::with_config(config) else {
+            return Self::Plain;
+        };
+        editor.set_helper(Some(Completion::default()));
+        let history = history_file();
+        if let Some(path) = &history {
+            // there's none the first time
+            let _ = editor.load_history(path);
+        }
+        Self::Editor {
+            editor: Box::new(editor),
+            history,
+        }
+    }
+
+    /// Read a line after `prompt`, completing the names bound in `ctx`.
+    pub(super) fn read(&mut self, prompt: &str, ctx: &mut Context) -> Read {
+        match self {
+            Self::Editor { editor, .. } => {
+                if let Some(completion) = editor.helper_mut() {
+                    completion.names = ctx.names();
+                }
+                match editor.readline(prompt) {
+                    Ok(line) => {
+                        if !line.trim().is_empty() {
+                            let _ = editor.add_history_entry(line.as_str());
+                        }
+                        Read::Line(line)
+                    }
+                    Err(ReadlineError::Interrupted) => Read::Interrupted,
+                    Err(_) => Read::Eof,
+                }
+            }
+            Self::Plain => {
+                print!("{}", prompt);
+                std::io::stdout().flush().expect("can flush stdout");
+                let mut line = String::new();
+                match ctx.read_line(&mut line) {
+                    Ok(0) => Read::Eof,
+                    Ok(_) => {
+                        let len = line.trim_end_matches(['\n', '\r']).len();
+                        line.truncate(len);
+                        Read::Line(line)
+                    }
+                    // Ctrl-C interrupted the read
+                    Err(_) => Read::Interrupted,
+                }
+            }
+        }
+    }
+
+    /// Save the history for the next session.
+    pub(super) fn save(&mut self) {
+        if let Self::Editor {
+            editor,
+            history: Some(path),
+        } = self
+        {
+            // losing the history isn't worth failing over
+            let _ = editor.save_history(path);
+        }
+    }
+}
+
+/// `~/.slpy_history`, if there's a home directory.
+fn history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(".slpy_history"))
+}
+
+/// Tab completion of keywords and of the names bound in the REPL's context.
+#[derive(Default)]
+pub(super) struct Completion {
+    /// The context's names, as of the prompt.
+    names: Vec<String>,
+}
+
+impl Completion {
+    /// Where the word before `pos` in `line` starts, and what it could be completed to. With no
+    /// word at the start of a line, Tab indents instead.
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| c.is_alphanumeric() || c == '_')
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &before[start..];
+        if word.is_empty() {
+            let indent = before.trim().is_empty();
+            return (
+                pos,
+                indent.then(|| "    ".to_string()).into_iter().collect(),
+            );
+        }
+        // attributes aren't known until the code runs
+        if before[..start].ends_with('.') {
+            return (start, Vec::new());
+        }
+        let mut words: Vec<String> = KEYWORDS
+            .iter()
+            .map(ToString::to_string)
+            .chain(self.names.iter().cloned())
+            .filter(|w| w.starts_with(word))
+            .collect();
+        words.sort();
+        words.dedup();
+        (start, words)
+    }
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(names: &[&str], line: &str) -> (usize, Vec<String>) {
+        let completion = Completion {
+            names: names.iter().map(ToString::to_string).collect(),
+        };
+        completion.candidates(line, line.len())
+    }
+
+    #[test]
+    fn keywords_and_names() {
+        assert_eq!(
+            complete(&["pi", "print_all"], "x = pr"),
+            (4, vec!["print".to_string(), "print_all".to_string()])
+        );
+        assert_eq!(complete(&[], "whi"), (0, vec!["while".to_string()]));
+        assert_eq!(
+            complete(&["count"], "f(cou"),
+            (2, vec!["count".to_string()])
+        );
+        assert_eq!(complete(&[], "zzz"), (0, Vec::new()));
+    }
+
+    #[test]
+    fn attributes_are_not_completed() {
+        assert_eq!(complete(&["self"], "self.se"), (5, Vec::new()));
+    }
+
+    #[test]
+    fn tab_indents_at_the_start_of_a_line() {
+        assert_eq!(complete(&[], "    "), (4, vec!["    ".to_string()]));
+        assert_eq!(complete(&[], "x = "), (4, Vec::new()));
+    }
+}
@@ -0,0 +1,297 @@
+//! Function parameters and binding call arguments to them.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::{Error, Kind, Result};
+use crate::object::{IntoSlpy, SlpyObject};
+use crate::Span;
+
+/// The parameters a function takes, following Python's rules:
+/// `def f(a, b=2, *args, c, d=4, **kwargs)`.
+///
+/// Arguments are bound to slots in the order the parameters are declared: the positional
+/// parameters, then `*args` as a tuple, then the keyword-only parameters, then `**kwargs` as a
+/// dict.
+///
+/// ```
+/// use slpy_rust::{Interpreter, Signature, SlpyObject};
+///
+/// let mut interp = Interpreter::with_io("".as_bytes(), Vec::new());
+/// let sig = Signature::new().param("n").param_with_default("step", 1);
+/// interp.register_fn_with_signature("advance", sig, |n: i32, step: i32| n + step);
+/// assert_eq!(interp.eval_expr("advance(1)").unwrap(), SlpyObject::Int(2));
+/// assert_eq!(interp.eval_expr("advance(1, step=5)").unwrap(), SlpyObject::Int(6));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Signature {
+    params: Vec<Param>,
+    var_args: Option<String>,
+    kw_only: Vec<Param>,
+    kw_args: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Param {
+    name: String,
+    default: Option<SlpyObject>,
+}
+
+impl Signature {
+    /// A signature with no parameters.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a required parameter, which is keyword-only if it comes after `var_args`.
+    #[must_use]
+    pub fn param(self, name: impl Into<String>) -> Self {
+        self.push(name.into(), None)
+    }
+
+    /// Add a parameter with a default value, which is keyword-only if it comes after `var_args`.
+    #[must_use]
+    pub fn param_with_default(self, name: impl Into<String>, val: impl IntoSlpy) -> Self {
+        self.push(name.into(), Some(val.into_slpy()))
+    }
+
+    pub(crate) fn push(mut self, name: String, default: Option<SlpyObject>) -> Self {
+        let param = Param { name, default };
+        if self.var_args.is_some() {
+            self.kw_only.push(param);
+        } else {
+            self.params.push(param);
+        }
+        self
+    }
+
+    /// Collect extra positional arguments into a tuple. Parameters added after this are
+    /// keyword-only.
+    #[must_use]
+    pub fn var_args(mut self, name: impl Into<String>) -> Self {
+        self.var_args = Some(name.into());
+        self
+    }
+
+    /// Make the parameters added after this keyword-only, like a bare `*`.
+    #[must_use]
+    pub fn keyword_only(self) -> Self {
+        self.var_args(String::new())
+    }
+
+    /// Collect extra keyword arguments into a dict.
+    #[must_use]
+    pub fn kw_args(mut self, name: impl Into<String>) -> Self {
+        self.kw_args = Some(name.into());
+        self
+    }
+
+    /// Whether extra positional arguments are collected, rather than just marking where the
+    /// keyword-only parameters start.
+    fn collects_args(&self) -> bool {
+        self.var_args.as_ref().is_some_and(|n| !n.is_empty())
+    }
+
+    /// The names of the slots arguments are bound to, in order.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        let var_args = self.var_args.as_deref().filter(|_| self.collects_args());
+        self.params
+            .iter()
+            .map(|p| p.name.as_str())
+            .chain(var_args)
+            .chain(self.kw_only.iter().map(|p| p.name.as_str()))
+            .chain(self.kw_args.as_deref())
+    }
+
+    /// The name of the first positional parameter, if there is one.
+    pub(crate) fn first_param(&self) -> Option<&str> {
+        self.params.first().map(|p| p.name.as_str())
+    }
+
+    /// The number of slots arguments are bound to.
+    pub(crate) fn slots(&self) -> usize {
+        self.names().count()
+    }
+
+    /// Bind a call's arguments to the slots of the function `name`, reporting errors at `span`.
+    pub(crate) fn bind(
+        &self,
+        name: &str,
+        args: Vec<SlpyObject>,
+        kwargs: Vec<(String, SlpyObject)>,
+        span: Span,
+    ) -> Result<Vec<SlpyObject>> {
+        let err = |kind| Error::new(kind, span);
+        let named = || self.params.iter().chain(&self.kw_only);
+
+        let n = self.params.len();
+        let mut slots = vec![None; n + self.kw_only.len()];
+        let mut args = args.into_iter();
+        for (slot, arg) in slots.iter_mut().zip(args.by_ref().take(n)) {
+            *slot = Some(arg);
+        }
+        let extra = args.collect::<Vec<_>>();
+        if !extra.is_empty() && !self.collects_args() {
+            return Err(err(Kind::Arity {
+                name: name.to_string(),
+                expected: n,
+                got: n + extra.len(),
+            }));
+        }
+
+        let mut extra_kw = Vec::new();
+        for (key, val) in kwargs {
+            match named().position(|p| p.name == key) {
+                Some(i) if slots[i].is_some() => {
+                    return Err(err(Kind::DuplicateArgument {
+                        name: name.to_string(),
+                        param: key,
+                    }))
+                }
+                Some(i) => slots[i] = Some(val),
+                None if extra_kw
+                    .iter()
+                    .any(|(k, _)| k == &SlpyObject::Str(key.clone())) =>
+                {
+                    return Err(err(Kind::DuplicateArgument {
+                        name: name.to_string(),
+                        param: key,
+                    }))
+                }
+                None if self.kw_args.is_some() => extra_kw.push((SlpyObject::Str(key), val)),
+                None => {
+                    return Err(err(Kind::UnexpectedKeyword {
+                        name: name.to_string(),
+                        param: key,
+                    }))
+                }
+            }
+        }
+
+        let mut bound = slots
+            .into_iter()
+            .zip(named())
+            .map(|(slot, param)| {
+                slot.or_else(|| param.default.clone()).ok_or_else(|| {
+                    err(Kind::MissingArgument {
+                        name: name.to_string(),
+                        param: param.name.clone(),
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let kw_only = bound.split_off(n);
+        if self.collects_args() {
+            bound.push(SlpyObject::Tuple(Rc::from(extra)));
+        }
+        bound.extend(kw_only);
+        if self.kw_args.is_some() {
+            bound.push(SlpyObject::Dict(Rc::new(RefCell::new(extra_kw))));
+        }
+        Ok(bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Loc;
+
+    fn bind(
+        sig: &Signature,
+        args: Vec<SlpyObject>,
+        kwargs: Vec<(&str, SlpyObject)>,
+    ) -> Result<Vec<SlpyObject>> {
+        let kwargs = kwargs
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let loc = Loc { row: 1, col: 1 };
+        sig.bind(
+            "f",
+            args,
+            kwargs,
+            Span {
+                start: loc,
+                end: loc,
+            },
+        )
+    }
+
+    #[test]
+    fn slots() {
+        let sig = Signature::new()
+            .param("a")
+            .var_args("args")
+            .param("b")
+            .kw_args("kwargs");
+        assert_eq!(
+            sig.names().collect::<Vec<_>>(),
+            ["a", "args", "b", "kwargs"]
+        );
+        let sig = Signature::new().param("a").keyword_only().param("b");
+        assert_eq!(sig.names().collect::<Vec<_>>(), ["a", "b"]);
+    }
+
+    #[test]
+    fn binds_in_slot_order() {
+        let sig = Signature::new()
+            .param("a")
+            .param_with_default("b", 2)
+            .var_args("args")
+            .param("c")
+            .kw_args("kwargs");
+        let bound = bind(
+            &sig,
+            vec![SlpyObject::Int(1), SlpyObject::Int(5), SlpyObject::Int(6)],
+            vec![("z", SlpyObject::Int(0)), ("c", SlpyObject::Int(3))],
+        )
+        .unwrap();
+        let strs = bound.iter().map(SlpyObject::repr).collect::<Vec<_>>();
+        assert_eq!(strs, ["1", "5", "(6,)", "3", "{'z': 0}"]);
+
+        let bound = bind(
+            &sig,
+            vec![SlpyObject::Int(1)],
+            vec![("c", SlpyObject::None)],
+        )
+        .unwrap();
+        let strs = bound.iter().map(SlpyObject::repr).collect::<Vec<_>>();
+        assert_eq!(strs, ["1", "2", "()", "None", "{}"]);
+    }
+
+    #[test]
+    fn errors() {
+        let sig = Signature::new().param("a").keyword_only().param("b");
+        let kind = |args, kwargs| bind(&sig, args, kwargs).unwrap_err().kind;
+        assert_eq!(
+            kind(vec![SlpyObject::Int(1), SlpyObject::Int(2)], vec![]),
+            Kind::Arity {
+                name: "f".to_string(),
+                expected: 1,
+                got: 2
+            }
+        );
+        assert_eq!(
+            kind(vec![SlpyObject::Int(1)], vec![]),
+            Kind::MissingArgument {
+                name: "f".to_string(),
+                param: "b".to_string()
+            }
+        );
+        assert_eq!(
+            kind(vec![SlpyObject::Int(1)], vec![("a", SlpyObject::Int(1))]),
+            Kind::DuplicateArgument {
+                name: "f".to_string(),
+                param: "a".to_string()
+            }
+        );
+        assert_eq!(
+            kind(vec![], vec![("c", SlpyObject::Int(1))]),
+            Kind::UnexpectedKeyword {
+                name: "f".to_string(),
+                param: "c".to_string()
+            }
+        );
+    }
+}
@@ -1,43 +1,124 @@
 //! The lexer.
 
+use std::collections::VecDeque;
 use std::fmt::Display;
+use std::ops::Range;
 
+use crate::ast::Bindings;
 use crate::error::{Error, Kind, Result};
 use crate::{Loc, Span};
 
+mod listing;
+
+pub use listing::{list, list_json};
+
+/// A token lexed from a program, and where it is.
 #[derive(PartialEq, Eq, Debug)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
+    /// The whitespace and comments between the previous token and this one, if lexed with
+    /// `lex_lossless`.
+    pub trivia: Vec<Trivia>,
+}
+
+/// Source text that isn't part of any token.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    /// The text, byte for byte.
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TriviaKind {
+    /// Spaces and tabs.
+    Whitespace,
+    /// A comment, from the `#` up to the end of the line.
+    Comment,
+    /// The end of a line with no `NewLine` token, because it only held whitespace or a comment.
+    NewLine,
+}
+
+/// A comment, which the parser skips but a pretty printer keeps.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Comment {
+    /// The comment's text, starting with the `#`.
+    pub text: String,
+    pub span: Span,
 }
 
+/// What a token is, with its value for names, numbers and strings.
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum TokenKind {
     NewLine,
+    Indent,
+    Dedent,
+    Colon,
     Comma,
+    Dot,
     Ident(String),
     Number(u32),
     Str(String),
     LParen,
     RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
     Op(Op),
 }
 
+impl TokenKind {
+    /// The variant's name, e.g. `Ident`, without its value.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::NewLine => "NewLine",
+            Self::Indent => "Indent",
+            Self::Dedent => "Dedent",
+            Self::Colon => "Colon",
+            Self::Comma => "Comma",
+            Self::Dot => "Dot",
+            Self::Ident(_) => "Ident",
+            Self::Number(_) => "Number",
+            Self::Str(_) => "Str",
+            Self::LParen => "LParen",
+            Self::RParen => "RParen",
+            Self::LBracket => "LBracket",
+            Self::RBracket => "RBracket",
+            Self::LBrace => "LBrace",
+            Self::RBrace => "RBrace",
+            Self::Op(_) => "Op",
+        }
+    }
+}
+
 impl Display for TokenKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::NewLine => write!(f, "newline"),
+            Self::Indent => write!(f, "indent"),
+            Self::Dedent => write!(f, "dedent"),
+            Self::Colon => write!(f, "`:`"),
             Self::Comma => write!(f, "`,`"),
+            Self::Dot => write!(f, "`.`"),
             Self::Ident(s) => write!(f, "identifier `{}`", s),
             Self::Number(n) => write!(f, "numeric literal `{}`", n),
             Self::Str(s) => write!(f, "string literal \"{}\"", s),
             Self::LParen => write!(f, "`(`"),
             Self::RParen => write!(f, "`)`"),
+            Self::LBracket => write!(f, "`[`"),
+            Self::RBracket => write!(f, "`]`"),
+            Self::LBrace => write!(f, "`{{`"),
+            Self::RBrace => write!(f, "`}}`"),
             Self::Op(o) => write!(f, "`{}`", o),
         }
     }
 }
 
+/// An operator, including `=` and the augmented assignments like `+=`.
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum Op {
     Plus,
@@ -48,6 +129,19 @@ pub enum Op {
     Asgn,
     Expt,
     AddEq,
+    SubEq,
+    MulEq,
+    DivEq,
+    ModEq,
+    ExptEq,
+    /// `:=`
+    ColonEq,
+    Eq,
+    NotEq,
+    Lt,
+    LtE,
+    Gt,
+    GtE,
 }
 
 impl Display for Op {
@@ -61,42 +155,239 @@ impl Display for Op {
             Self::Asgn => "=",
             Self::Expt => "**",
             Self::AddEq => "+=",
+            Self::SubEq => "-=",
+            Self::MulEq => "*=",
+            Self::DivEq => "//=",
+            Self::ModEq => "%=",
+            Self::ExptEq => "**=",
+            Self::ColonEq => ":=",
+            Self::Eq => "==",
+            Self::NotEq => "!=",
+            Self::Lt => "<",
+            Self::LtE => "<=",
+            Self::Gt => ">",
+            Self::GtE => ">=",
         };
         write!(f, "{}", message)
     }
 }
 
+impl Op {
+    /// The augmented assignment made by following the operator with `=`, like `+=` for `+`.
+    const fn augmented(self) -> Option<Self> {
+        Some(match self {
+            Self::Plus => Self::AddEq,
+            Self::Minus => Self::SubEq,
+            Self::Times => Self::MulEq,
+            Self::Div => Self::DivEq,
+            Self::Mod => Self::ModEq,
+            Self::Expt => Self::ExptEq,
+            _ => return None,
+        })
+    }
+}
+
+/// The tokens lexed from a program, which the parser reads through.
 #[derive(Default, PartialEq, Eq, Debug)]
 pub struct TokenStream {
     tokens: Vec<Token>,
     index: usize,
+    /// The lines of the source the tokens came from.
+    lines: Vec<String>,
+    /// The comments in the source, in order.
+    comments: Vec<Comment>,
+    /// The source, if lexed losslessly.
+    source: String,
+    /// Where each line of `source` starts, in bytes.
+    line_starts: Vec<usize>,
+    /// The whitespace and comments after the last token, if lexed losslessly.
+    trailing: Vec<Trivia>,
+    /// Whether to leave `assert` statements out of the parsed program.
+    strip_asserts: bool,
+    /// Whether the parser is inside a function body, where `return` is allowed.
+    pub(crate) in_function: bool,
+    /// Whether the parser is inside a loop, where `break` and `continue` are allowed.
+    pub(crate) in_loop: bool,
+    /// Whether the function body being parsed contains a `yield`, making it a generator.
+    pub(crate) yields: bool,
+    /// The function and class bodies being parsed, innermost last, with the names they bind.
+    pub(crate) bindings: Vec<Bindings>,
 }
 
 impl TokenStream {
+    /// Leave `assert` statements out of what's parsed from now on, like `python -O`.
+    pub(crate) fn set_strip_asserts(&mut self, strip: bool) {
+        self.strip_asserts = strip;
+    }
+
+    /// Whether `assert` statements are being left out.
+    #[must_use]
+    pub(crate) const fn strips_asserts(&self) -> bool {
+        self.strip_asserts
+    }
+
+    /// The source text covered by `span`.
+    #[must_use]
+    pub(crate) fn text(&self, span: Span) -> String {
+        (span.start.row..=span.end.row)
+            .filter_map(|row| {
+                let line = self.lines.get(row - 1)?;
+                let start = if row == span.start.row {
+                    span.start.col - 1
+                } else {
+                    0
+                };
+                let end = if row == span.end.row {
+                    span.end.col
+                } else {
+                    usize::MAX
+                };
+                Some(line.chars().take(end).skip(start).collect::<String>())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The comments in the source, in order.
+    #[must_use]
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// The tokens, from the beginning.
+    #[must_use]
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// The whitespace and comments after the last token, if lexed losslessly.
+    #[must_use]
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        &self.trailing
+    }
+
+    /// The source text of `tkn`, if lexed losslessly. `Indent` and `Dedent` have none, and neither
+    /// does the `NewLine` that ends a file without a line break.
+    #[must_use]
+    pub fn token_text(&self, tkn: &Token) -> &str {
+        &self.source[self.byte_range(tkn)]
+    }
+
+    /// The source the stream was lexed from, put back together from its tokens and trivia, if lexed
+    /// losslessly and not yet parsed.
+    #[must_use]
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        for tkn in &self.tokens {
+            for trivia in &tkn.trivia {
+                out += &trivia.text;
+            }
+            out += self.token_text(tkn);
+        }
+        for trivia in &self.trailing {
+            out += &trivia.text;
+        }
+        out
+    }
+
+    /// The byte offset of `loc` in the source.
+    fn offset(&self, loc: Loc) -> usize {
+        let Some(&line) = self.line_starts.get(loc.row - 1) else {
+            return self.source.len();
+        };
+        self.source[line..]
+            .char_indices()
+            .nth(loc.col - 1)
+            .map_or(self.source.len(), |(i, _)| line + i)
+    }
+
+    /// The location of the byte at `offset` in the source.
+    fn loc(&self, offset: usize) -> Loc {
+        let row = self.line_starts.partition_point(|&start| start <= offset);
+        Loc {
+            row,
+            col: self.source[self.line_starts[row - 1]..offset]
+                .chars()
+                .count()
+                + 1,
+        }
+    }
+
+    /// The bytes of the source `tkn` was lexed from.
+    fn byte_range(&self, tkn: &Token) -> Range<usize> {
+        let start = self.offset(tkn.span.start);
+        let rest = &self.source[start..];
+        let len = match tkn.kind {
+            TokenKind::Indent | TokenKind::Dedent => 0,
+            TokenKind::NewLine if rest.starts_with("\r\n") => 2,
+            TokenKind::NewLine if rest.starts_with('\n') => 1,
+            TokenKind::NewLine => 0,
+            _ => {
+                let end = self.offset(tkn.span.end);
+                end - start + self.source[end..].chars().next().map_or(0, char::len_utf8)
+            }
+        };
+        start..start + len
+    }
+
+    /// Split the source between two tokens into trivia.
+    fn trivia(&self, range: Range<usize>) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
+        let mut pos = range.start;
+        while pos < range.end {
+            let rest = &self.source[pos..range.end];
+            let (kind, len) = if rest.starts_with("\r\n") {
+                (TriviaKind::NewLine, 2)
+            } else if rest.starts_with('\n') {
+                (TriviaKind::NewLine, 1)
+            } else {
+                // up to the line break, not including the `\r` of a `\r\n`
+                let end = rest.find('\n').unwrap_or(rest.len());
+                let end = end - usize::from(rest[..end].ends_with('\r') && end < rest.len());
+                if rest.starts_with('#') {
+                    (TriviaKind::Comment, end)
+                } else {
+                    (TriviaKind::Whitespace, rest[..end].find('#').unwrap_or(end))
+                }
+            };
+            let last = rest[..len].chars().next_back().map_or(0, char::len_utf8);
+            trivia.push(Trivia {
+                kind,
+                text: rest[..len].to_string(),
+                span: Span {
+                    start: self.loc(pos),
+                    end: self.loc(pos + len - last),
+                },
+            });
+            pos += len;
+        }
+        trivia
+    }
+
     /// Append to the token.
-    pub fn append(&mut self, tkn: Token) {
+    pub(crate) fn append(&mut self, tkn: Token) {
         self.tokens.push(tkn);
     }
 
     /// Reset to the beginning of the tokens.
-    pub fn reset(&mut self) {
+    pub(crate) fn reset(&mut self) {
         self.index = 0;
     }
 
     /// Return the current token.
     #[must_use]
-    pub fn current(&self) -> Option<&Token> {
+    pub(crate) fn current(&self) -> Option<&Token> {
         self.tokens.get(self.index)
     }
 
-    pub fn current_or(&self) -> Result<&Token> {
-        self.current().ok_or(Error {
-            span: Span {
+    pub(crate) fn current_or(&self) -> Result<&Token> {
+        self.current().ok_or(Error::new(
+            Kind::UnexpectedEof,
+            Span {
                 start: self.eof(),
                 end: self.eof(),
             },
-            kind: Kind::UnexpectedEof,
-        })
+        ))
     }
 
     // Get the location of the end of file.
@@ -106,31 +397,37 @@ impl TokenStream {
             .map_or(Loc { row: 1, col: 1 }, |t| t.span.end)
     }
 
+    /// Return the token after the current one.
+    #[must_use]
+    pub(crate) fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index + 1)
+    }
+
     /// Advance the token stream.
-    pub fn advance(&mut self) {
+    pub(crate) fn advance(&mut self) {
         assert!(self.index < self.tokens.len());
         self.index += 1;
     }
 
     /// Eat a token of `TokenKind`.
-    pub fn eat(&mut self, target: &TokenKind) -> Result<()> {
+    pub(crate) fn eat(&mut self, target: &TokenKind) -> Result<()> {
         let tkn = self.current_or()?;
         if &tkn.kind == target {
             self.advance();
             Ok(())
         } else {
-            Err(Error {
-                span: tkn.span,
-                kind: Kind::WrongChar {
-                    expected: target.clone(),
-                    got: tkn.kind.clone(),
+            Err(Error::new(
+                Kind::WrongChar {
+                    expected: Box::new(target.clone()),
+                    got: Box::new(tkn.kind.clone()),
                 },
-            })
+                tkn.span,
+            ))
         }
     }
 
     /// Take the current token from the tokenizer.
-    pub fn take(&mut self) -> Token {
+    pub(crate) fn take(&mut self) -> Token {
         self.tokens.remove(self.index)
     }
 }
@@ -138,14 +435,26 @@ impl TokenStream {
 pub struct Tokenizer<'a> {
     loc: Loc,
     source: &'a str,
+    /// The widths of the enclosing indented blocks, innermost last.
+    indents: Vec<usize>,
+    /// Tokens that have been produced but not yet returned.
+    pending: VecDeque<Token>,
+    /// The comments skipped so far.
+    comments: Vec<Comment>,
+    /// How many brackets are open; line breaks inside them don't end the statement.
+    brackets: usize,
 }
 
 impl<'a> Tokenizer<'a> {
     /// Create a new tokenizer from the source string.
-    pub const fn new(source: &'a str) -> Self {
+    pub fn new(source: &'a str) -> Self {
         Self {
             loc: Loc { row: 1, col: 1 },
             source,
+            indents: vec![0],
+            pending: VecDeque::new(),
+            comments: Vec::new(),
+            brackets: 0,
         }
     }
 
@@ -177,7 +486,7 @@ impl<'a> Tokenizer<'a> {
             self.loc.row -= 1;
             self.loc.col = 1;
             // go to the end of the line
-            while self.curr_char().map_or(false, |c| c != '\n') {
+            while self.curr_char().is_some_and(|c| c != '\n') {
                 self.advance();
             }
         } else {
@@ -185,6 +494,48 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Whether the source carries on with `text` from the current character, on the same line.
+    fn at(&self, text: &str) -> bool {
+        self.source
+            .lines()
+            .nth(self.loc.row - 1)
+            .is_some_and(|line| {
+                line.chars()
+                    .skip(self.loc.col - 1)
+                    .take(text.chars().count())
+                    .eq(text.chars())
+            })
+    }
+
+    /// Parse a `"""` string, which can hold `"`s as well as line breaks.
+    fn triple_quoted(&mut self) -> Result<Token> {
+        let start = self.loc;
+        let mut end = self.loc;
+        for _ in 0..3 {
+            end = self.loc;
+            self.advance();
+        }
+        let mut text = String::new();
+        while !self.at("\"\"\"") {
+            let Some(c) = self.curr_char() else {
+                // the string runs to the end of the file
+                return Err(Error::new(Kind::UnexpectedEof, Span { start, end }));
+            };
+            text.push(c);
+            end = self.loc;
+            self.advance();
+        }
+        for _ in 0..3 {
+            end = self.loc;
+            self.advance();
+        }
+        Ok(Token {
+            kind: TokenKind::Str(text),
+            span: Span { start, end },
+            trivia: Vec::new(),
+        })
+    }
+
     /// Parse a token consisting of a single character.
     fn single_char(&mut self, kind: TokenKind) -> Token {
         let token = Token {
@@ -193,6 +544,7 @@ impl<'a> Tokenizer<'a> {
                 start: self.loc,
                 end: self.loc,
             },
+            trivia: Vec::new(),
         };
         self.advance();
         token
@@ -210,12 +562,13 @@ impl<'a> Tokenizer<'a> {
 
         if self.curr_char() == Some(next) {
             self.advance();
-            Ok(Token { kind, span })
-        } else {
-            Err(Error {
-                kind: Kind::Tokenization,
+            Ok(Token {
+                kind,
                 span,
+                trivia: Vec::new(),
             })
+        } else {
+            Err(Error::new(Kind::Tokenization, span))
         }
     }
 
@@ -243,9 +596,80 @@ impl<'a> Tokenizer<'a> {
         Token {
             kind: finally(init),
             span: Span { start, end },
+            trivia: Vec::new(),
         }
     }
 
+    /// Handle the indentation at the start of a line, queueing `Indent` and `Dedent` tokens.
+    ///
+    /// Lines that only hold a comment are skipped entirely, and blank lines don't change the
+    /// indentation. At the end of the file, every open block is closed.
+    fn indentation(&mut self) -> Result<()> {
+        let width = loop {
+            let mut width = 0;
+            loop {
+                match self.curr_char() {
+                    Some(' ') => width += 1,
+                    // tabs go to the next multiple of eight, as in Python
+                    Some('\t') => width += 8 - width % 8,
+                    _ => break,
+                }
+                self.advance();
+            }
+            match self.curr_char() {
+                Some('\n') => return Ok(()),
+                Some('#') => {
+                    self.comment();
+                    self.advance();
+                }
+                Some(_) => break width,
+                None => break 0,
+            }
+        };
+
+        let span = Span {
+            start: self.loc,
+            end: self.loc,
+        };
+        let current = *self.indents.last().expect("there is always a top level");
+        if width > current {
+            self.indents.push(width);
+            self.pending.push_back(Token {
+                kind: TokenKind::Indent,
+                span,
+                trivia: Vec::new(),
+            });
+        }
+        while width < *self.indents.last().expect("there is always a top level") {
+            self.indents.pop();
+            self.pending.push_back(Token {
+                kind: TokenKind::Dedent,
+                span,
+                trivia: Vec::new(),
+            });
+        }
+        if width != *self.indents.last().expect("there is always a top level") {
+            return Err(Error::new(Kind::Indentation, span));
+        }
+        Ok(())
+    }
+
+    /// Skip the comment starting at the current character, up to the end of the line.
+    fn comment(&mut self) {
+        let start = self.loc;
+        let mut text = String::new();
+        let mut end = self.loc;
+        while let Some(c) = self.curr_char().filter(|&c| c != '\n') {
+            text.push(c);
+            end = self.loc;
+            self.advance();
+        }
+        self.comments.push(Comment {
+            text,
+            span: Span { start, end },
+        });
+    }
+
     fn next_or(&mut self, next: char, kind: TokenKind, fallback: TokenKind) -> Result<Token> {
         self.expect_next(kind, next).or_else(|_| {
             self.backup();
@@ -253,6 +677,28 @@ impl<'a> Tokenizer<'a> {
         })
     }
 
+    /// Extend the operator `token` just parsed to its augmented assignment if `=` comes next.
+    fn or_augmented(&mut self, token: Token) -> Token {
+        let TokenKind::Op(op) = token.kind else {
+            return token;
+        };
+        match op.augmented() {
+            Some(aug) if self.curr_char() == Some('=') => {
+                let end = self.loc;
+                self.advance();
+                Token {
+                    kind: TokenKind::Op(aug),
+                    span: Span {
+                        start: token.span.start,
+                        end,
+                    },
+                    trivia: Vec::new(),
+                }
+            }
+            _ => token,
+        }
+    }
+
     /// Parse the next token from the string.
     fn next_token(&mut self) -> Result<Option<Token>> {
         #[allow(clippy::enum_glob_use)]
@@ -260,6 +706,13 @@ impl<'a> Tokenizer<'a> {
         #[allow(clippy::enum_glob_use)]
         use TokenKind::*;
 
+        if self.pending.is_empty() && self.loc.col == 1 && self.brackets == 0 {
+            self.indentation()?;
+        }
+        if let Some(tkn) = self.pending.pop_front() {
+            return Ok(Some(tkn));
+        }
+
         // skip whitespace that we don't care about
         while self.curr_char() == Some(' ') || self.curr_char() == Some('\t') {
             self.advance();
@@ -267,32 +720,81 @@ impl<'a> Tokenizer<'a> {
 
         Ok(if let Some(c) = self.curr_char() {
             Some(match c {
+                '\n' if self.brackets > 0 => {
+                    // the line goes on
+                    self.advance();
+                    return self.next_token();
+                }
                 '\n' => self.single_char(NewLine),
-                '(' => self.single_char(LParen),
-                ')' => self.single_char(RParen),
+                '(' | '[' | '{' => {
+                    self.brackets += 1;
+                    self.single_char(match c {
+                        '(' => LParen,
+                        '[' => LBracket,
+                        _ => LBrace,
+                    })
+                }
+                ')' | ']' | '}' => {
+                    self.brackets = self.brackets.saturating_sub(1);
+                    self.single_char(match c {
+                        ')' => RParen,
+                        ']' => RBracket,
+                        _ => RBrace,
+                    })
+                }
                 ',' => self.single_char(Comma),
-                '+' => self.next_or('=', Op(AddEq), Op(Plus))?,
-                '-' => self.single_char(Op(Minus)),
-                '*' => self.next_or('*', Op(Expt), Op(Times))?,
-                '/' => self.expect_next(Op(Div), '/')?,
-                '%' => self.single_char(Op(Mod)),
-                '=' => self.single_char(Op(Asgn)),
+                ':' => self.next_or('=', Op(ColonEq), Colon)?,
+                '.' => self.single_char(Dot),
+                '+' => {
+                    let token = self.single_char(Op(Plus));
+                    self.or_augmented(token)
+                }
+                '-' => {
+                    let token = self.single_char(Op(Minus));
+                    self.or_augmented(token)
+                }
+                '*' => {
+                    let token = self.next_or('*', Op(Expt), Op(Times))?;
+                    self.or_augmented(token)
+                }
+                '/' => {
+                    let token = self.expect_next(Op(Div), '/')?;
+                    self.or_augmented(token)
+                }
+                '%' => {
+                    let token = self.single_char(Op(Mod));
+                    self.or_augmented(token)
+                }
+                '=' => self.next_or('=', Op(Eq), Op(Asgn))?,
+                '!' => self.expect_next(Op(NotEq), '=')?,
+                '<' => self.next_or('=', Op(LtE), Op(Lt))?,
+                '>' => self.next_or('=', Op(GtE), Op(Gt))?,
                 '#' => {
-                    self.loc.row += 1;
-                    self.loc.col = 1;
+                    // the end of the line still ends the statement
+                    self.comment();
                     return self.next_token();
                 }
-                '0'..='9' => self.parse_while(
-                    0,
-                    |n, c| {
-                        c.to_digit(10).map_or(false, |d| {
-                            *n *= 10;
-                            *n += d;
-                            true
-                        })
-                    },
-                    Number,
-                ),
+                '0'..='9' => {
+                    let mut overflowed = false;
+                    let token = self.parse_while(
+                        0,
+                        |n: &mut u32, c| {
+                            c.to_digit(10).is_some_and(|d| {
+                                match n.checked_mul(10).and_then(|n| n.checked_add(d)) {
+                                    Some(next) => *n = next,
+                                    // keep going, so the error spans the whole literal
+                                    None => overflowed = true,
+                                }
+                                true
+                            })
+                        },
+                        Number,
+                    );
+                    if overflowed {
+                        return Err(Error::new(Kind::Tokenization, token.span));
+                    }
+                    token
+                }
                 'a'..='z' | 'A'..='Z' | '_' => self.parse_while(
                     String::new(),
                     |s, c| {
@@ -305,9 +807,10 @@ impl<'a> Tokenizer<'a> {
                     },
                     Ident,
                 ),
+                '"' if self.at("\"\"\"") => self.triple_quoted()?,
                 '"' => {
                     let mut seen_even_quotes = true;
-                    self.parse_while(
+                    let token = self.parse_while(
                         String::new(),
                         |s, c| {
                             if c == '"' {
@@ -325,9 +828,22 @@ impl<'a> Tokenizer<'a> {
                             }
                         },
                         Str,
-                    )
+                    );
+                    if !seen_even_quotes {
+                        // the string runs to the end of the file
+                        return Err(Error::new(Kind::UnexpectedEof, token.span));
+                    }
+                    token
+                }
+                _ => {
+                    let span = Span {
+                        start: self.loc,
+                        end: self.loc,
+                    };
+                    // skip the character, so that lexing can carry on past it
+                    self.advance();
+                    return Err(Error::new(Kind::Tokenization, span));
                 }
-                _ => todo!(),
             })
         } else {
             None
@@ -337,14 +853,55 @@ impl<'a> Tokenizer<'a> {
     /// Lex source into a `TokenStream`.
     pub fn lex(source: &'a str) -> Result<TokenStream> {
         let mut tokenizer = Self::new(source);
-        let mut tokens = TokenStream::default();
+        let mut tokens = TokenStream {
+            lines: source.lines().map(String::from).collect(),
+            ..TokenStream::default()
+        };
 
         while let Some(tkn) = tokenizer.next_token()? {
             tokens.append(tkn);
         }
+        tokens.comments = tokenizer.comments;
 
         Ok(tokens)
     }
+
+    /// Lex all of source, carrying on past errors instead of stopping at the first one. Each error
+    /// is in order among the tokens.
+    pub fn lex_recovering(source: &'a str) -> Vec<Result<Token>> {
+        let mut tokenizer = Self::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            match tokenizer.next_token() {
+                Ok(Some(tkn)) => tokens.push(Ok(tkn)),
+                Ok(None) => break,
+                Err(e) => tokens.push(Err(e)),
+            }
+        }
+        tokens
+    }
+
+    /// Lex source into a `TokenStream` that also keeps what the tokens leave out: each token's
+    /// `trivia` holds the whitespace, comments and line breaks before it, so that the tokens and
+    /// trivia together are the source, byte for byte.
+    pub fn lex_lossless(source: &'a str) -> Result<TokenStream> {
+        let mut stream = Self::lex(source)?;
+        stream.source = source.to_string();
+        stream.line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        let mut tokens = std::mem::take(&mut stream.tokens);
+        let mut pos = 0;
+        for tkn in &mut tokens {
+            let range = stream.byte_range(tkn);
+            tkn.trivia = stream.trivia(pos..range.start);
+            pos = range.end;
+        }
+        stream.tokens = tokens;
+        stream.trailing = stream.trivia(pos..source.len());
+        Ok(stream)
+    }
 }
 
 #[cfg(test)]
@@ -371,8 +928,13 @@ mod tests {
         }
 
         ntt!(comma: "," => Comma);
+        ntt!(dot: ".x" => Dot);
         ntt!(l_paren: "(" => LParen);
         ntt!(r_paren: ")" => RParen);
+        ntt!(l_bracket: "[" => LBracket);
+        ntt!(r_bracket: "]" => RBracket);
+        ntt!(l_brace: "{" => LBrace);
+        ntt!(r_brace: "}" => RBrace);
         ntt!(newline: "\n" => NewLine);
         ntt!(plus: "+" => Op(Plus));
         ntt!(minus: "-" => Op(Minus));
@@ -381,12 +943,36 @@ mod tests {
         ntt!(modulus: "%" => Op(Mod));
         ntt!(eq: "=" => Op(Asgn));
         ntt!(add_eq: "+=" => Op(AddEq));
+        ntt!(sub_eq: "-=" => Op(SubEq));
+        ntt!(mul_eq: "*=" => Op(MulEq));
+        ntt!(div_eq: "//=" => Op(DivEq));
+        ntt!(mod_eq: "%=" => Op(ModEq));
+        ntt!(expt_eq: "**=" => Op(ExptEq));
+        ntt!(expt_before_eq: "** =" => Op(Expt));
+        ntt!(colon_eq: ":=" => Op(ColonEq));
+        ntt!(colon_before_eq: ": =" => Colon);
+        ntt!(eq_eq: "==" => Op(Eq));
+        ntt!(not_eq: "!=" => Op(NotEq));
+        ntt!(lt: "<" => Op(Lt));
+        ntt!(lt_e: "<=" => Op(LtE));
+        ntt!(gt: "> 1" => Op(Gt));
+        ntt!(gt_e: ">=" => Op(GtE));
+        ntt!(asgn_before_eq: "= =" => Op(Asgn));
         ntt!(comment: "#\nx" => Ident("x".to_string()));
         ntt!(num: "1234" => Number(1234));
         ntt!(ident: "abcd" => Ident("abcd".to_string()));
         ntt!(ident_underscore: "_abcd" => Ident("_abcd".to_string()));
         ntt!(ident_numbers: "a_124_Bb41" => Ident("a_124_Bb41".to_string()));
         ntt!(str1: "\"a b c\"" => Str("a b c".to_string()));
+        ntt!(empty_str: "\"\" x" => Str(String::new()));
+        ntt!(triple_quoted: "\"\"\"say \"hi\"\n\"\"\"" => Str("say \"hi\"\n".to_string()));
+        ntt!(triple_quoted_quote_first: "\"\"\"\"a\"\"\"" => Str("\"a".to_string()));
+
+        #[test]
+        fn unknown_char() {
+            let err = Tokenizer::new("$").next_token().unwrap_err();
+            assert_eq!(err.kind, Kind::Tokenization);
+        }
     }
 
     mod lex {
@@ -411,6 +997,7 @@ mod tests {
                             col: $ecol,
                         },
                     },
+                    trivia: Vec::new(),
                 }
             };
             ($srow:expr,$scol:expr => $kind:expr) => {tok!($srow,$scol;$srow,$scol => $kind)}
@@ -427,6 +1014,13 @@ mod tests {
             };
         }
 
+        lt! {expt_eq: "x **= 2" =>
+            tok!(1,1 => Ident("x".to_string())),
+            tok!(1,3;1,5 => Op(ExptEq)),
+            tok!(1,7 => Number(2)),
+            tok!(1,8 => NewLine)
+        }
+
         lt! {expt: "2 ** 3" =>
             tok!(1,1 => Number(2)),
             tok!(1,3;1,4 => Op(Expt)),
@@ -442,6 +1036,15 @@ mod tests {
             tok!(1,9 => NewLine)
         }
 
+        lt! {method_call: "self.f()" =>
+            tok!(1,1;1,4 => Ident("self".to_string())),
+            tok!(1,5 => Dot),
+            tok!(1,6 => Ident("f".to_string())),
+            tok!(1,7 => LParen),
+            tok!(1,8 => RParen),
+            tok!(1,9 => NewLine)
+        }
+
         lt! {mult: "a_b*y" =>
             tok!(1,1;1,3 => Ident("a_b".to_string())),
             tok!(1,4 => Op(Times)),
@@ -481,6 +1084,189 @@ mod tests {
             tok!(2,9 => NewLine)
         }
 
-        // TODO: whitespace
+        lt! {block: "try:\n  x\ny" =>
+            tok!(1,1;1,3 => Ident("try".to_string())),
+            tok!(1,4 => Colon),
+            tok!(1,5 => NewLine),
+            tok!(2,3 => Indent),
+            tok!(2,3 => Ident("x".to_string())),
+            tok!(2,4 => NewLine),
+            tok!(3,1 => Dedent),
+            tok!(3,1 => Ident("y".to_string())),
+            tok!(3,2 => NewLine)
+        }
+
+        lt! {dedent_at_eof: "a:\n b\n\n   # c" =>
+            tok!(1,1 => Ident("a".to_string())),
+            tok!(1,2 => Colon),
+            tok!(1,3 => NewLine),
+            tok!(2,2 => Indent),
+            tok!(2,2 => Ident("b".to_string())),
+            tok!(2,3 => NewLine),
+            tok!(3,1 => NewLine),
+            tok!(5,1 => Dedent)
+        }
+
+        lt! {trailing_comment: "x # hi\ny" =>
+            tok!(1,1 => Ident("x".to_string())),
+            tok!(1,7 => NewLine),
+            tok!(2,1 => Ident("y".to_string())),
+            tok!(2,2 => NewLine)
+        }
+
+        lt! {lines_join_inside_brackets: "f(x,\n      [y]\n)\nz" =>
+            tok!(1,1 => Ident("f".to_string())),
+            tok!(1,2 => LParen),
+            tok!(1,3 => Ident("x".to_string())),
+            tok!(1,4 => Comma),
+            tok!(2,7 => LBracket),
+            tok!(2,8 => Ident("y".to_string())),
+            tok!(2,9 => RBracket),
+            tok!(3,1 => RParen),
+            tok!(3,2 => NewLine),
+            tok!(4,1 => Ident("z".to_string())),
+            tok!(4,2 => NewLine)
+        }
+
+        #[test]
+        fn number_too_large() {
+            let err = Tokenizer::lex("x = 99999999999").unwrap_err();
+            assert_eq!(err.kind, Kind::Tokenization);
+            assert_eq!(
+                err.span,
+                Span {
+                    start: Loc { row: 1, col: 5 },
+                    end: Loc { row: 1, col: 15 },
+                }
+            );
+            assert_eq!(
+                Tokenizer::lex("4294967295").unwrap().tokens[0].kind,
+                Number(u32::MAX)
+            );
+        }
+
+        #[test]
+        fn unterminated_string() {
+            let err = Tokenizer::lex("x = \"abc\ny").unwrap_err();
+            assert_eq!(err.kind, Kind::UnexpectedEof);
+            let err = Tokenizer::lex("x = \"\"\"abc\"\"\ny").unwrap_err();
+            assert_eq!(err.kind, Kind::UnexpectedEof);
+            assert_eq!(
+                err.span,
+                Span {
+                    start: Loc { row: 1, col: 5 },
+                    end: Loc { row: 2, col: 2 },
+                }
+            );
+        }
+
+        #[test]
+        fn triple_quoted_span() {
+            let tokens = Tokenizer::lex("s = \"\"\"a\nb\"\"\"\nt").unwrap();
+            assert_eq!(tokens.tokens[2].kind, Str("a\nb".to_string()));
+            assert_eq!(
+                tokens.tokens[2].span,
+                Span {
+                    start: Loc { row: 1, col: 5 },
+                    end: Loc { row: 2, col: 4 },
+                }
+            );
+            assert_eq!(tokens.tokens[3].kind, NewLine);
+            assert_eq!(tokens.tokens[4].kind, Ident("t".to_string()));
+        }
+
+        #[test]
+        fn comments() {
+            let tokens = Tokenizer::lex("# a\nx # b\n  # c").unwrap();
+            let at = |row, start, end| Span {
+                start: Loc { row, col: start },
+                end: Loc { row, col: end },
+            };
+            assert_eq!(
+                tokens
+                    .comments()
+                    .iter()
+                    .map(|c| (c.text.as_str(), c.span))
+                    .collect::<Vec<_>>(),
+                [
+                    ("# a", at(1, 1, 3)),
+                    ("# b", at(2, 3, 5)),
+                    ("# c", at(3, 3, 5))
+                ]
+            );
+        }
+
+        #[test]
+        fn bad_dedent() {
+            let err = Tokenizer::lex("a:\n    b\n  c").unwrap_err();
+            assert_eq!(err.kind, Kind::Indentation);
+        }
+
+        #[test]
+        fn text() {
+            let tokens = Tokenizer::lex("x = 1\nassert (x +\n  2), \"é\"").unwrap();
+            let span = |start: (usize, usize), end: (usize, usize)| Span {
+                start: Loc {
+                    row: start.0,
+                    col: start.1,
+                },
+                end: Loc {
+                    row: end.0,
+                    col: end.1,
+                },
+            };
+            assert_eq!(tokens.text(span((2, 9), (3, 3))), "x +\n  2");
+            assert_eq!(tokens.text(span((3, 7), (3, 9))), "\"é\"");
+        }
+
+        #[test]
+        fn lossless_round_trip() {
+            for source in [
+                "",
+                "x",
+                "# only a comment",
+                "def f(a,  b):\n\t# tab\n\n    return a**b  # power\n\n\n# the end\n",
+                "if x:\r\n    y = \"é\"   # crlf\r\n\r\n  \r\nz\r\n",
+                "while x:\n  if y:\n    pass\n      # deeper\n# shallower\n",
+                "s = \"\"\"a \"quote\"\n  # not a comment\n\"\"\"  # a comment\n",
+            ] {
+                let tokens = Tokenizer::lex_lossless(source).unwrap();
+                assert_eq!(tokens.to_source(), source);
+                let kinds = |t: &TokenStream| t.tokens().iter().map(|t| t.kind.clone()).collect();
+                let plain: Vec<_> = kinds(&Tokenizer::lex(source).unwrap());
+                assert_eq!(kinds(&tokens), plain);
+            }
+        }
+
+        #[test]
+        fn trivia() {
+            let tokens = Tokenizer::lex_lossless("x  # hi\n# bye\n").unwrap();
+            let at = |row, start, end| Span {
+                start: Loc { row, col: start },
+                end: Loc { row, col: end },
+            };
+            let trivia = |kind, text: &str, span| Trivia {
+                kind,
+                text: text.to_string(),
+                span,
+            };
+            let newline = &tokens.tokens()[1];
+            assert_eq!(newline.kind, NewLine);
+            assert_eq!(tokens.token_text(newline), "\n");
+            assert_eq!(
+                newline.trivia,
+                [
+                    trivia(TriviaKind::Whitespace, "  ", at(1, 2, 3)),
+                    trivia(TriviaKind::Comment, "# hi", at(1, 4, 7)),
+                ]
+            );
+            assert_eq!(
+                tokens.trailing_trivia(),
+                [
+                    trivia(TriviaKind::Comment, "# bye", at(2, 1, 5)),
+                    trivia(TriviaKind::NewLine, "\n", at(2, 6, 6)),
+                ]
+            );
+        }
     }
 }
@@ -0,0 +1,132 @@
+//! Listing the tokens lexed from a program, for debugging the lexer and the parser.
+use super::{Token, TokenKind, TokenStream};
+use crate::ast::write_str;
+use crate::error::Result;
+use crate::Span;
+
+/// The tokens lexed from `source`, one per line as `row:col-row:col  KIND  lexeme`, with each error
+/// on a line of its own where it happened, as `row:col-row:col  ERROR  message`.
+#[must_use]
+pub fn list(source: &str, tokens: &[Result<Token>]) -> String {
+    let stream = lines(source);
+    let mut out = String::new();
+    for entry in tokens {
+        let (span, kind, text) = match entry {
+            Ok(tkn) => (tkn.span, tkn.kind.name(), lexeme(&stream, tkn)),
+            Err(e) => (e.span, "ERROR", e.kind.to_string()),
+        };
+        let span = format!(
+            "{}:{}-{}:{}",
+            span.start.row, span.start.col, span.end.row, span.end.col
+        );
+        let line = format!("{:<11}  {:<8}  {}", span, kind, text.replace('\n', "\\n"));
+        out += line.trim_end();
+        out.push('\n');
+    }
+    out
+}
+
+/// The same as `list`, as a JSON array: a token is `{"kind", "span", "text"}`, and an error is
+/// `{"error", "span"}`, with spans as in the AST schema.
+#[must_use]
+pub fn list_json(source: &str, tokens: &[Result<Token>]) -> String {
+    let stream = lines(source);
+    let mut out = String::from("[");
+    for (i, entry) in tokens.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        match entry {
+            Ok(tkn) => {
+                out += "{\"kind\":";
+                write_str(tkn.kind.name(), &mut out);
+                out += ",\"span\":";
+                write_span(tkn.span, &mut out);
+                out += ",\"text\":";
+                write_str(&lexeme(&stream, tkn), &mut out);
+            }
+            Err(e) => {
+                out += "{\"error\":";
+                write_str(&e.kind.to_string(), &mut out);
+                out += ",\"span\":";
+                write_span(e.span, &mut out);
+            }
+        }
+        out.push('}');
+    }
+    out + "]"
+}
+
+/// A stream holding just the lines of `source`, to take the tokens' text from.
+fn lines(source: &str) -> TokenStream {
+    TokenStream {
+        lines: source.lines().map(String::from).collect(),
+        ..TokenStream::default()
+    }
+}
+
+/// The source text of `tkn`. Line breaks, indents and dedents have none.
+fn lexeme(stream: &TokenStream, tkn: &Token) -> String {
+    match tkn.kind {
+        TokenKind::NewLine | TokenKind::Indent | TokenKind::Dedent => String::new(),
+        _ => stream.text(tkn.span),
+    }
+}
+
+fn write_span(span: Span, out: &mut String) {
+    *out += &format!(
+        "{{\"start\":{{\"row\":{},\"col\":{}}},\"end\":{{\"row\":{},\"col\":{}}}}}",
+        span.start.row, span.start.col, span.end.row, span.end.col
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    #[test]
+    fn text() {
+        let source = "if x:\n    y = \"a\" $ 10\n";
+        assert_eq!(
+            list(source, &Tokenizer::lex_recovering(source)),
+            "1:1-1:2      Ident     if\n\
+             1:4-1:4      Ident     x\n\
+             1:5-1:5      Colon     :\n\
+             1:6-1:6      NewLine\n\
+             2:5-2:5      Indent\n\
+             2:5-2:5      Ident     y\n\
+             2:7-2:7      Op        =\n\
+             2:9-2:11     Str       \"a\"\n\
+             2:13-2:13    ERROR     tokenization failed\n\
+             2:15-2:16    Number    10\n\
+             2:17-2:17    NewLine\n\
+             3:1-3:1      Dedent\n"
+        );
+    }
+
+    #[test]
+    fn errors_do_not_stop_lexing() {
+        let source = "a ! b\n  c\n";
+        let tokens = Tokenizer::lex_recovering(source);
+        let kinds: Vec<_> = tokens
+            .iter()
+            .map(|t| t.as_ref().map_or("ERROR", |t| t.kind.name()))
+            .collect();
+        assert_eq!(
+            kinds,
+            ["Ident", "ERROR", "Ident", "NewLine", "Indent", "Ident", "NewLine", "Dedent"]
+        );
+    }
+
+    #[test]
+    fn json() {
+        let source = "x $";
+        assert_eq!(
+            list_json(source, &Tokenizer::lex_recovering(source)),
+            "[{\"kind\":\"Ident\",\"span\":{\"start\":{\"row\":1,\"col\":1},\"end\":{\"row\":1,\"col\":1}},\"text\":\"x\"},\
+             {\"error\":\"tokenization failed\",\"span\":{\"start\":{\"row\":1,\"col\":3},\"end\":{\"row\":1,\"col\":3}}},\
+             {\"kind\":\"NewLine\",\"span\":{\"start\":{\"row\":1,\"col\":4},\"end\":{\"row\":1,\"col\":4}},\"text\":\"\"}]"
+        );
+    }
+}
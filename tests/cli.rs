@@ -0,0 +1,87 @@
+//! Running the `slpy-rust` binary.
+use std::io::Write;
+use std::process::{Child, Command, Output, Stdio};
+
+/// Start the binary with `args`, with its standard streams piped.
+fn spawn(args: &[&str]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_slpy-rust"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+/// Run the binary with `args`, giving it `stdin`, until it exits.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = spawn(args);
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+#[test]
+fn runs_code_given_with_c() {
+    let out = run(&["-c", "print(argv)", "a", "b"], "");
+    assert!(out.status.success());
+    assert_eq!(stdout(&out), "['<string>', 'a', 'b']\n");
+}
+
+#[test]
+fn reads_the_program_from_stdin() {
+    let out = run(&["-"], "print(1 + 2)\n");
+    assert!(out.status.success());
+    assert_eq!(stdout(&out), "3\n");
+
+    let out = run(&["run", "-", "x"], "print(argv)\n");
+    assert!(out.status.success());
+    assert_eq!(stdout(&out), "['<stdin>', 'x']\n");
+}
+
+#[test]
+fn exit_codes() {
+    let missing = "/nonexistent/program.slpy";
+    for (args, stdin, code) in [
+        (&["-c", "print(y)"][..], "", 1),
+        (&["run", "--no-such-flag"], "", 2),
+        (&["-c", "x = ("], "", 65),
+        (&["check", "-"], "x = (\n", 65),
+        (&["compile", "-"], "x = (\n", 65),
+        (&["run", missing], "", 66),
+        (&["check", missing], "", 66),
+        (&["compile", "-"], "x = 1\n", 69),
+    ] {
+        let out = run(args, stdin);
+        assert_eq!(out.status.code(), Some(code), "{:?}", args);
+        assert!(!out.stderr.is_empty(), "{:?}", args);
+    }
+}
+
+#[test]
+fn closed_stdout_is_not_an_error() {
+    for args in [
+        &["dump", "-f", "json", "-"][..],
+        &["dump", "--tokens", "-"],
+        &["fmt", "-"],
+    ] {
+        let mut child = spawn(args);
+        // like piping into `head`, but the reader goes away before reading anything
+        drop(child.stdout.take());
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"x = [1, 2]\nprint(x)\n").unwrap();
+        drop(stdin);
+        let out = child.wait_with_output().unwrap();
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(out.status.success(), "{:?}: {}", args, stderr);
+        assert_eq!(stderr, "", "{:?}", args);
+    }
+}